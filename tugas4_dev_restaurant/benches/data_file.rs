@@ -0,0 +1,48 @@
+// Rough timings of writing and reading a generated data file, without a
+// benchmarking crate: `cargo bench`, or `cargo bench -- 500000` for another
+// number of items.
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use tugas4_dev_restaurant::storage::{format_food, load_foods, parse_food, parse_foods, write_foods, CsvOptions};
+use tugas4_dev_restaurant::{Food, Foods};
+
+const ITEMS: usize = 100_000;
+
+fn time<T>(what: &str, items: usize, run: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let result = black_box(run());
+    let elapsed = start.elapsed();
+    let per_item = elapsed.checked_div(items as u32).unwrap_or(Duration::ZERO);
+    println!("{:<28} {:>10.1?} {:>10.1?}/item", what, elapsed, per_item);
+    result
+}
+
+fn main() {
+    let items = std::env::args().skip(1).find_map(|arg| arg.parse().ok()).unwrap_or(ITEMS);
+    let mut foods = Foods::new();
+    for id in 1..=items as i64 {
+        let stock = format!("{}.{:03}", id % 500, id % 1000).parse().unwrap();
+        let price = format!("{}.{:02}", 1000 + id % 90_000, id % 100).parse().unwrap();
+        foods.insert(Food::new(id, &format!("item number {}", id), stock, price));
+    }
+    println!("{} items", items);
+
+    let opts = CsvOptions::default();
+    let mut data = Vec::new();
+    time("write_foods", items, || write_foods(&mut data, &foods, &opts).unwrap());
+    let (parsed, _) = time("parse_foods", items, || parse_foods(data.as_slice()).unwrap());
+    assert_eq!(parsed.sorted().len(), items);
+
+    let path = std::env::temp_dir().join(format!("restaurant-bench-{}.csv", std::process::id()));
+    std::fs::write(&path, &data).unwrap();
+    time("load_foods", items, || load_foods(path.clone(), &opts).unwrap());
+    std::fs::remove_file(&path).unwrap();
+
+    let record = format_food(foods.get(1).unwrap()).unwrap();
+    time("parse_food, one record", items, || {
+        for _ in 0..items {
+            black_box(parse_food(black_box(&record)).unwrap());
+        }
+    });
+}
@@ -0,0 +1,16 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use tugas4_dev_restaurant::storage::{load_foods, CsvOptions};
+
+// The whole way a data file is read, gzip included when the first byte is odd.
+fuzz_target!(|data: &[u8]| {
+    let Some((choice, contents)) = data.split_first() else {
+        return;
+    };
+    let name = if choice % 2 == 1 { "food.csv.gz" } else { "food.csv" };
+    let path = std::env::temp_dir().join(format!("restaurant-fuzz-{}-{}", std::process::id(), name));
+    std::fs::write(&path, contents).unwrap();
+    let _ = load_foods(path.clone(), &CsvOptions::default());
+    let _ = std::fs::remove_file(path);
+});
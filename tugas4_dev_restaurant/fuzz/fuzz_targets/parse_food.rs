@@ -0,0 +1,11 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use tugas4_dev_restaurant::storage::{format_food, parse_food};
+
+fuzz_target!(|record: &str| {
+    // Whatever reads as an item has to write out again.
+    if let Ok(food) = parse_food(record) {
+        format_food(&food).unwrap();
+    }
+});
@@ -0,0 +1,13 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use tugas4_dev_restaurant::storage::parse_foods;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok((foods, warnings)) = parse_foods(data) {
+        // Each item and each warning comes from at least one line, and CR
+        // alone ends a line too.
+        let lines = data.iter().filter(|b| matches!(b, b'\n' | b'\r')).count() + 1;
+        assert!(foods.sorted().len() + warnings.len() <= lines);
+    }
+});
@@ -0,0 +1,1085 @@
+mod args;
+mod files;
+mod lists;
+mod menu;
+mod sales;
+mod setup;
+
+use std::fs::{File, OpenOptions};
+use std::io::{IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+
+use chrono::prelude::*;
+use structopt::StructOpt;
+
+use crate::color;
+use crate::config::{self, Config};
+use crate::currency::{self, Currency};
+use crate::error::{AppError, ParseError, ParseWarning};
+use crate::model::{
+    diff_foods, normalize_tax_rate, parse_date, parse_id_range, GroupBy, Money, OrderStatus, Quantity, SearchField,
+    SortKey,
+};
+use crate::output::Format;
+use crate::storage::{self, is_dry_run, is_stdio, parse_delimiter, Backend, CsvOptions, Storage, DRY_RUN};
+
+use args::{DiscountOpt, FoodDetails, FoodFilter, RangeFilter, RepriceItems};
+
+fn read_input(path: &Path) -> std::io::Result<String> {
+    let mut buffer = String::new();
+    if path.as_os_str() == "-" {
+        std::io::stdin().read_to_string(&mut buffer)?;
+    } else {
+        File::open(path)?.read_to_string(&mut buffer)?;
+    }
+    Ok(buffer)
+}
+
+// Without a terminal to answer from, or with --yes, the answer is yes so
+// scripts and cron jobs do not hang. A dry run has nothing to confirm.
+pub(crate) fn confirm(prompt: &str, assume_yes: bool) -> std::io::Result<bool> {
+    if assume_yes || is_dry_run() || !std::io::stdin().is_terminal() {
+        return Ok(true);
+    }
+    print!("{} [y/N] ", prompt);
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+fn write_report(out: Option<PathBuf>, overwrite: bool, text: &str) -> Result<(), AppError> {
+    let path = match out {
+        Some(path) if path.as_os_str() != "-" => path,
+        _ => {
+            print!("{}", text);
+            return Ok(());
+        }
+    };
+
+    let mut file = match OpenOptions::new()
+        .write(true)
+        .create(true)
+        .create_new(!overwrite)
+        .truncate(true)
+        .open(&path)
+    {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => return Err(AppError::OutputExists(path)),
+        Err(e) => return Err(e.into()),
+    };
+    file.write_all(text.as_bytes())?;
+    file.flush()?;
+    Ok(())
+}
+
+#[derive(StructOpt, Debug)]
+#[structopt(
+    about,
+    after_help = "EXIT STATUS:\n    0 success, 1 failure, 2 usage error, 3 item not found, 4 data file problem, \
+                  5 low-stock or out-of-stock items found"
+)]
+pub struct Opt {
+    #[structopt(
+        short,
+        long,
+        parse(from_os_str),
+        value_name = "PATH",
+        help = "the items file, or - to read it from stdin for commands that only read [default: food.csv] \
+                [env: RESTAURANT_DATA_FILE]"
+    )]
+    pub(crate) data_file: Option<PathBuf>,
+    #[structopt(
+        long,
+        value_name = "NAME",
+        help = "use the data and sales files of this profile, see the profile command"
+    )]
+    profile: Option<String>,
+    #[structopt(
+        long,
+        possible_values = &["csv", "sqlite"],
+        help = "storage backend [default: sqlite for .db files, csv otherwise]"
+    )]
+    pub(crate) backend: Option<Backend>,
+    #[structopt(
+        long,
+        parse(from_os_str),
+        help = "sales log file [default: sales.csv next to the data file] [env: RESTAURANT_SALES_FILE]"
+    )]
+    pub(crate) sales_file: Option<PathBuf>,
+    #[structopt(
+        long,
+        parse(from_os_str),
+        help = "kitchen orders file [default: orders.csv next to the data file]"
+    )]
+    orders_file: Option<PathBuf>,
+    #[structopt(
+        long,
+        parse(from_os_str),
+        help = "end-of-day totals file [default: daily_totals.csv next to the data file]"
+    )]
+    totals_file: Option<PathBuf>,
+    #[structopt(
+        long,
+        parse(from_os_str),
+        help = "waste log file [default: waste.csv next to the data file]"
+    )]
+    waste_file: Option<PathBuf>,
+    #[structopt(
+        long,
+        help = "how money is shown: plain, idr, usd or eur [default: from the config file]"
+    )]
+    pub(crate) currency: Option<Currency>,
+    #[structopt(
+        long,
+        parse(try_from_str = parse_delimiter),
+        default_value = ",",
+        help = "field separator of the data file, \\t for tab"
+    )]
+    pub(crate) delimiter: u8,
+    #[structopt(
+        long,
+        parse(try_from_str = parse_delimiter),
+        help = "field separator to save the data file with [default: --delimiter]"
+    )]
+    pub(crate) save_delimiter: Option<u8>,
+    #[structopt(long, help = "end lines of the saved data file with CRLF, as Excel on Windows does")]
+    pub(crate) crlf: bool,
+    #[structopt(
+        long,
+        default_value = "10",
+        help = "how many backups of the data file to keep in backups/, 0 turns them off"
+    )]
+    pub(crate) keep_backups: usize,
+    #[structopt(long, help = "replace invalid UTF-8 in the data file instead of refusing to load it")]
+    pub(crate) lossy: bool,
+    #[structopt(long, help = "refuse to load a data file with bad records instead of skipping them")]
+    pub(crate) strict: bool,
+    #[structopt(long, help = "trust the data file even though it was edited outside of this program")]
+    accept_external_changes: bool,
+    #[structopt(
+        long,
+        help = "encrypt the data file when saving it, the passphrase is asked for or read from RESTAURANT_PASSPHRASE"
+    )]
+    pub(crate) encrypt: bool,
+    #[structopt(
+        long,
+        global = true,
+        env = "RESTAURANT_FORMAT",
+        possible_values = &["table", "csv", "json", "jsonl", "debug"],
+        help = "output of list, search, show and the reports [default: table]; debug is the old one-struct-per-line output. \
+                export and import take json (the default) or jsonl, the sales report table or csv"
+    )]
+    pub(crate) format: Option<Format>,
+    #[structopt(
+        short,
+        long,
+        global = true,
+        help = "answer yes to every confirmation; prompts are also skipped when stdin is not a terminal"
+    )]
+    yes: bool,
+    #[structopt(
+        long,
+        global = true,
+        help = "run the command and show what it would change without writing anything"
+    )]
+    dry_run: bool,
+    #[structopt(
+        long,
+        possible_values = &["auto", "always", "never"],
+        default_value = "auto",
+        help = "color tables; auto colors only a terminal and honours NO_COLOR"
+    )]
+    pub(crate) color: color::ColorChoice,
+    #[structopt(subcommand)]
+    cmd : Command,
+    #[structopt(short, long, help = "print every bad record that was skipped and why the data file could not be locked")]
+    pub(crate) verbose: bool,
+}
+
+impl Opt {
+    // Errors are printed as JSON too when the output is.
+    pub fn wants_json(&self) -> bool {
+        matches!(self.format, Some(Format::Json | Format::Jsonl))
+    }
+}
+
+const DATA_FILE: &str = "food.csv";
+
+const ID_HELP: &str = "id of the item, as shown by list";
+const PRICE_HELP: &str = "price in the menu currency with at most two decimals, e.g. 25000 or 4.50, or in cents like 450c";
+
+#[derive(StructOpt, Debug)]
+pub(crate) enum Command {
+    #[structopt(
+        about = "add an item to the menu",
+        visible_alias = "new",
+        after_help = "EXAMPLES:\n    add \"nasi goreng\" 20 25000\n    add teh 4.5 8000 --unit l --category drinks --cost 2000\n    add --batch new-items.csv"
+    )]
+    Add{
+        #[structopt(required_unless = "batch", value_name = "NAME", help = "name shown on the menu and receipts")]
+        name: Option<String>,
+        #[structopt(
+            required_unless = "batch",
+            value_name = "STOCK",
+            help = "how much is on hand, in the item's --unit; up to three decimals for kg, g, l and ml"
+        )]
+        stock: Option<Quantity>,
+        #[structopt(required_unless = "batch", value_name = "PRICE", help = PRICE_HELP)]
+        price: Option<Money>,
+        #[structopt(
+            long,
+            parse(from_os_str),
+            value_name = "PATH",
+            conflicts_with_all = &["name", "sku"],
+            help = "add every name,stock,price row of this CSV file, - for stdin; other flags apply to each row"
+        )]
+        batch: Option<PathBuf>,
+        #[structopt(long, help = "add the item even if another one already has its name")]
+        allow_duplicate: bool,
+        #[structopt(
+            long,
+            conflicts_with = "allow-duplicate",
+            help = "when an item with the name exists, add the stock to it instead; its price and details are kept"
+        )]
+        merge_stock: bool,
+        #[structopt(flatten)]
+        details: FoodDetails,
+    },
+    #[structopt(
+        about = "change some fields of an item, e.g. edit 3 --price 12.50; the rest is kept. \
+                 Without an id, --name picks the item instead of renaming it"
+    )]
+    Edit {
+        #[structopt(required_unless = "name", value_name = "ID", help = "id of the item to change")]
+        id: Option<i64>,
+        #[structopt(long, value_name = "NAME", help = "new name, or the item to edit when no id is given")]
+        name: Option<String>,
+        #[structopt(long, value_name = "STOCK", help = "new stock, in the item's unit")]
+        stock: Option<Quantity>,
+        #[structopt(long, value_name = "PRICE", help = PRICE_HELP)]
+        price: Option<Money>,
+        #[structopt(
+            long,
+            requires = "id",
+            help = "create the item at this id if it does not exist; needs --name, --stock and --price"
+        )]
+        upsert: bool,
+        #[structopt(flatten)]
+        details: FoodDetails,
+    },
+    #[structopt(about = "change only the name of an item, e.g. rename 3 \"Ayam Bakar Madu\"")]
+    Rename {
+        #[structopt(value_name = "ID", help = ID_HELP)]
+        id: i64,
+        #[structopt(value_name = "NEW_NAME", help = "the new name; spaces around and between words are tidied")]
+        new_name: String,
+    },
+    #[structopt(about = "list the menu", visible_alias = "ls")]
+    List{
+        #[structopt(
+            long,
+            possible_values = &["id", "name", "price", "stock", "popularity", "margin", "updated", "file"],
+            default_value = "id",
+            help = "file keeps the order of the data file and streams it instead of loading the whole menu"
+        )]
+        sort: SortKey,
+        #[structopt(long, help = "sort from highest to lowest")]
+        desc: bool,
+        #[structopt(long, help = "show price minus cost and the margin percentage")]
+        margin: bool,
+        #[structopt(long, help = "print a header per category")]
+        grouped: bool,
+        #[structopt(flatten)]
+        filter: FoodFilter,
+        #[structopt(long, default_value = "0", help = "show at most N items, 0 shows all")]
+        limit: usize,
+        #[structopt(long, default_value = "0", help = "skip the first N items")]
+        offset: usize,
+    },
+    #[structopt(about = "remove items by id, e.g. remove 3 7 20-29", visible_alias = "rm")]
+    Remove {
+        #[structopt(
+            required_unless = "name",
+            parse(try_from_str = parse_id_range),
+            value_name = "ID",
+            help = "ids or inclusive ranges such as 20-29"
+        )]
+        ids: Vec<std::ops::RangeInclusive<i64>>,
+        #[structopt(long, conflicts_with = "ids", help = "remove the item with this name, ignoring case")]
+        name: Option<String>,
+        #[structopt(long, help = "remove the ids that exist even if some do not")]
+        missing_ok: bool,
+        #[structopt(long, short, help = "do not print what was removed")]
+        quiet: bool,
+    },
+    #[structopt(about = "list the automatic backups of the data file, newest first")]
+    Backups,
+    #[structopt(about = "replace the data file with a backup, the newest one by default")]
+    Restore {
+        #[structopt(help = "backup file name as shown by backups, or a path")]
+        which: Option<String>,
+    },
+    #[structopt(
+        about = "upgrade the data file to the current schema, or copy the items of a CSV file into a fresh one, e.g. -d menu.db migrate food.csv"
+    )]
+    Migrate {
+        #[structopt(parse(from_os_str), value_name = "CSV", help = "CSV data file to copy the items from")]
+        csv: Option<PathBuf>,
+    },
+    #[structopt(about = "encrypt the data file in place with a passphrase")]
+    Encrypt,
+    #[structopt(about = "decrypt the data file in place")]
+    Decrypt,
+    #[structopt(about = "check that the data file was not changed outside of this program since it was last saved")]
+    Verify,
+    #[structopt(about = "check the data file for bad records, duplicate ids and bad values, exiting non-zero if any")]
+    Validate,
+    #[structopt(
+        about = "browse the menu full screen: arrows move, +/- change stock, e edits the price, / filters, q quits"
+    )]
+    Tui,
+    #[structopt(about = "load the menu once and run commands typed on stdin until quit or end of input")]
+    Shell {
+        #[structopt(long, help = "keep changes in memory until save or quit instead of writing after every command")]
+        save_on_quit: bool,
+    },
+    #[structopt(about = "list, create or remove profiles, one per restaurant or location")]
+    Profile {
+        #[structopt(subcommand)]
+        cmd: ProfileCommand,
+    },
+    #[structopt(about = "show the settings in effect, or write a starter config file")]
+    Config {
+        #[structopt(subcommand)]
+        cmd: ConfigCommand,
+    },
+    #[structopt(about = "print a completion script, e.g. completions zsh > _tugas4_dev_restaurant")]
+    Completions {
+        #[structopt(
+            possible_values = &["bash", "zsh", "fish", "powershell", "elvish"],
+            value_name = "SHELL",
+            help = "shell to complete for"
+        )]
+        shell: structopt::clap::Shell,
+    },
+    // Called by the bash completion to offer the ids in the data file.
+    #[structopt(name = "complete-ids", setting = structopt::clap::AppSettings::Hidden)]
+    CompleteIds,
+    #[structopt(about = "list groups of items that share a name, ignoring case and extra spaces")]
+    Dupes,
+    #[structopt(about = "item count, stock value, price range and out-of-stock count of the menu")]
+    Stats,
+    #[structopt(about = "list items at or below their minimum stock, exiting with status 5 if any")]
+    LowStock {
+        #[structopt(long, value_name = "STOCK", help = "list items with stock at or below this instead of their own minimum")]
+        threshold: Option<Quantity>,
+        #[structopt(long, help = "leave out items that are 86'd")]
+        ignore_disabled: bool,
+    },
+    #[structopt(about = "list items with no stock left, exiting with status 5 if any")]
+    OutOfStock {
+        #[structopt(long, help = "leave out items that are 86'd")]
+        ignore_disabled: bool,
+    },
+    #[structopt(about = "list every item containing an allergen")]
+    Allergen {
+        #[structopt(value_name = "ALLERGEN", help = "allergen to look for, ignoring case, e.g. nuts")]
+        name: String,
+    },
+    #[structopt(about = "find an item by SKU, exiting non-zero if there is none")]
+    Lookup {
+        #[structopt(value_name = "SKU", help = "SKU or barcode to look up")]
+        sku: String,
+    },
+    #[structopt(about = "purchase lists of low-stock items, one block per supplier")]
+    ReorderList {
+        #[structopt(long, help = "list items with stock below N instead of their minimum stock")]
+        below: Option<Quantity>,
+    },
+    #[structopt(about = "list items that expire within the next DAYS days")]
+    Expiring {
+        #[structopt(value_name = "DAYS", help = "how many days ahead to look, 0 for today only")]
+        days: u32,
+    },
+    #[structopt(about = "list items past their expiry date")]
+    Expired,
+    #[structopt(about = "86 an item so it cannot be sold")]
+    Disable {
+        #[structopt(value_name = "ID", help = ID_HELP)]
+        id: i64,
+    },
+    #[structopt(about = "make a disabled item sellable again")]
+    Enable {
+        #[structopt(value_name = "ID", help = ID_HELP)]
+        id: i64,
+    },
+    #[structopt(
+        about = "list items whose name contains the query",
+        after_help = "By default the query matches anywhere in the name, ignoring case. Whole-name \
+                      matches are listed first, then names starting with the query, then the rest.\n\n\
+                      EXAMPLES:\n    search ayam --max-price 20000 --min-stock 1\n    \
+                      search spicy --fields name,notes",
+        visible_aliases = &["s", "find"]
+    )]
+    Search {
+        #[structopt(value_name = "QUERY", help = "text to find in item names, ignoring case")]
+        query : String,
+        #[structopt(long, conflicts_with_all = &["prefix", "regex"], help = "match the whole name")]
+        exact: bool,
+        #[structopt(long, conflicts_with = "regex", help = "match the start of the name")]
+        prefix: bool,
+        #[structopt(long, help = "treat the query as a regular expression")]
+        regex: bool,
+        #[structopt(
+            long,
+            conflicts_with_all = &["exact", "prefix", "regex"],
+            help = "list the closest names, allowing for typos"
+        )]
+        fuzzy: bool,
+        #[structopt(long, help = "tell upper and lower case apart")]
+        case_sensitive: bool,
+        #[structopt(
+            long,
+            value_name = "FIELDS",
+            use_delimiter = true,
+            help = "comma-separated fields to match: name, category, notes [default: name]"
+        )]
+        fields: Vec<SearchField>,
+        #[structopt(long, help = "also match the query against categories, like adding category to --fields")]
+        category: bool,
+        #[structopt(long, help = "also match the query against notes, like adding notes to --fields")]
+        include_notes: bool,
+        #[structopt(long, help = "only return items with this tag")]
+        tag: Option<String>,
+        #[structopt(flatten)]
+        range: RangeFilter,
+    },
+    #[structopt(about = "add or remove free-form tags on an item")]
+    Tag {
+        #[structopt(value_name = "ID", help = ID_HELP)]
+        id: i64,
+        #[structopt(long, help = "tags to add, e.g. --add vegan seasonal")]
+        add: Vec<String>,
+        #[structopt(long, help = "tags to remove")]
+        remove: Vec<String>,
+    },
+    #[structopt(about = "list every tag in use with how many items carry it")]
+    Tags,
+    #[structopt(about = "mark an item as a special until the end of a day")]
+    Special {
+        #[structopt(value_name = "ID", help = ID_HELP)]
+        id: i64,
+        #[structopt(long, parse(try_from_str = parse_date), help = "last day of the special as YYYY-MM-DD [default: today]")]
+        until: Option<NaiveDate>,
+    },
+    #[structopt(about = "print every detail of one item, including its notes")]
+    Show {
+        #[structopt(value_name = "ID", help = ID_HELP)]
+        id: i64,
+    },
+    #[structopt(about = "write the whole menu to a file, - for stdout")]
+    Export {
+        #[structopt(long, parse(from_os_str), default_value = "-", value_name = "PATH", help = "file to write")]
+        out: PathBuf,
+        #[structopt(long, help = "replace the --out file if it already exists")]
+        overwrite: bool,
+    },
+    #[structopt(about = "load menu items from a file, - for stdin")]
+    Import {
+        #[structopt(parse(from_os_str), value_name = "PATH", help = "file written by export, - for stdin")]
+        input: PathBuf,
+        #[structopt(long, conflicts_with = "replace", help = "keep existing items and only add new ids (the default)")]
+        merge: bool,
+        #[structopt(long, help = "drop the current menu and use the imported items instead")]
+        replace: bool,
+    },
+    #[structopt(
+        about = "sell an item, taking it out of stock and printing a receipt",
+        after_help = "EXAMPLES:\n    sell 3 2\n    sell 8991234567890 1 --sku\n    sell 3 2 --discount-percent 10 --receipt-out receipt.txt"
+    )]
+    Sell {
+        #[structopt(value_name = "ID", help = "item id, or its SKU with --sku")]
+        id: String,
+        #[structopt(value_name = "QUANTITY", help = "how much to sell, in the item's unit")]
+        quantity: Quantity,
+        #[structopt(long, help = "treat the first argument as a SKU instead of an id")]
+        sku: bool,
+        #[structopt(long, help = "allow selling more than the current stock")]
+        force: bool,
+        #[structopt(long, parse(from_os_str), help = "also write the receipt to this file")]
+        receipt_out: Option<PathBuf>,
+        #[structopt(flatten)]
+        discount: DiscountOpt,
+    },
+    #[structopt(about = "sales per day, week or month, or one of the reports below")]
+    Report {
+        #[structopt(value_name = "DATE", help = "day to report on as YYYY-MM-DD [default: today]")]
+        date: Option<String>,
+        #[structopt(long, conflicts_with = "date", help = "first day of the range, inclusive")]
+        from: Option<String>,
+        #[structopt(long, conflicts_with = "date", help = "last day of the range, inclusive")]
+        to: Option<String>,
+        #[structopt(
+            long,
+            possible_values = &["day", "week", "month"],
+            default_value = "day",
+            help = "one row per day, ISO week or calendar month"
+        )]
+        group_by: GroupBy,
+        #[structopt(long, parse(from_os_str), help = "write the report to this file, - for stdout")]
+        out: Option<PathBuf>,
+        #[structopt(long, help = "replace the --out file if it already exists")]
+        overwrite: bool,
+        #[structopt(long, conflicts_with = "format", help = "draw a bar per day instead of a table")]
+        chart: bool,
+        #[structopt(subcommand)]
+        kind: Option<ReportKind>,
+    },
+    #[structopt(about = "refund a sale, putting its items back in stock")]
+    Refund {
+        #[structopt(value_name = "SALE_ID", help = "id of the sale as printed on its receipt")]
+        sale_id: i64,
+    },
+    #[structopt(
+        about = "raise or lower the price of many items at once, showing the changes before saving",
+        after_help = "EXAMPLES:\n    reprice --percent 7.5 --round-to 500\n    reprice --amount -1000 --category drinks\n    reprice --percent 10 --ids 1,4,9-12\n    reprice --percent -20 --name-contains sate --yes"
+    )]
+    Reprice {
+        #[structopt(
+            long,
+            required_unless = "amount",
+            conflicts_with = "amount",
+            allow_hyphen_values = true,
+            help = "change every price by this percentage, e.g. 7.5 or -10"
+        )]
+        percent: Option<f64>,
+        #[structopt(long, allow_hyphen_values = true, help = "add this amount to every price, e.g. 1000 or -500")]
+        amount: Option<Money>,
+        #[structopt(long, value_name = "PRICE", help = "round every new price to the nearest multiple of this, e.g. 500")]
+        round_to: Option<Money>,
+        #[structopt(flatten)]
+        items: RepriceItems,
+    },
+    #[structopt(
+        about = "add a delivery to the stock, e.g. restock 3 20; use sell or waste to take stock off",
+        after_help = "EXAMPLES:\n    restock 3 20\n    restock --batch delivery-note.csv"
+    )]
+    Restock {
+        #[structopt(required_unless = "batch", value_name = "ID", help = ID_HELP)]
+        id: Option<i64>,
+        #[structopt(required_unless = "batch", value_name = "QUANTITY", help = "how much arrived, in the item's unit")]
+        quantity: Option<Quantity>,
+        #[structopt(
+            long,
+            parse(from_os_str),
+            value_name = "PATH",
+            conflicts_with = "id",
+            help = "add every id,quantity row of this CSV file, - for stdin, with one save"
+        )]
+        batch: Option<PathBuf>,
+    },
+    #[structopt(about = "take spoiled or thrown-out stock off the shelf")]
+    Waste {
+        #[structopt(value_name = "ID", help = ID_HELP)]
+        id: i64,
+        #[structopt(value_name = "QUANTITY", help = "how much was thrown out, in the item's unit")]
+        quantity: Quantity,
+        #[structopt(value_name = "REASON", help = "why, e.g. spoiled or dropped")]
+        reason: String,
+    },
+    #[structopt(about = "close the day, recording its totals and reconciling the till")]
+    CloseDay {
+        #[structopt(long, help = "day to close as YYYY-MM-DD [default: today]")]
+        date: Option<String>,
+        #[structopt(long, help = "cash counted in the till, to compare against sales")]
+        counted: Option<Money>,
+        #[structopt(long, help = "overwrite the totals if the day was already closed")]
+        force: bool,
+    },
+    #[structopt(about = "sell several items on one receipt, or manage pending kitchen orders")]
+    Order {
+        #[structopt(value_name = "ID:QTY", help = "items to sell right away as ID:QTY pairs, e.g. 3:2 7:1")]
+        items: Vec<String>,
+        #[structopt(long, help = "allow selling more than the current stock")]
+        force: bool,
+        #[structopt(long, parse(from_os_str), help = "also write the receipt to this file")]
+        receipt_out: Option<PathBuf>,
+        #[structopt(flatten)]
+        discount: DiscountOpt,
+        #[structopt(subcommand)]
+        cmd: Option<OrderCommand>,
+    },
+
+}
+
+#[derive(StructOpt, Debug)]
+pub(crate) enum OrderCommand {
+    #[structopt(about = "create a pending order without touching stock")]
+    New {
+        #[structopt(value_name = "ID", help = ID_HELP)]
+        food_id: i64,
+        #[structopt(value_name = "QUANTITY", help = "how much is ordered, in the item's unit")]
+        quantity: Quantity,
+    },
+    #[structopt(about = "list orders, optionally by status")]
+    List {
+        #[structopt(long, possible_values = &["pending", "completed", "cancelled"], help = "only list orders in this state")]
+        status: Option<OrderStatus>,
+    },
+    #[structopt(about = "complete a pending order, taking its items out of stock")]
+    Complete {
+        #[structopt(value_name = "ORDER_ID", help = "id of the order")]
+        id: i64,
+        #[structopt(long, help = "allow completing with more than the current stock")]
+        force: bool,
+    },
+    #[structopt(about = "cancel a pending order")]
+    Cancel {
+        #[structopt(value_name = "ORDER_ID", help = "id of the order")]
+        id: i64,
+    },
+}
+
+#[derive(StructOpt, Debug)]
+pub(crate) enum ProfileCommand {
+    #[structopt(about = "list the profiles")]
+    List,
+    #[structopt(about = "create an empty profile")]
+    Create {
+        #[structopt(value_name = "NAME", help = "letters, digits, - and _")]
+        name: String,
+    },
+    #[structopt(about = "delete a profile with all of its files")]
+    Remove {
+        #[structopt(value_name = "NAME")]
+        name: String,
+    },
+}
+
+#[derive(StructOpt, Debug)]
+pub(crate) enum ConfigCommand {
+    #[structopt(about = "print the settings after merging the config files and the flags given")]
+    Show,
+    #[structopt(about = "write a commented starter config file")]
+    Init {
+        #[structopt(
+            parse(from_os_str),
+            value_name = "PATH",
+            help = "where to write it [default: ~/.config/restaurant/config.toml]"
+        )]
+        path: Option<PathBuf>,
+        #[structopt(long, help = "replace the file if it already exists")]
+        overwrite: bool,
+    },
+}
+
+#[derive(StructOpt, Debug)]
+pub(crate) enum ReportKind {
+    #[structopt(about = "best-selling items by quantity")]
+    Top {
+        #[structopt(long, default_value = "10", help = "how many items to show")]
+        limit: usize,
+        #[structopt(long, help = "draw a bar per item instead of a table")]
+        chart: bool,
+    },
+    #[structopt(about = "revenue, average ticket and busiest hour")]
+    Summary,
+    #[structopt(about = "revenue minus cost of goods per item")]
+    Profit,
+    #[structopt(about = "wasted quantity and lost value per item")]
+    Waste,
+}
+
+const LOCK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+// Duplicate ids are always worth a warning as a record is lost on the next
+// save, records that could not be read are only shown when verbose.
+fn report_warning(warning: &ParseWarning, verbose: bool) {
+    match &warning.error {
+        ParseError::DuplicateRecord { line, kept, .. } => eprintln!(
+            "warning: {}, keeping line {}\n  kept line {}: \"{}\"\n  dropped line {}: \"{}\"",
+            warning.error, line, line, kept, warning.line, warning.raw
+        ),
+        ParseError::ReplacedUtf8(_) | ParseError::SchemaMismatch(_) | ParseError::ExternalChange(_) => {
+            eprintln!("warning: {}", warning.error)
+        }
+        _ if !verbose => {}
+        _ if warning.raw.is_empty() => eprintln!("\nError on {}\n", warning.error),
+        _ => eprintln!("\nError on {}\n > \"{}\"\n", warning.error, warning.raw),
+    }
+}
+
+fn lock_data_file(data_file: &Path, exclusive: bool, verbose: bool) -> Result<Option<File>, AppError> {
+    let mut lock_name = data_file.as_os_str().to_owned();
+    lock_name.push(".lock");
+    let lock_file = PathBuf::from(lock_name);
+
+    let file = match OpenOptions::new().write(true).create(true).truncate(false).open(&lock_file) {
+        Ok(file) => file,
+        Err(e) => {
+            if verbose {
+                eprintln!("could not open {}: {}, continuing without a lock", lock_file.display(), e);
+            }
+            return Ok(None);
+        }
+    };
+
+    let started = std::time::Instant::now();
+    loop {
+        let attempt = if exclusive { file.try_lock() } else { file.try_lock_shared() };
+        match attempt {
+            Ok(()) => return Ok(Some(file)),
+            Err(std::fs::TryLockError::WouldBlock) if started.elapsed() < LOCK_TIMEOUT => {
+                std::thread::sleep(std::time::Duration::from_millis(100));
+            }
+            Err(std::fs::TryLockError::WouldBlock) => return Err(AppError::Locked(data_file.to_path_buf())),
+            Err(std::fs::TryLockError::Error(e)) => {
+                if verbose {
+                    eprintln!("could not lock {}: {}, continuing without a lock", lock_file.display(), e);
+                }
+                return Ok(None);
+            }
+        }
+    }
+}
+
+fn resolve_tax_rate(flag: Option<i64>, config: &Config) -> Result<i64, ParseError> {
+    match (flag, config.tax_rate) {
+        (Some(rate), _) => Ok(rate),
+        (None, Some(rate)) => normalize_tax_rate(rate),
+        (None, None) => Ok(0),
+    }
+}
+
+// The data file and sales log, each from the first of its flag, --profile,
+// its RESTAURANT_* variable, the config file and the default. A profile keeps
+// its sales next to its own data file, whatever the variable or config say.
+fn resolve_paths(
+    opt: &Opt,
+    profile_dir: Option<&Path>,
+    config: &Config,
+    env: impl Fn(&str) -> Option<std::ffi::OsString>,
+) -> (PathBuf, PathBuf) {
+    let env = |var: &str| env(var).filter(|value| !value.is_empty()).map(PathBuf::from);
+    let data_file = match (&opt.data_file, profile_dir) {
+        (Some(path), _) => path.clone(),
+        (None, Some(dir)) => dir.join(DATA_FILE),
+        (None, None) => env("RESTAURANT_DATA_FILE")
+            .or_else(|| config.data_file.clone())
+            .unwrap_or_else(|| PathBuf::from(DATA_FILE)),
+    };
+    let sales_file = match (&opt.sales_file, profile_dir) {
+        (Some(path), _) => path.clone(),
+        (None, Some(_)) => data_file.with_file_name("sales.csv"),
+        (None, None) => env("RESTAURANT_SALES_FILE")
+            .or_else(|| config.sales_file.clone())
+            .unwrap_or_else(|| data_file.with_file_name("sales.csv")),
+    };
+    (data_file, sales_file)
+}
+
+pub fn run(opt: Opt) -> Result<(), AppError> {
+    let config = Config::load()?;
+    currency::set_active(opt.currency.or(config.currency).unwrap_or(Currency::Plain));
+    let profile_dir = match &opt.profile {
+        Some(_) if opt.data_file.is_some() => return Err(AppError::ProfileWithDataFile),
+        Some(name) => {
+            let dir = config::profile_dir(name)?;
+            if !dir.is_dir() && !matches!(opt.cmd, Command::Profile { .. }) {
+                return Err(AppError::ProfileNotFound(name.clone()));
+            }
+            Some(dir)
+        }
+        None => None,
+    };
+    let (data_file, sales_file) = resolve_paths(&opt, profile_dir.as_deref(), &config, |var| std::env::var_os(var));
+    let csv_opts = CsvOptions {
+        delimiter: opt.delimiter,
+        save_delimiter: opt.save_delimiter.unwrap_or(opt.delimiter),
+        crlf: opt.crlf,
+        keep_backups: opt.keep_backups,
+        lossy: opt.lossy,
+        strict: opt.strict,
+        accept_changes: opt.accept_external_changes,
+        encrypt: opt.encrypt,
+        verbose: opt.verbose,
+        on_warning: report_warning,
+    };
+    // Accepting external changes writes a new checksum for the data file.
+    let read_only = opt.cmd.is_read_only() && !opt.accept_external_changes;
+    // A dry run reads like the real command but writes like a read-only one.
+    let writes = !read_only && !opt.dry_run;
+    DRY_RUN.store(opt.dry_run, Ordering::Relaxed);
+    if is_stdio(&data_file) && writes {
+        return Err(AppError::StdinDataFile);
+    }
+    if let Some(dir) = data_file.parent().filter(|dir| writes && !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    let _lock = match is_stdio(&data_file) {
+        true => None,
+        false => lock_data_file(&data_file, writes, opt.verbose)?,
+    };
+    let mut store = storage::open(&data_file, opt.backend, csv_opts, !read_only)?;
+    let orders_file = match opt.orders_file {
+        Some(ref path) => path.clone(),
+        None => data_file.with_file_name("orders.csv"),
+    };
+    let totals_file = match opt.totals_file {
+        Some(ref path) => path.clone(),
+        None => data_file.with_file_name("daily_totals.csv"),
+    };
+    let waste_file = match opt.waste_file {
+        Some(ref path) => path.clone(),
+        None => data_file.with_file_name("waste.csv"),
+    };
+
+    if opt.verbose {
+        if let Some(name) = &opt.profile {
+            eprintln!("profile: {}", name);
+        }
+        eprintln!("data file: {}", data_file.display());
+        eprintln!("sales file: {}", sales_file.display());
+    }
+
+    color::init(opt.color);
+    let context = Context {
+        format: opt.format.or(config.default_format).unwrap_or(Format::Table),
+        data_file,
+        backend: opt.backend,
+        requested_format: opt.format,
+        yes: opt.yes,
+        config,
+        csv_opts,
+        sales_file,
+        orders_file,
+        totals_file,
+        waste_file,
+        dry_run: opt.dry_run,
+        profile: opt.profile.clone(),
+    };
+    if !opt.dry_run {
+        return execute(opt.cmd, &context, &mut *store);
+    }
+    let mut memory = storage::MemoryStore::new(&mut *store)?;
+    let before = memory.load_foods()?;
+    execute(opt.cmd, &context, &mut memory)?;
+    let changes = diff_foods(&before, &memory.load_foods()?)?;
+    match changes.is_empty() {
+        true => eprintln!("Dry run, nothing was written"),
+        false => eprintln!("Dry run, nothing was written. The menu would change:\n{}", changes),
+    }
+    Ok(())
+}
+
+// Everything a command needs besides the store, worked out once per run so
+// the shell can execute many commands with it.
+pub(crate) struct Context {
+    pub(crate) data_file: PathBuf,
+    pub(crate) backend: Option<Backend>,
+    requested_format: Option<Format>,
+    yes: bool,
+    pub(crate) config: Config,
+    csv_opts: CsvOptions,
+    pub(crate) sales_file: PathBuf,
+    orders_file: PathBuf,
+    totals_file: PathBuf,
+    waste_file: PathBuf,
+    pub(crate) format: Format,
+    dry_run: bool,
+    profile: Option<String>,
+}
+
+// Hands the command to the module for its group. The match has no catch-all
+// so a new command does not compile until it is given one.
+pub(crate) fn execute(cmd: Command, context: &Context, store: &mut dyn Storage) -> Result<(), AppError> {
+    match cmd {
+        Command::Add { .. }
+        | Command::Rename { .. }
+        | Command::Edit { .. }
+        | Command::Remove { .. }
+        | Command::Disable { .. }
+        | Command::Enable { .. }
+        | Command::Tag { .. }
+        | Command::Special { .. }
+        | Command::Reprice { .. }
+        | Command::Restock { .. }
+        | Command::Waste { .. } => menu::execute(cmd, context, store),
+        Command::List { .. }
+        | Command::Dupes
+        | Command::Stats
+        | Command::LowStock { .. }
+        | Command::OutOfStock { .. }
+        | Command::Allergen { .. }
+        | Command::Lookup { .. }
+        | Command::ReorderList { .. }
+        | Command::Expiring { .. }
+        | Command::Expired
+        | Command::Search { .. }
+        | Command::Tags
+        | Command::Show { .. }
+        | Command::CompleteIds => lists::execute(cmd, context, store),
+        Command::Backups
+        | Command::Restore { .. }
+        | Command::Migrate { .. }
+        | Command::Encrypt
+        | Command::Decrypt
+        | Command::Verify
+        | Command::Validate
+        | Command::Export { .. }
+        | Command::Import { .. } => files::execute(cmd, context, store),
+        Command::Tui
+        | Command::Shell { .. }
+        | Command::Profile { .. }
+        | Command::Config { .. }
+        | Command::Completions { .. } => setup::execute(cmd, context, store),
+        Command::Sell { .. }
+        | Command::Order { .. }
+        | Command::CloseDay { .. }
+        | Command::Refund { .. }
+        | Command::Report { .. } => sales::execute(cmd, context, store),
+    }
+}
+
+impl Command {
+    // These read or write the data file directly instead of going through
+    // the store, so the shell saves before and reloads after them.
+    pub(crate) fn uses_data_file(&self) -> bool {
+        matches!(
+            self,
+            Command::Backups
+                | Command::Restore { .. }
+                | Command::Migrate { .. }
+                | Command::Encrypt
+                | Command::Decrypt
+                | Command::Verify
+                | Command::Validate
+        )
+    }
+
+    // Whether the command can share the lock and read the items from stdin.
+    // A report written with --out and config init save files, so they count
+    // as writers.
+    fn is_read_only(&self) -> bool {
+        match self {
+            Command::Report { out, .. } => out.as_deref().is_none_or(is_stdio),
+            Command::Config { cmd } => matches!(cmd, ConfigCommand::Show),
+            Command::List { .. }
+            | Command::Search { .. }
+            | Command::Show { .. }
+            | Command::LowStock { .. }
+            | Command::OutOfStock { .. }
+            | Command::Stats
+            | Command::Dupes
+            | Command::Allergen { .. }
+            | Command::Lookup { .. }
+            | Command::ReorderList { .. }
+            | Command::Expiring { .. }
+            | Command::Expired
+            | Command::Tags
+            | Command::Export { .. }
+            | Command::Backups
+            | Command::Validate
+            | Command::Verify
+            | Command::Order { cmd: Some(OrderCommand::List { .. }), .. }
+            | Command::Completions { .. }
+            | Command::Profile { .. }
+            | Command::CompleteIds => true,
+            _ => false,
+        }
+    }
+
+}
+
+pub const ENV_OVERRIDES: [&str; 3] = ["RESTAURANT_DATA_FILE", "RESTAURANT_SALES_FILE", "RESTAURANT_FORMAT"];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The data and sales files for these flags, variables and config file
+    // values, as plain strings.
+    fn paths(flags: &[&str], vars: &[(&str, &str)], profile: Option<&str>, config: (Option<&str>, Option<&str>)) -> (String, String) {
+        let opt = Opt::from_iter(["restaurant"].iter().chain(flags).chain(&["list"]));
+        let config = Config {
+            data_file: config.0.map(PathBuf::from),
+            sales_file: config.1.map(PathBuf::from),
+            ..Config::default()
+        };
+        let env = |var: &str| vars.iter().find(|(name, _)| *name == var).map(|(_, value)| value.into());
+        let (data_file, sales_file) = resolve_paths(&opt, profile.map(Path::new), &config, env);
+        (data_file.display().to_string(), sales_file.display().to_string())
+    }
+
+    const DATA_VAR: (&str, &str) = ("RESTAURANT_DATA_FILE", "env/food.csv");
+    const SALES_VAR: (&str, &str) = ("RESTAURANT_SALES_FILE", "env/sales.csv");
+    const CONFIG: (Option<&str>, Option<&str>) = (Some("config/food.csv"), Some("config/sales.csv"));
+
+    #[test]
+    fn paths_default_to_the_working_directory() {
+        assert_eq!(paths(&[], &[], None, (None, None)), ("food.csv".into(), "sales.csv".into()));
+    }
+
+    #[test]
+    fn the_config_file_beats_the_defaults() {
+        assert_eq!(paths(&[], &[], None, CONFIG), ("config/food.csv".into(), "config/sales.csv".into()));
+        assert_eq!(paths(&[], &[], None, (Some("config/food.csv"), None)), ("config/food.csv".into(), "config/sales.csv".into()));
+        assert_eq!(paths(&[], &[], None, (None, Some("config/sales.csv"))), ("food.csv".into(), "config/sales.csv".into()));
+    }
+
+    #[test]
+    fn variables_beat_the_config_file() {
+        assert_eq!(paths(&[], &[DATA_VAR, SALES_VAR], None, CONFIG), ("env/food.csv".into(), "env/sales.csv".into()));
+        assert_eq!(paths(&[], &[DATA_VAR], None, CONFIG), ("env/food.csv".into(), "config/sales.csv".into()));
+        assert_eq!(paths(&[], &[DATA_VAR], None, (None, None)), ("env/food.csv".into(), "env/sales.csv".into()));
+        assert_eq!(paths(&[], &[SALES_VAR], None, CONFIG), ("config/food.csv".into(), "env/sales.csv".into()));
+        // Empty ones, as container setups leave behind, count as unset.
+        let empty = [("RESTAURANT_DATA_FILE", ""), ("RESTAURANT_SALES_FILE", "")];
+        assert_eq!(paths(&[], &empty, None, CONFIG), ("config/food.csv".into(), "config/sales.csv".into()));
+    }
+
+    #[test]
+    fn flags_beat_everything() {
+        let flags = ["-d", "cli/food.csv", "--sales-file", "cli/sales.csv"];
+        for vars in [&[][..], &[DATA_VAR, SALES_VAR]] {
+            for config in [(None, None), CONFIG] {
+                assert_eq!(paths(&flags, vars, None, config), ("cli/food.csv".into(), "cli/sales.csv".into()));
+            }
+        }
+        assert_eq!(paths(&["-d", "cli/food.csv"], &[SALES_VAR], None, CONFIG), ("cli/food.csv".into(), "env/sales.csv".into()));
+        assert_eq!(paths(&["-d", "cli/food.csv"], &[], None, (None, None)), ("cli/food.csv".into(), "cli/sales.csv".into()));
+        assert_eq!(paths(&["--sales-file", "cli/sales.csv"], &[DATA_VAR], None, CONFIG), ("env/food.csv".into(), "cli/sales.csv".into()));
+    }
+
+    #[test]
+    fn a_profile_beats_variables_and_the_config_file() {
+        let profile = Some("profiles/shop");
+        let expected = ("profiles/shop/food.csv".into(), "profiles/shop/sales.csv".into());
+        assert_eq!(paths(&[], &[], profile, (None, None)), expected);
+        assert_eq!(paths(&[], &[DATA_VAR, SALES_VAR], profile, CONFIG), expected);
+        let sales_flag = ["--sales-file", "cli/sales.csv"];
+        assert_eq!(paths(&sales_flag, &[SALES_VAR], profile, CONFIG), ("profiles/shop/food.csv".into(), "cli/sales.csv".into()));
+    }
+
+    // The command these arguments parse to, as Debug shows it.
+    fn command(args: &[&str]) -> String {
+        let opt = Opt::from_iter_safe(["restaurant"].iter().chain(args)).unwrap();
+        format!("{:?}", opt.cmd)
+    }
+
+    #[test]
+    fn aliases_parse_to_the_same_commands() {
+        assert_eq!(command(&["ls"]), command(&["list"]));
+        assert_eq!(command(&["ls", "--category", "drinks"]), command(&["list", "--category", "drinks"]));
+        assert_eq!(command(&["rm", "3", "7"]), command(&["remove", "3", "7"]));
+        assert_eq!(command(&["s", "bak"]), command(&["search", "bak"]));
+        assert_eq!(command(&["find", "bak"]), command(&["search", "bak"]));
+        assert_eq!(command(&["new", "soto", "5", "9000"]), command(&["add", "soto", "5", "9000"]));
+        assert!(command(&["ls"]).starts_with("List"), "{}", command(&["ls"]));
+        assert!(command(&["rm", "3"]).starts_with("Remove"), "{}", command(&["rm", "3"]));
+        assert!(command(&["find", "bak"]).starts_with("Search"), "{}", command(&["find", "bak"]));
+        assert!(command(&["new", "soto", "5", "9000"]).starts_with("Add"), "{}", command(&["new", "soto", "5", "9000"]));
+    }
+}
@@ -0,0 +1,239 @@
+use chrono::prelude::*;
+use structopt::StructOpt;
+
+use crate::error::AppError;
+use crate::model::{
+    parse_date, parse_id_range, parse_tags, parse_tax_rate, Food, FoodIndex, Money, Quantity, SearchMode,
+};
+
+#[derive(StructOpt, Debug)]
+pub(crate) struct DiscountOpt {
+    #[structopt(
+        long,
+        conflicts_with = "discount-amount",
+        help = "percentage taken off the total, e.g. 10 or 12.5"
+    )]
+    discount_percent: Option<f64>,
+    #[structopt(long, help = "fixed amount taken off the total, e.g. 2.50")]
+    discount_amount: Option<Money>,
+    #[structopt(
+        long,
+        parse(try_from_str = parse_tax_rate),
+        help = "tax added to the discounted total, as 10, 10.0 or 0.10 [default: from the config file]"
+    )]
+    pub(crate) tax_rate: Option<i64>,
+}
+
+impl DiscountOpt {
+    pub(crate) fn amount(&self, gross: i64) -> Result<i64, AppError> {
+        let discount = match (self.discount_percent, self.discount_amount) {
+            (Some(percent), _) => {
+                if !percent.is_finite() || percent < 0.0 {
+                    return Err(AppError::InvalidDiscount(format!("{}%", percent)));
+                }
+                let hundredths = (percent * 100.0).round() as i128;
+                (gross as i128 * hundredths + 5_000) / 10_000
+            }
+            (None, Some(amount)) => {
+                if amount.cents() < 0 {
+                    return Err(AppError::InvalidDiscount(amount.to_string()));
+                }
+                amount.cents() as i128
+            }
+            (None, None) => 0,
+        };
+        Ok(discount.clamp(0, gross.max(0) as i128) as i64)
+    }
+}
+
+// Which items reprice changes. 86'd items are included, as they come back on
+// the menu at whatever price they have.
+#[derive(StructOpt, Debug)]
+pub(crate) struct RepriceItems {
+    #[structopt(
+        long,
+        use_delimiter = true,
+        parse(try_from_str = parse_id_range),
+        value_name = "IDS",
+        help = "only these ids, e.g. 1,4,9-12"
+    )]
+    ids: Vec<std::ops::RangeInclusive<i64>>,
+    #[structopt(long, help = "only items in this category")]
+    category: Option<String>,
+    #[structopt(long, help = "only items whose name contains this, ignoring case")]
+    name_contains: Option<String>,
+    #[structopt(
+        long,
+        required_unless_one = &["ids", "category", "name-contains"],
+        conflicts_with_all = &["ids", "category", "name-contains"],
+        help = "every item on the menu"
+    )]
+    all: bool,
+}
+
+impl RepriceItems {
+    pub(crate) fn matches(&self, food: &Food) -> bool {
+        self.all
+            || ((self.ids.is_empty() || self.ids.iter().any(|range| range.contains(&food.id)))
+                && self.category.as_ref().is_none_or(|category| food.category.eq_ignore_ascii_case(category))
+                && self.name_contains.as_ref().is_none_or(|part| SearchMode::substring(part).matches(food)))
+    }
+}
+
+#[derive(StructOpt, Debug, Default)]
+pub(crate) struct FoodFilter {
+    #[structopt(long, help = "only show items in this category")]
+    pub(crate) category: Option<String>,
+    #[structopt(long, help = "include items that are 86'd")]
+    pub(crate) all: bool,
+    #[structopt(long, help = "only show items with this tag")]
+    pub(crate) tag: Option<String>,
+    #[structopt(flatten)]
+    pub(crate) range: RangeFilter,
+    #[structopt(long, help = "only show items whose name contains this, ignoring case")]
+    name_contains: Option<String>,
+}
+
+impl FoodFilter {
+    pub(crate) fn matches(&self, food: &Food) -> bool {
+        (self.all || food.available)
+            && self.tag.as_ref().is_none_or(|tag| food.has_tag(tag))
+            && self.category.as_ref().is_none_or(|category| food.category.eq_ignore_ascii_case(category))
+            && self.range.matches(food)
+            && self.name_contains.as_ref().is_none_or(|part| SearchMode::substring(part).matches(food))
+    }
+}
+
+// The price and stock bounds shared by list and search.
+#[derive(StructOpt, Debug, Default)]
+pub(crate) struct RangeFilter {
+    #[structopt(long, help = "only show items priced at or above this")]
+    min_price: Option<Money>,
+    #[structopt(long, help = "only show items priced at or below this")]
+    max_price: Option<Money>,
+    #[structopt(long, help = "only show items with at least this much stock")]
+    pub(crate) min_stock: Option<Quantity>,
+    #[structopt(long, help = "only show items with at most this much stock")]
+    max_stock: Option<Quantity>,
+}
+
+impl RangeFilter {
+    pub(crate) fn matches(&self, food: &Food) -> bool {
+        self.min_price.is_none_or(|price| food.price >= price)
+            && self.max_price.is_none_or(|price| food.price <= price)
+            && self.min_stock.is_none_or(|stock| food.stock >= stock)
+            && self.max_stock.is_none_or(|stock| food.stock <= stock)
+    }
+}
+
+#[derive(StructOpt, Debug, Default, Clone)]
+pub(crate) struct FoodDetails {
+    #[structopt(long, help = "menu section such as mains or drinks [default: uncategorized]")]
+    pub(crate) category: Option<String>,
+    #[structopt(long, help = "what one unit costs us to buy or make")]
+    pub(crate) cost: Option<Money>,
+    #[structopt(long, help = "reorder point; low-stock lists the item at or below this [default: 0]")]
+    pub(crate) min_stock: Option<Quantity>,
+    #[structopt(long, parse(try_from_str = parse_date), help = "best-before date as YYYY-MM-DD")]
+    pub(crate) expires: Option<NaiveDate>,
+    #[structopt(long, help = "who we buy the item from")]
+    pub(crate) supplier: Option<String>,
+    #[structopt(long, help = "barcode or stock-keeping code, unique per item; empty clears it")]
+    pub(crate) sku: Option<String>,
+    #[structopt(long, help = "semicolon-separated allergens, e.g. \"nuts;dairy\"")]
+    pub(crate) allergens: Option<String>,
+    #[structopt(long, help = "unit stock is counted in; kg, g, l and ml allow fractions [default: pcs]")]
+    pub(crate) unit: Option<String>,
+    #[structopt(long, help = "free-text description, shown by show")]
+    pub(crate) notes: Option<String>,
+}
+
+impl FoodDetails {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.category.is_none()
+            && self.cost.is_none()
+            && self.min_stock.is_none()
+            && self.expires.is_none()
+            && self.supplier.is_none()
+            && self.sku.is_none()
+            && self.allergens.is_none()
+            && self.unit.is_none()
+            && self.notes.is_none()
+    }
+
+    pub(crate) fn validate(&self, index: &FoodIndex, id: i64) -> Result<(), AppError> {
+        if let Some(cost) = self.cost.filter(|cost| cost.cents() < 0) {
+            return Err(AppError::InvalidCost(cost));
+        }
+        if let Some(sku) = self.sku.as_deref().filter(|sku| !sku.is_empty()) {
+            if let Some(&owner) = index.skus.get(sku).filter(|&&owner| owner != id) {
+                return Err(AppError::DuplicateSku { sku: sku.to_owned(), id: owner });
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn apply(self, food: &mut Food) {
+        if let Some(category) = self.category {
+            food.category = category;
+        }
+        if self.cost.is_some() {
+            food.cost = self.cost;
+        }
+        if let Some(min_stock) = self.min_stock {
+            food.min_stock = min_stock;
+        }
+        if self.expires.is_some() {
+            food.expires = self.expires;
+        }
+        if let Some(supplier) = self.supplier {
+            food.supplier = supplier;
+        }
+        if let Some(sku) = self.sku {
+            food.sku = Some(sku).filter(|sku| !sku.is_empty());
+        }
+        if let Some(allergens) = self.allergens {
+            food.allergens = parse_tags(&allergens);
+        }
+        if let Some(unit) = self.unit {
+            food.unit = unit.trim().to_lowercase();
+        }
+        if let Some(notes) = self.notes {
+            food.notes = notes;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn percent_off(percent: f64) -> DiscountOpt {
+        DiscountOpt { discount_percent: Some(percent), discount_amount: None, tax_rate: None }
+    }
+
+    #[test]
+    fn percent_discounts_round_half_a_cent_up() {
+        assert_eq!(percent_off(10.0).amount(1999).unwrap(), 200);
+        assert_eq!(percent_off(12.5).amount(1999).unwrap(), 250);
+        assert_eq!(percent_off(15.0).amount(333).unwrap(), 50);
+        assert_eq!(percent_off(50.0).amount(1).unwrap(), 1);
+        assert_eq!(percent_off(10.0).amount(4).unwrap(), 0);
+    }
+
+    #[test]
+    fn discounts_stay_within_the_total() {
+        assert_eq!(percent_off(150.0).amount(1999).unwrap(), 1999);
+        let fixed = DiscountOpt { discount_percent: None, discount_amount: Some(Money(5000)), tax_rate: None };
+        assert_eq!(fixed.amount(1999).unwrap(), 1999);
+        assert_eq!(DiscountOpt { discount_percent: None, discount_amount: None, tax_rate: None }.amount(1999).unwrap(), 0);
+    }
+
+    #[test]
+    fn bad_discounts_are_rejected() {
+        assert!(matches!(percent_off(-5.0).amount(1999), Err(AppError::InvalidDiscount(_))));
+        assert!(matches!(percent_off(f64::NAN).amount(1999), Err(AppError::InvalidDiscount(_))));
+        let negative = DiscountOpt { discount_percent: None, discount_amount: Some(Money(-100)), tax_rate: None };
+        assert!(matches!(negative.amount(1999), Err(AppError::InvalidDiscount(_))));
+    }
+}
@@ -0,0 +1,260 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use crate::error::{AppError, ParseError};
+use crate::model::{diff_foods, Food, Quantity, FOOD_HEADER};
+use crate::output::Format;
+use crate::storage::{
+    backup_dir, backup_file, check_checksum, decrypt_data, encrypt_data, find_backup, foods_from_json, foods_from_jsonl,
+    foods_to_json, foods_to_jsonl, is_stdio, list_backups, load_foods, open_data_file, save_atomically, write_checksum,
+    Backend, FoodRecords, Storage, ENCRYPTED_MAGIC, SCHEMA_VERSION,
+};
+
+use super::{confirm, read_input, write_report, Command, Context};
+
+fn format_age(age: std::time::Duration) -> String {
+    let seconds = age.as_secs();
+    match seconds {
+        0..=59 => format!("{}s ago", seconds),
+        60..=3599 => format!("{}m ago", seconds / 60),
+        3600..=86399 => format!("{}h ago", seconds / 3600),
+        _ => format!("{}d ago", seconds / 86400),
+    }
+}
+
+// Backups, migrations and the data file itself, importing and exporting.
+pub(super) fn execute(cmd: Command, context: &Context, store: &mut dyn Storage) -> Result<(), AppError> {
+    let csv_opts = context.csv_opts;
+    let data_file = context.data_file.clone();
+    match cmd {
+        Command::Backups => {
+            let backups = list_backups(&data_file)?;
+            if backups.is_empty() {
+                println!("No backups in {}", backup_dir(&data_file).display());
+            }
+            for backup in backups.iter().rev() {
+                let metadata = std::fs::metadata(backup)?;
+                let age = metadata.modified()?.elapsed().unwrap_or_default();
+                println!("{:<40} {:>10} bytes  {}", backup.display(), metadata.len(), format_age(age));
+            }
+        }
+        Command::Restore { which } => {
+            let backup = find_backup(&data_file, which.as_deref())?;
+            let (restored, warnings) = load_foods(backup.clone(), &csv_opts)?;
+            csv_opts.report(&warnings);
+            let current = store.load_foods()?;
+            println!("Restoring {} would change:\n{}", backup.display(), diff_foods(&current, &restored)?);
+            if !confirm("Replace the data file?", context.yes)? {
+                println!("Restore cancelled");
+                return Ok(());
+            }
+            if context.dry_run {
+                return Ok(());
+            }
+
+            let bytes = std::fs::read(&backup)?;
+            if data_file.exists() {
+                let keep = if csv_opts.keep_backups == 0 { usize::MAX } else { csv_opts.keep_backups };
+                backup_file(&data_file, keep)?;
+            }
+            save_atomically(&data_file, |file| file.write_all(&bytes))?;
+            write_checksum(&data_file)?;
+            println!("Restored {}", backup.display());
+        }
+        Command::Migrate { csv: Some(csv) } => {
+            let (foods, warnings) = load_foods(csv.clone(), &csv_opts)?;
+            csv_opts.report(&warnings);
+            if !store.load_foods()?.inner.is_empty() {
+                return Err(AppError::StoreNotEmpty(data_file));
+            }
+            let count = foods.inner.len();
+            if context.dry_run {
+                println!("would migrate {} item(s) from {} into {}", count, csv.display(), data_file.display());
+                return Ok(());
+            }
+            store.save_foods(foods)?;
+            println!("migrated {} item(s) from {} into {}", count, csv.display(), data_file.display());
+        }
+        Command::Migrate { csv: None } => {
+            if context.backend.unwrap_or_else(|| Backend::detect(&data_file)) != Backend::Csv {
+                println!("Nothing to migrate, only CSV data files carry a schema version");
+                return Ok(());
+            }
+            if !data_file.exists() {
+                return Err(AppError::NoDataFile(data_file));
+            }
+            let records = FoodRecords::new(open_data_file(&data_file)?, &csv_opts)?;
+            csv_opts.report(&records.warnings);
+            let mut changes = Vec::new();
+            if records.schema < SCHEMA_VERSION {
+                changes.push(format!("schema {} -> {}", records.schema, SCHEMA_VERSION));
+            }
+            if records.pending.is_some() {
+                changes.push("adds a header row".to_owned());
+            }
+            let missing: Vec<&str> = FOOD_HEADER
+                .into_iter()
+                .filter(|column| !records.headers.iter().any(|header| header == *column))
+                .collect();
+            if !missing.is_empty() {
+                changes.push(format!("adds columns: {}", missing.join(", ")));
+            }
+            let positions: Vec<usize> = records
+                .headers
+                .iter()
+                .filter_map(|header| FOOD_HEADER.iter().position(|column| *column == header))
+                .collect();
+            if !positions.is_sorted() {
+                changes.push("reorders columns".to_owned());
+            }
+
+            if changes.is_empty() {
+                println!("{} is already at schema {}", data_file.display(), SCHEMA_VERSION);
+                return Ok(());
+            }
+            println!("{}:", data_file.display());
+            for change in changes {
+                println!("  {}", change);
+            }
+            if context.dry_run {
+                return Ok(());
+            }
+            let fds = store.load_foods()?;
+            let count = fds.inner.len();
+            store.save_foods(fds)?;
+            println!("Migrated {} item(s) to schema {}", count, SCHEMA_VERSION);
+        }
+        Command::Encrypt | Command::Decrypt => {
+            if !data_file.exists() {
+                return Err(AppError::NoDataFile(data_file));
+            }
+            let data = std::fs::read(&data_file)?;
+            let encrypt = matches!(cmd, Command::Encrypt);
+            if data.starts_with(ENCRYPTED_MAGIC) == encrypt {
+                let state = if encrypt { "already" } else { "not" };
+                println!("{} is {} encrypted", data_file.display(), state);
+                return Ok(());
+            }
+            let data = if encrypt { encrypt_data(&data, true)? } else { decrypt_data(&data)? };
+            if !encrypt && csv_opts.keep_backups > 0 {
+                backup_file(&data_file, csv_opts.keep_backups)?;
+            }
+            save_atomically(&data_file, |file| file.write_all(&data))?;
+            write_checksum(&data_file)?;
+            if encrypt {
+                println!("Encrypted {}", data_file.display());
+                if !list_backups(&data_file)?.is_empty() {
+                    println!("Older backups in {} are still readable, delete them if needed", backup_dir(&data_file).display());
+                }
+            } else {
+                println!("Decrypted {}", data_file.display());
+            }
+        }
+        Command::Verify => {
+            if !data_file.exists() {
+                return Err(AppError::NoDataFile(data_file));
+            }
+            if csv_opts.accept_changes {
+                write_checksum(&data_file)?;
+                println!("Accepted the current contents of {}", data_file.display());
+                return Ok(());
+            }
+            match check_checksum(&data_file)? {
+                Some(true) => println!("{} matches its checksum", data_file.display()),
+                Some(false) => return Err(ParseError::ExternalChange(data_file).into()),
+                None => println!("{} has no checksum yet, one is written on the next save", data_file.display()),
+            }
+        }
+        Command::Validate => {
+            let mut issues = Vec::new();
+            let mut foods = Vec::new();
+            if context.backend.unwrap_or_else(|| Backend::detect(&data_file)) == Backend::Csv {
+                if !is_stdio(&data_file) && !data_file.exists() {
+                    return Err(AppError::NoDataFile(data_file));
+                }
+                let mut records = FoodRecords::new(open_data_file(&data_file)?, &csv_opts)?;
+                csv_opts.report(&std::mem::take(&mut records.warnings));
+                while let Some(food) = records.next() {
+                    csv_opts.report(&std::mem::take(&mut records.warnings));
+                    match food {
+                        Ok(food) => foods.push(food),
+                        Err(e) if e.is_fatal() => return Err(e.into()),
+                        Err(e) => issues.push(e.to_string()),
+                    }
+                }
+            } else {
+                foods = store.load_foods()?.into_vec();
+            }
+
+            let mut by_id: BTreeMap<i64, Vec<&Food>> = BTreeMap::new();
+            for food in &foods {
+                by_id.entry(food.id).or_default().push(food);
+                if food.name.trim().is_empty() {
+                    issues.push(format!("item {} has no name", food.id));
+                }
+                if food.stock < Quantity(0) {
+                    issues.push(format!("item {} ({}) has negative stock {}", food.id, food.name, food.stock));
+                }
+                if food.price.0 < 0 {
+                    issues.push(format!("item {} ({}) has negative price {}", food.id, food.name, food.price));
+                }
+            }
+            for (id, rows) in by_id.iter().filter(|(_, rows)| rows.len() > 1) {
+                let names: Vec<String> = rows.iter().map(|food| format!("{:?}", food.name)).collect();
+                issues.push(format!("id {} is used by {} rows: {}", id, rows.len(), names.join(", ")));
+            }
+
+            for issue in &issues {
+                println!("{}", issue);
+            }
+            let mut previous = 0;
+            for &id in by_id.keys() {
+                match id - previous {
+                    ..=1 => {}
+                    2 => println!("note: id {} is unused", previous + 1),
+                    _ => println!("note: ids {}-{} are unused", previous + 1, id - 1),
+                }
+                previous = id;
+            }
+            if !issues.is_empty() {
+                return Err(AppError::ValidationFailed(issues.len()));
+            }
+            println!("No issues found in {} item(s)", foods.len());
+        }
+        Command::Export { out, overwrite } => {
+            let fds = store.load_foods()?;
+            let text = match context.requested_format.unwrap_or(Format::Json) {
+                Format::Json => foods_to_json(fds.sorted())?,
+                Format::Jsonl => foods_to_jsonl(fds.sorted())?,
+                format => return Err(AppError::UnsupportedFormat { command: "export", format: format.name() }),
+            };
+            write_report(Some(out.clone()), overwrite, &text)?;
+            if out.as_os_str() != "-" {
+                println!("exported {} item(s) to {}", fds.inner.len(), out.display());
+            }
+        }
+        Command::Import { input, merge, replace } => {
+            let text = read_input(&input)?;
+            let foods = match context.requested_format.unwrap_or(Format::Json) {
+                Format::Json => foods_from_json(&text)?,
+                Format::Jsonl => foods_from_jsonl(&text)?,
+                format => return Err(AppError::UnsupportedFormat { command: "import", format: format.name() }),
+            };
+            let mut fds = store.load_foods()?;
+            let replace = replace && !merge;
+            let before = fds.clone();
+            let (added, skipped) = fds.import(foods, replace)?;
+            if replace && !before.inner.is_empty() {
+                println!("Replacing the menu would change:\n{}", diff_foods(&before, &fds)?);
+                if !confirm("Replace the menu?", context.yes)? {
+                    println!("Import cancelled");
+                    return Ok(());
+                }
+            }
+            store.save_foods(fds)?;
+            println!("imported {} item(s), skipped {} existing id(s)", added, skipped);
+        }
+        cmd => unreachable!("{:?} is not a file command", cmd),
+    }
+    Ok(())
+}
@@ -0,0 +1,399 @@
+use std::collections::BTreeMap;
+
+use chrono::prelude::*;
+
+use crate::error::AppError;
+use crate::fuzzy;
+use crate::model::{join_tags, sort_foods, Food, SearchField, SearchMode, SortKey, SortOrder, FUZZY_LIMIT};
+use crate::output::{self, Format};
+use crate::storage::{food_to_json, foods_to_jsonl, Storage};
+
+use super::{Command, Context};
+
+fn page_range(offset: usize, limit: usize) -> std::ops::Range<usize> {
+    match limit {
+        0 => offset..usize::MAX,
+        _ => offset..offset.saturating_add(limit),
+    }
+}
+
+// Machine-readable formats get the footer on stderr so it stays out of the data.
+fn print_page_footer(page: &std::ops::Range<usize>, matched: usize, to_stderr: bool) {
+    let shown = page.end.min(matched).saturating_sub(page.start);
+    let footer = match shown {
+        _ if shown == matched => return,
+        0 => format!("showing none of {}", matched),
+        _ => format!("showing {}–{} of {}", page.start + 1, page.start + shown, matched),
+    };
+    if to_stderr {
+        eprintln!("{}", footer);
+    } else {
+        println!("{}", footer);
+    }
+}
+
+// Every detail of one item, as show and lookup print it.
+fn print_details(food: &Food, format: Format) -> Result<(), AppError> {
+    match format {
+        Format::Table => {}
+        // One item, so json is a single object rather than an array.
+        Format::Json => {
+            println!("{}", food_to_json(food)?);
+            return Ok(());
+        }
+        _ => {
+            print!("{}", output::render(&[food], format, false)?);
+            return Ok(());
+        }
+    }
+    let or_dash = |value: &str| if value.is_empty() { "-".to_owned() } else { value.to_owned() };
+    println!("#{} {}{}", food.id, food.name, if food.available { "" } else { " [86]" });
+    println!("  {:<12} {}", "Category", food.category);
+    println!("  {:<12} {}", "Price", food.price);
+    println!(
+        "  {:<12} {}",
+        "Cost",
+        food.cost.map(|cost| cost.to_string()).unwrap_or_else(|| "-".to_owned())
+    );
+    println!("  {:<12} {} {} (min {})", "Stock", food.stock, food.unit, food.min_stock);
+    println!("  {:<12} {}", "Sold", food.sold_count);
+    println!(
+        "  {:<12} {}",
+        "Expires",
+        food.expires.map(|expires| expires.to_string()).unwrap_or_else(|| "-".to_owned())
+    );
+    println!("  {:<12} {}", "Supplier", or_dash(&food.supplier));
+    println!("  {:<12} {}", "SKU", or_dash(food.sku.as_deref().unwrap_or_default()));
+    println!("  {:<12} {}", "Allergens", or_dash(&join_tags(&food.allergens)));
+    println!("  {:<12} {}", "Tags", or_dash(&join_tags(&food.tags)));
+    match food.special_until.filter(|_| food.is_special(Local::now().date_naive())) {
+        Some(until) => println!("  {:<12} until {}", "Special", until),
+        None => println!("  {:<12} -", "Special"),
+    }
+    let stamp = |stamp: Option<DateTime<Local>>| match stamp {
+        Some(stamp) => stamp.format("%Y-%m-%d %H:%M:%S").to_string(),
+        None => "-".to_owned(),
+    };
+    println!("  {:<12} {}", "Created", stamp(food.created_at));
+    println!("  {:<12} {}", "Updated", stamp(food.updated_at));
+    for (column, value) in &food.extra {
+        println!("  {:<12} {}", column, or_dash(value));
+    }
+    if food.notes.is_empty() {
+        println!("  {:<12} -", "Notes");
+    } else {
+        println!("  Notes");
+        for line in food.notes.lines() {
+            println!("    {}", line);
+        }
+    }
+    Ok(())
+}
+
+// The commands that only read the menu.
+pub(super) fn execute(cmd: Command, context: &Context, store: &mut dyn Storage) -> Result<(), AppError> {
+    let format = context.format;
+    match cmd {
+        Command::List { sort, desc, margin, grouped, filter, limit, offset } => {
+            let today = Local::now().date_naive();
+            if sort == SortKey::File && !grouped {
+                let mut buffered = Vec::new();
+                let (page, mut matched, mut total) = (page_range(offset, limit), 0, 0);
+                store.stream_foods(&mut |food| {
+                    total += 1;
+                    if !filter.matches(&food) {
+                        return Ok(());
+                    }
+                    matched += 1;
+                    if page.contains(&(matched - 1)) {
+                        match format {
+                            Format::Jsonl => print!("{}", foods_to_jsonl([&food])?),
+                            Format::Debug => println!("{}", output::debug_line(&food, margin, today)),
+                            _ => buffered.push(food),
+                        }
+                    }
+                    Ok(())
+                })?;
+                if matched == 0 && !format.is_data() {
+                    println!("{}", if total == 0 { "No items yet" } else { "No items match" });
+                    return Ok(());
+                }
+                if !matches!(format, Format::Jsonl | Format::Debug) && (page.start < matched || format.is_data()) {
+                    print!("{}", output::render(&buffered.iter().collect::<Vec<_>>(), format, margin)?);
+                }
+                print_page_footer(&page, matched, format.is_data());
+                return Ok(());
+            }
+
+            let fds = store.load_foods()?;
+            let order = if desc { SortOrder::Desc } else { sort.default_order() };
+            let mut foods = fds.filter(|food| filter.matches(food));
+            sort_foods(&mut foods, sort, order);
+            if foods.is_empty() && !format.is_data() {
+                println!("{}", if fds.inner.is_empty() { "No items yet" } else { "No items match" });
+                return Ok(());
+            }
+            let (page, matched) = (page_range(offset, limit), foods.len());
+            let foods: Vec<&Food> = foods.into_iter().skip(page.start).take(page.len()).collect();
+            if foods.is_empty() && !format.is_data() {
+                print_page_footer(&page, matched, false);
+                return Ok(());
+            }
+            if grouped && !format.is_data() {
+                let mut sections: BTreeMap<String, Vec<&Food>> = BTreeMap::new();
+                for food in foods {
+                    sections.entry(food.category.clone()).or_default().push(food);
+                }
+                for (category, foods) in sections {
+                    println!("== {} ==", category);
+                    print!("{}", output::render(&foods, format, margin)?);
+                }
+            } else {
+                print!("{}", output::render(&foods, format, margin)?);
+            }
+            print_page_footer(&page, matched, format.is_data());
+        }
+        Command::Dupes => {
+            let fds = store.load_foods()?;
+            let groups = fds.duplicate_names();
+            if format != Format::Table {
+                print!("{}", output::render(&groups.concat(), format, false)?);
+                return Ok(());
+            }
+            if groups.is_empty() {
+                println!("No two items share a name");
+            }
+            for group in groups {
+                let ids: Vec<String> = group.iter().map(|food| food.id.to_string()).collect();
+                println!("{}: ids {}", group[0].name, ids.join(", "));
+            }
+        }
+        Command::Stats => {
+            let stats = store.load_foods()?.stats();
+            match format {
+                Format::Table => {
+                    for (label, value) in stats.rows() {
+                        println!("{:<16} {}", label, value);
+                    }
+                }
+                Format::Csv => {
+                    let mut writer = csv::Writer::from_writer(std::io::stdout());
+                    writer.write_record(["stat", "value"])?;
+                    for (label, value) in stats.rows() {
+                        writer.write_record([label, value.as_str()])?;
+                    }
+                    writer.flush()?;
+                }
+                Format::Json => println!("{}", serde_json::to_string_pretty(&stats)?),
+                Format::Jsonl => println!("{}", serde_json::to_string(&stats)?),
+                Format::Debug => println!("{:?}", stats),
+            }
+        }
+        Command::CompleteIds => {
+            for id in store.load_foods()?.inner.keys() {
+                println!("{}", id);
+            }
+        }
+        Command::LowStock { threshold, ignore_disabled } => {
+            let fds = store.load_foods()?;
+            let mut low = fds.low_stock(threshold);
+            low.retain(|food| food.available || !ignore_disabled);
+            if format != Format::Table {
+                print!("{}", output::render(&low, format, false)?);
+                return if low.is_empty() { Ok(()) } else { Err(AppError::LowStock(low.len())) };
+            }
+            if low.is_empty() {
+                println!("All items are above their minimum stock");
+                return Ok(());
+            }
+            println!("{:<6} {:<24} {:>8} {:>8} {:>8}", "Id", "Item", "Stock", "Min", "Short");
+            for food in low.iter() {
+                let min = threshold.unwrap_or(food.min_stock);
+                println!("{:<6} {:<24} {:>8} {:>8} {:>8}", food.id, food.name, food.stock, min, min - food.stock);
+            }
+            return Err(AppError::LowStock(low.len()));
+        }
+        Command::OutOfStock { ignore_disabled } => {
+            let fds = store.load_foods()?;
+            let mut out = fds.out_of_stock();
+            out.retain(|food| food.available || !ignore_disabled);
+            if out.is_empty() && format == Format::Table {
+                println!("Nothing is out of stock");
+                return Ok(());
+            }
+            print!("{}", output::render(&out, format, false)?);
+            return if out.is_empty() { Ok(()) } else { Err(AppError::OutOfStock(out.len())) };
+        }
+        Command::Allergen { name } => {
+            let fds = store.load_foods()?;
+            let foods = fds.with_allergen(&name);
+            if format != Format::Table {
+                print!("{}", output::render(&foods, format, false)?);
+                return Ok(());
+            }
+            if foods.is_empty() {
+                println!("No items contain {}", name.trim().to_lowercase());
+            }
+            for food in foods {
+                println!("{:<6} {:<24} {}", food.id, food.name, join_tags(&food.allergens));
+            }
+        }
+        Command::Lookup { sku } => {
+            let fds = store.load_foods()?;
+            let food = fds.by_sku(&sku)?;
+            print_details(food, format)?;
+        }
+        Command::ReorderList { below } => {
+            let fds = store.load_foods()?;
+            let low = match below {
+                Some(below) => {
+                    let mut foods: Vec<&Food> = fds.inner.values().filter(|food| food.stock < below).collect();
+                    foods.sort_by_key(|food| (food.stock, food.id));
+                    foods
+                }
+                None => fds.low_stock(None),
+            };
+            if format != Format::Table {
+                print!("{}", output::render(&low, format, false)?);
+                return Ok(());
+            }
+            if low.is_empty() {
+                println!("Nothing to reorder");
+                return Ok(());
+            }
+
+            let mut suppliers: BTreeMap<&str, Vec<&Food>> = BTreeMap::new();
+            for food in low {
+                let supplier = match food.supplier.as_str() {
+                    "" => "(unassigned)",
+                    supplier => supplier,
+                };
+                suppliers.entry(supplier).or_default().push(food);
+            }
+            for (supplier, foods) in suppliers {
+                println!("== {} ==", supplier);
+                println!("  {:<6} {:<24} {:>8} {:>8}", "Id", "Item", "Stock", "Min");
+                for food in foods {
+                    println!("  {:<6} {:<24} {:>8} {:>8}", food.id, food.name, food.stock, food.min_stock);
+                }
+                println!();
+            }
+        }
+        Command::Expiring { days } => {
+            let fds = store.load_foods()?;
+            let today = Local::now().date_naive();
+            let foods = fds.expiring_between(Some(today), today + chrono::Duration::days(days as i64));
+            if format != Format::Table {
+                print!("{}", output::render(&foods, format, false)?);
+                return Ok(());
+            }
+            if foods.is_empty() {
+                println!("Nothing expires in the next {} day(s)", days);
+            }
+            for food in foods {
+                let expires = food.expires.unwrap_or(today);
+                println!(
+                    "{:<6} {:<24} {:>8}  expires {} (in {} day(s))",
+                    food.id, food.name, food.stock, expires, (expires - today).num_days()
+                );
+            }
+        }
+        Command::Expired => {
+            let fds = store.load_foods()?;
+            let today = Local::now().date_naive();
+            let foods = fds.expiring_between(None, today - chrono::Duration::days(1));
+            if format != Format::Table {
+                print!("{}", output::render(&foods, format, false)?);
+                return Ok(());
+            }
+            if foods.is_empty() {
+                println!("Nothing has expired");
+            }
+            for food in foods {
+                let expires = food.expires.unwrap_or(today);
+                println!(
+                    "{:<6} {:<24} {:>8}  expired {} ({} day(s) ago)",
+                    food.id, food.name, food.stock, expires, (today - expires).num_days()
+                );
+            }
+        }
+        Command::Search {
+            query,
+            exact,
+            prefix,
+            regex,
+            fuzzy,
+            case_sensitive,
+            mut fields,
+            category,
+            include_notes,
+            tag,
+            range,
+        } => {
+            let mode = SearchMode::new(&query, exact, prefix, regex, fuzzy, case_sensitive)?;
+            if fields.is_empty() {
+                fields.push(SearchField::Name);
+            }
+            fields.extend(category.then_some(SearchField::Category));
+            fields.extend(include_notes.then_some(SearchField::Notes));
+            let (mut total, mut found, mut suggestions) = (0, Vec::new(), Vec::new());
+            store.stream_foods(&mut |food| {
+                total += 1;
+                if food.matches(&mode, &fields) && tag.as_ref().is_none_or(|tag| food.has_tag(tag)) && range.matches(&food) {
+                    found.push(food);
+                } else if found.is_empty() && !fuzzy {
+                    let score = fuzzy::score(&query, &food.name);
+                    if score >= fuzzy::THRESHOLD {
+                        suggestions.push((score, food.id, food.name));
+                    }
+                }
+                Ok(())
+            })?;
+            if found.is_empty() && !format.is_data() {
+                println!("No records found among {} items", total);
+                suggestions.sort_by(|a, b| b.0.total_cmp(&a.0));
+                for (_, id, name) in suggestions.into_iter().take(3) {
+                    println!("did you mean: {} (id {})?", name, id);
+                }
+                return Ok(());
+            }
+            match fuzzy {
+                true => {
+                    found.sort_by(|a, b| fuzzy::score(&query, &b.name).total_cmp(&fuzzy::score(&query, &a.name)));
+                    found.truncate(FUZZY_LIMIT);
+                }
+                false => found.sort_by_key(|food| mode.rank(&food.name)),
+            }
+            match format {
+                Format::Jsonl => print!("{}", foods_to_jsonl(found.iter())?),
+                Format::Debug => found.iter().for_each(|food| println!("{:?}", food)),
+                _ => print!("{}", output::render_matches(&found.iter().collect::<Vec<_>>(), format, false, mode.highlight())?),
+            }
+            let footer = format!("{} of {} items matched", found.len(), total);
+            match format.is_data() {
+                true => eprintln!("{}", footer),
+                false => println!("{}", footer),
+            }
+        }
+        Command::Tags => {
+            let fds = store.load_foods()?;
+            let counts = fds.tag_counts();
+            if counts.is_empty() {
+                println!("No tags in use");
+            }
+            for (tag, count) in counts {
+                println!("{:<24} {:>6}", tag, count);
+            }
+        }
+        Command::Show { id } => {
+            let fds = store.load_foods()?;
+            let food = match fds.inner.get(&id) {
+                Some(food) => food,
+                None => return Err(AppError::NotFound(id)),
+            };
+            print_details(food, format)?;
+        }
+        cmd => unreachable!("{:?} is not a listing command", cmd),
+    }
+    Ok(())
+}
@@ -0,0 +1,430 @@
+use std::collections::HashMap;
+
+use chrono::prelude::*;
+
+use crate::currency;
+use crate::error::{AppError, ParseError};
+use crate::model::{format_ids, join_tags, name_key, Food, FoodIndex, Money, Quantity, Waste};
+use crate::storage::{load_wastes, save_wastes, write_all_or_nothing, Storage};
+use crate::table;
+
+use super::{confirm, read_input, Command, Context};
+
+struct BatchRow {
+    pub(crate) line: u64,
+    pub(crate) name: String,
+    pub(crate) stock: Quantity,
+    pub(crate) price: Money,
+}
+
+struct Delivery {
+    pub(crate) line: u64,
+    pub(crate) id: i64,
+    pub(crate) quantity: Quantity,
+}
+
+struct Batch<T> {
+    pub(crate) rows: Vec<T>,
+    // (line, reason) for every row that could not be used
+    pub(crate) rejected: Vec<(u64, String)>,
+}
+
+// The csv position of a record that follows blank lines points at the first
+// blank line, so skip those before counting.
+fn line_of(text: &str, offset: usize) -> u64 {
+    let rest = &text[offset..];
+    let start = offset + rest.len() - rest.trim_start_matches(['\r', '\n']).len();
+    text[..start].matches('\n').count() as u64 + 1
+}
+
+// Blank lines and a first line naming the columns are skipped.
+fn read_rows<T>(
+    text: &str,
+    header: &[&str],
+    parse: impl Fn(u64, &[&str]) -> Result<T, String>,
+) -> Result<Batch<T>, AppError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(text.as_bytes());
+    let mut batch = Batch { rows: Vec::new(), rejected: Vec::new() };
+    for (i, record) in reader.records().enumerate() {
+        let record = record?;
+        let line = record.position().map_or(i as u64 + 1, |position| line_of(text, position.byte() as usize));
+        if i == 0 && record.iter().map(str::to_lowercase).eq(header.iter().copied()) {
+            continue;
+        }
+        let fields: Vec<&str> = record.iter().collect();
+        if fields == [""] {
+            continue;
+        }
+        match parse(line, &fields) {
+            Ok(row) => batch.rows.push(row),
+            Err(reason) => batch.rejected.push((line, reason)),
+        }
+    }
+    Ok(batch)
+}
+
+fn read_batch(text: &str) -> Result<Batch<BatchRow>, AppError> {
+    read_rows(text, &["name", "stock", "price"], |line, fields| match fields {
+        ["", _, _] => Err("name is empty".to_owned()),
+        [name, stock, price] => stock
+            .parse::<Quantity>()
+            .and_then(|stock| Ok((stock, price.parse::<Money>()?)))
+            .map(|(stock, price)| BatchRow { line, name: name.to_string(), stock, price })
+            .map_err(|e| e.to_string()),
+        fields => Err(format!("expected name,stock,price but found {} field(s)", fields.len())),
+    })
+}
+
+// Percentages round to the nearest cent, --round-to to the nearest multiple,
+// halves away from zero.
+fn reprice(price: Money, percent: Option<f64>, amount: Option<Money>, round_to: Option<Money>) -> Money {
+    let mut cents = price.0 as i128;
+    if let Some(percent) = percent {
+        let hundredths = (percent * 100.0).round() as i128;
+        let scaled = cents * (10_000 + hundredths);
+        cents = (scaled + scaled.signum() * 5_000) / 10_000;
+    }
+    if let Some(amount) = amount {
+        cents += amount.0 as i128;
+    }
+    if let Some(step) = round_to.map(|step| step.0 as i128).filter(|step| *step > 0) {
+        cents = (cents + cents.signum() * step / 2) / step * step;
+    }
+    Money(cents.clamp(i64::MIN as i128, i64::MAX as i128) as i64)
+}
+
+fn read_deliveries(text: &str) -> Result<Batch<Delivery>, AppError> {
+    read_rows(text, &["id", "quantity"], |line, fields| match fields {
+        [id, quantity] => id
+            .parse::<i64>()
+            .map_err(|e| ParseError::from(e).to_string())
+            .and_then(|id| Ok(Delivery { line, id, quantity: quantity.parse().map_err(|e: ParseError| e.to_string())? })),
+        fields => Err(format!("expected id,quantity but found {} field(s)", fields.len())),
+    })
+}
+
+// Adding, changing and removing items.
+pub(super) fn execute(cmd: Command, context: &Context, store: &mut dyn Storage) -> Result<(), AppError> {
+    let csv_opts = context.csv_opts;
+    let data_file = context.data_file.clone();
+    let waste_file = context.waste_file.clone();
+    match cmd {
+        Command::Add { batch: Some(batch), details, allow_duplicate, merge_stock, .. } => {
+            details.validate(&FoodIndex::default(), 0)?;
+            let Batch { rows, mut rejected } = read_batch(&read_input(&batch)?)?;
+            let mut fds = store.load_foods()?;
+            let existing = fds.index().names;
+            // name_key -> (line, index into foods) of the first row adding it
+            let mut new_rows: HashMap<String, (u64, usize)> = HashMap::new();
+            let (mut duplicates, mut merged) = (0, 0);
+            let mut foods: Vec<Food> = Vec::new();
+            for row in rows {
+                let mut food = Food::new(0, &row.name, row.stock, row.price);
+                details.clone().apply(&mut food);
+                if let Err(e) = food.check_divisible(food.stock) {
+                    rejected.push((row.line, e.to_string()));
+                    continue;
+                }
+                let key = name_key(&food.name);
+                let first = match (existing.get(&key), new_rows.get(&key)) {
+                    (Some((id, _)), _) => Some(format!("item {}", id)),
+                    (None, Some((line, _))) => Some(format!("line {}", line)),
+                    (None, None) => None,
+                };
+                match first {
+                    Some(first) if allow_duplicate => {
+                        duplicates += 1;
+                        eprintln!("warning: line {}: {:?} has the same name as {}", row.line, food.name, first);
+                    }
+                    Some(_) if merge_stock => {
+                        let result = match (existing.get(&key), new_rows.get(&key)) {
+                            (Some((id, _)), _) => fds.restock(*id, food.stock).map(|_| ()),
+                            (None, Some((_, index))) => {
+                                foods[*index].stock += food.stock;
+                                Ok(())
+                            }
+                            (None, None) => Ok(()),
+                        };
+                        match result {
+                            Ok(()) => merged += 1,
+                            Err(e) => rejected.push((row.line, e.to_string())),
+                        }
+                        continue;
+                    }
+                    Some(first) => {
+                        rejected.push((row.line, format!("{:?} has the same name as {}", food.name, first)));
+                        continue;
+                    }
+                    None => {}
+                }
+                new_rows.entry(key).or_insert((row.line, foods.len()));
+                foods.push(food);
+            }
+            rejected.sort();
+            for (line, reason) in &rejected {
+                eprintln!("line {}: {}", line, reason);
+            }
+            if csv_opts.strict && (!rejected.is_empty() || duplicates > 0) {
+                return Err(AppError::BatchRejected { rejected: rejected.len(), duplicates });
+            }
+            let first = fds.next_id();
+            for (id, mut food) in (first..).zip(foods) {
+                food.id = id;
+                fds.add(food);
+            }
+            let added = fds.next_id() - first;
+            if added > 0 || merged > 0 {
+                store.save_foods(fds)?;
+            }
+            if merged > 0 {
+                println!("added the stock of {} row(s) to items of the same name", merged);
+            }
+            match added {
+                0 => println!("added no items, rejected {}", rejected.len()),
+                1 => println!("added 1 item (id {}), rejected {}", first, rejected.len()),
+                _ => println!("added {} items (ids {}-{}), rejected {}", added, first, first + added - 1, rejected.len()),
+            }
+        }
+        Command::Add { name: Some(name), stock: Some(stock), price: Some(price), details, allow_duplicate, merge_stock, .. } => {
+            let index = store.index()?;
+            match index.names.get(&name_key(&name)) {
+                Some(_) if allow_duplicate => {}
+                Some((id, _)) if merge_stock => {
+                    let mut fds = store.load_foods()?;
+                    let (before, food) = fds.restock(*id, stock)?;
+                    let restocked = format!("{} (id {}) stock {} -> {}", food.name, food.id, before, food.stock);
+                    store.save_foods(fds)?;
+                    println!("{}", restocked);
+                    return Ok(());
+                }
+                Some((id, existing)) => return Err(AppError::DuplicateItem { name: existing.clone(), id: *id }),
+                None => {}
+            }
+            details.validate(&index, index.next_id)?;
+            let mut food = Food::new(index.next_id, &name, stock, price);
+            details.apply(&mut food);
+            food.check_divisible(food.stock)?;
+            store.add_food(food)?;
+        }
+        Command::Add { .. } => unreachable!("name, stock and price are required without --batch"),
+        Command::Rename { id, new_name } => {
+            let mut fds = store.load_foods()?;
+            let old = fds.inner.get(&id).ok_or(AppError::NotFound(id))?.name.clone();
+            let key = name_key(&new_name);
+            if let Some(other) = fds.inner.values().find(|food| food.id != id && !key.is_empty() && name_key(&food.name) == key) {
+                if csv_opts.strict {
+                    return Err(AppError::DuplicateName { name: other.name.clone(), id: other.id });
+                }
+                eprintln!("warning: item {} is already called {:?}", other.id, other.name);
+            }
+            let new = fds.edit(id, Some(new_name), None, None, |_| {})?.name.clone();
+            store.save_foods(fds)?;
+            println!("{:?} -> {:?}", old, new);
+        }
+        Command::Edit {id, name, stock, price, upsert, details} => {
+            let renames = id.is_some() && name.is_some();
+            if !renames && stock.is_none() && price.is_none() && details.is_empty() {
+                return Err(AppError::NothingToEdit);
+            }
+            let mut fds = store.load_foods()?;
+            let (id, name) = match (id, name) {
+                (Some(id), name) => (id, name),
+                (None, name) => (fds.by_name(name.as_deref().unwrap_or_default())?.id, None),
+            };
+            details.validate(&fds.index(), id)?;
+            let food = match (name, stock, price) {
+                (Some(name), Some(stock), Some(price)) if upsert && !fds.inner.contains_key(&id) => {
+                    let mut food = Food::new(id, &name, stock, price);
+                    details.apply(&mut food);
+                    fds.add(food);
+                    &fds.inner[&id]
+                }
+                _ if upsert && !fds.inner.contains_key(&id) => return Err(AppError::UpsertIncomplete(id)),
+                (name, stock, price) => fds.edit(id, name, stock, price, |food| details.apply(food))?,
+            };
+            food.check_divisible(food.stock)?;
+            store.save_food(food)?;
+        }
+        Command::Remove { ids, name, missing_ok, quiet } => {
+            let fds = store.load_foods()?;
+            let mut wanted: Vec<i64> = Vec::new();
+            if let Some(name) = name {
+                wanted.push(fds.by_name(&name)?.id);
+            }
+            for id in ids.into_iter().flatten() {
+                if !wanted.contains(&id) {
+                    wanted.push(id);
+                }
+            }
+            let missing: Vec<i64> = wanted.iter().copied().filter(|id| !fds.inner.contains_key(id)).collect();
+            match missing.as_slice() {
+                [] => {}
+                _ if missing_ok => {}
+                [id] if wanted.len() == 1 => return Err(AppError::NotFound(*id)),
+                _ => return Err(AppError::MissingIds(missing)),
+            }
+            let found: Vec<&Food> = wanted.iter().filter_map(|id| fds.inner.get(id)).collect();
+            let prompt = match found.as_slice() {
+                [] => None,
+                [food] => Some(format!("Remove '{}' (id {})?", food.name, food.id)),
+                foods => Some(format!(
+                    "Remove {} items: {}?",
+                    foods.len(),
+                    foods.iter().map(|food| format!("'{}' (id {})", food.name, food.id)).collect::<Vec<_>>().join(", ")
+                )),
+            };
+            if let Some(prompt) = prompt {
+                if !confirm(&prompt, context.yes)? {
+                    println!("Nothing removed");
+                    return Ok(());
+                }
+            }
+            let (removed, missing) = store.remove_foods(&wanted, missing_ok)?;
+            if !quiet {
+                for food in &removed {
+                    println!("removed {} (id {})", food.name, food.id);
+                }
+            }
+            if !missing.is_empty() {
+                println!("no item with id {}", format_ids(&missing));
+            }
+        }
+        Command::Disable { id } => {
+            let mut fds = store.load_foods()?;
+            println!("{} is now 86'd", fds.set_available(id, false)?.name);
+            store.save_foods(fds)?;
+        }
+        Command::Enable { id } => {
+            let mut fds = store.load_foods()?;
+            println!("{} is available again", fds.set_available(id, true)?.name);
+            store.save_foods(fds)?;
+        }
+        Command::Tag { id, add, remove } => {
+            let mut fds = store.load_foods()?;
+            let food = fds.retag(id, &add, &remove)?;
+            match food.tags.is_empty() {
+                true => println!("{} has no tags", food.name),
+                false => println!("{} tags: {}", food.name, join_tags(&food.tags)),
+            }
+            store.save_foods(fds)?;
+        }
+        Command::Special { id, until } => {
+            let mut fds = store.load_foods()?;
+            let until = until.unwrap_or_else(|| Local::now().date_naive());
+            let food = fds.set_special(id, until)?;
+            println!("{} is a special until {}", food.name, until);
+            store.save_foods(fds)?;
+        }
+        Command::Reprice { percent, amount, round_to, items } => {
+            let mut fds = store.load_foods()?;
+            let mut changes = Vec::new();
+            for food in fds.sorted().into_iter().filter(|food| items.matches(food)) {
+                let price = reprice(food.price, percent, amount, round_to);
+                if price < Money::default() {
+                    return Err(AppError::NegativePrice { name: food.name.clone(), price });
+                }
+                if price != food.price {
+                    changes.push((food.id, food.price, price));
+                }
+            }
+            if changes.is_empty() {
+                println!("No prices change");
+                return Ok(());
+            }
+
+            let mut table = table::Table::new(&[
+                ("Id", table::Align::Right),
+                ("Name", table::Align::Left),
+                ("Old", table::Align::Right),
+                ("New", table::Align::Right),
+                ("Change", table::Align::Right),
+            ])
+            .shrink(1);
+            for (id, old, new) in &changes {
+                let row = vec![
+                    id.to_string(),
+                    fds.inner[id].name.clone(),
+                    currency::format(old.0),
+                    currency::format(new.0),
+                    currency::format(new.0 - old.0),
+                ];
+                table.push_colored(row, None);
+            }
+            print!("{}", table.render(None));
+            if !confirm(&format!("Change {} price(s)?", changes.len()), context.yes)? {
+                println!("Prices kept");
+                return Ok(());
+            }
+            let now = Local::now();
+            for (id, _, new) in &changes {
+                if let Some(food) = fds.inner.get_mut(id) {
+                    food.price = *new;
+                    food.updated_at = Some(now);
+                }
+            }
+            store.save_foods(fds)?;
+            println!("repriced {} item(s)", changes.len());
+        }
+        Command::Restock { batch: Some(batch), .. } => {
+            let Batch { rows, mut rejected } = read_deliveries(&read_input(&batch)?)?;
+            let mut fds = store.load_foods()?;
+            let mut restocked = Vec::new();
+            for row in rows {
+                match fds.restock(row.id, row.quantity) {
+                    Ok((before, food)) => restocked.push(format!("{}: {} -> {}", food.name, before, food.stock)),
+                    Err(e) => rejected.push((row.line, e.to_string())),
+                }
+            }
+            rejected.sort();
+            for (line, reason) in &rejected {
+                eprintln!("line {}: {}", line, reason);
+            }
+            if csv_opts.strict && !rejected.is_empty() {
+                return Err(AppError::BatchRejected { rejected: rejected.len(), duplicates: 0 });
+            }
+            if !restocked.is_empty() {
+                store.save_foods(fds)?;
+            }
+            for line in &restocked {
+                println!("{}", line);
+            }
+            println!("restocked {} item(s), rejected {}", restocked.len(), rejected.len());
+        }
+        Command::Restock { id: Some(id), quantity: Some(quantity), .. } => {
+            let mut fds = store.load_foods()?;
+            let (before, food) = fds.restock(id, quantity)?;
+            let restocked = format!("{} stock {} -> {}", food.name, before, food.stock);
+            store.save_foods(fds)?;
+            println!("{}", restocked);
+        }
+        Command::Restock { .. } => unreachable!("id and quantity are required without --batch"),
+        Command::Waste { id, quantity, reason } => {
+            let mut fds = store.load_foods()?;
+            let mut wst = load_wastes(waste_file.clone(), csv_opts.verbose)?;
+            let food = fds.waste(id, quantity)?;
+            println!(
+                "Wasted {} x {} ({}), stock is now {}",
+                quantity, food.name, reason, food.stock
+            );
+            wst.add(Waste {
+                id: wst.next_id(),
+                food_id: food.id,
+                quantity,
+                unit_price: food.price.cents(),
+                timestamp: Local::now(),
+                reason,
+            });
+            write_all_or_nothing(&[&data_file, &waste_file], || {
+                store.save_foods(fds)?;
+                save_wastes(waste_file.clone(), wst)?;
+                Ok(())
+            })?;
+        }
+        cmd => unreachable!("{:?} is not a menu command", cmd),
+    }
+    Ok(())
+}
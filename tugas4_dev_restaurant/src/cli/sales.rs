@@ -0,0 +1,864 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Write;
+
+use chrono::prelude::*;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::currency;
+use crate::error::AppError;
+use crate::model::{
+    busiest_hour, day_part, fill_buckets, format_price, group_sales, parse_date, parse_range, split_amount, summarize,
+    top_sellers, DailyTotal, Food, Foods, GroupBy, Order, OrderStatus, Quantity, Sale, Sales, SalesSummary, TopSeller,
+    DAY_PARTS,
+};
+use crate::output::{self, Format};
+use crate::storage::{
+    load_daily_totals, load_orders, load_sales, load_wastes, parse_order_items, reserve_sale_id, sale_counter_file,
+    save_daily_totals, save_orders, save_sales, write_all_or_nothing, Storage,
+};
+use crate::table::{self, Align};
+
+use super::args::DiscountOpt;
+use super::{resolve_tax_rate, write_report, Command, Context, OrderCommand, ReportKind};
+
+fn render_report_table(rows: &[(String, SalesSummary)], total: &SalesSummary, heading: &str) -> String {
+    let mut out = format!(
+        "{:<12} {:>8} {:>14} {:>14} {:>12}\n",
+        heading, "Items", "Transactions", "Income", "Tax"
+    );
+    for (label, summary) in rows {
+        out.push_str(&format!(
+            "{:<12} {:>8} {:>14} {:>14} {:>12}\n",
+            label,
+            summary.items,
+            summary.transactions,
+            currency::format(summary.income),
+            currency::format(summary.tax)
+        ));
+    }
+    out.push_str(&format!(
+        "{:<12} {:>8} {:>14} {:>14} {:>12}\n",
+        "Total",
+        total.items,
+        total.transactions,
+        currency::format(total.income),
+        currency::format(total.tax)
+    ));
+    out
+}
+
+fn render_report_csv(rows: &[(String, SalesSummary)], heading: &str) -> String {
+    let mut out = format!("{},items,transactions,revenue,tax\n", heading.to_lowercase());
+    for (label, summary) in rows {
+        out.push_str(&format!(
+            "{},{},{},{},{}\n",
+            label,
+            summary.items,
+            summary.transactions,
+            format_price(summary.income),
+            format_price(summary.tax)
+        ));
+    }
+    out
+}
+
+const REPORT_NAME_WIDTH: usize = 24;
+
+// The item column of the top, waste and profit reports. Names are cut and
+// padded by display width, so wide ones keep the numbers after them in line.
+fn report_name(name: &str) -> String {
+    table::pad(&table::truncate(name, REPORT_NAME_WIDTH), REPORT_NAME_WIDTH, Align::Left)
+}
+
+const BAR_EIGHTHS: [char; 8] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉', '█'];
+
+fn render_chart(rows: &[(String, i64)], width: usize, show: fn(i64) -> String) -> String {
+    let values: Vec<String> = rows.iter().map(|(_, value)| show(*value)).collect();
+    let label_width = rows.iter().map(|(label, _)| label.width()).max().unwrap_or(0);
+    let value_width = values.iter().map(|value| value.width()).max().unwrap_or(0);
+    let bar_width = width.saturating_sub(label_width + value_width + 4).max(10);
+    let max = rows.iter().map(|(_, value)| *value).max().unwrap_or(0).max(1);
+
+    let mut out = String::new();
+    for ((label, value), shown) in rows.iter().zip(values) {
+        let value = (*value).max(0);
+        let mut eighths = (value as i128 * bar_width as i128 * 8 / max as i128) as usize;
+        if value > 0 && eighths == 0 {
+            eighths = 1;
+        }
+        let mut bar = BAR_EIGHTHS[7].to_string().repeat(eighths / 8);
+        if !eighths.is_multiple_of(8) {
+            bar.push(BAR_EIGHTHS[eighths % 8 - 1]);
+        }
+        // Padded by display width, as names and currency symbols can be wide
+        // or take more than one byte.
+        out.push_str(&format!(
+            "{}{} | {:<bar_width$} {}{}\n",
+            label,
+            " ".repeat(label_width - label.width()),
+            bar,
+            " ".repeat(value_width - shown.width()),
+            shown,
+            bar_width = bar_width
+        ));
+    }
+    out
+}
+
+const RESTAURANT_NAME: &str = "Dev Restaurant";
+const RECEIPT_WIDTH: usize = 40;
+// Narrower than this the name goes on its own line above the numbers.
+const RECEIPT_MIN_NAME_WIDTH: usize = 8;
+
+#[derive(Debug)]
+struct ReceiptLine {
+    pub(crate) name: String,
+    pub(crate) quantity: Quantity,
+    pub(crate) unit_price: i64,
+    pub(crate) total: i64,
+}
+
+// Wraps at spaces by display width. A word wider than the line is broken
+// into pieces that fit.
+fn wrap_name(name: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for word in name.split_whitespace() {
+        let mut pieces: Vec<String> = Vec::new();
+        for c in word.chars() {
+            match pieces.last_mut() {
+                Some(piece) if piece.width() + c.width().unwrap_or(0) <= width => piece.push(c),
+                _ => pieces.push(c.to_string()),
+            }
+        }
+        for piece in pieces {
+            if current.is_empty() {
+                current = piece;
+            } else if current.width() + 1 + piece.width() <= width {
+                current.push(' ');
+                current.push_str(&piece);
+            } else {
+                lines.push(std::mem::replace(&mut current, piece));
+            }
+        }
+    }
+    if !current.is_empty() || lines.is_empty() {
+        lines.push(current);
+    }
+    lines
+}
+
+// Left and right on one receipt line with at least a space between them, or
+// right-aligned on a line of its own when they do not fit together.
+fn receipt_row(left: &str, right: &str) -> String {
+    match RECEIPT_WIDTH.checked_sub(left.width() + right.width()) {
+        Some(gap) if gap > 0 => format!("{}{}{}\n", left, " ".repeat(gap), right),
+        _ => format!("{}\n{}\n", left, table::pad(right, RECEIPT_WIDTH, Align::Right)),
+    }
+}
+
+fn render_receipt(
+    sale_id: i64,
+    timestamp: DateTime<Local>,
+    lines: &[ReceiptLine],
+    discount: i64,
+    tax: i64,
+    tax_rate: i64,
+    show: fn(i64) -> String,
+) -> String {
+    let rule = "-".repeat(RECEIPT_WIDTH);
+    let double_rule = "=".repeat(RECEIPT_WIDTH);
+    let mut out = String::new();
+
+    out.push_str(&format!("{}\n", double_rule));
+    out.push_str(&format!("{:^width$}\n", RESTAURANT_NAME, width = RECEIPT_WIDTH));
+    out.push_str(&format!(
+        "{:^width$}\n",
+        timestamp.format("%Y-%m-%d %H:%M:%S").to_string(),
+        width = RECEIPT_WIDTH
+    ));
+    out.push_str(&format!("{:^width$}\n", format!("Receipt #{}", sale_id), width = RECEIPT_WIDTH));
+    out.push_str(&format!("{}\n", rule));
+
+    // Quantity, price and total, each column as wide as its widest value by
+    // display width, and the name gets what is left of the line.
+    let numbers: Vec<[String; 3]> = lines
+        .iter()
+        .map(|line| [line.quantity.to_string(), show(line.unit_price), show(line.total)])
+        .collect();
+    let mut widths = [4, 8, 8];
+    for row in &numbers {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.width());
+        }
+    }
+    let name_width = RECEIPT_WIDTH.saturating_sub(widths.iter().sum::<usize>() + widths.len());
+    let columns = |name: &str, row: [&str; 3]| {
+        let mut text = table::pad(name, name_width, Align::Left);
+        for (cell, width) in row.iter().zip(widths) {
+            text.push(' ');
+            text.push_str(&table::pad(cell, width, Align::Right));
+        }
+        text.push('\n');
+        text
+    };
+
+    let stacked = name_width < RECEIPT_MIN_NAME_WIDTH;
+    match stacked {
+        true => out.push_str(&receipt_row("Item", "Total")),
+        false => out.push_str(&columns("Item", ["Qty", "Price", "Total"])),
+    }
+    let mut subtotal = 0;
+    for (line, [quantity, price, total]) in lines.iter().zip(&numbers) {
+        if stacked {
+            for name in wrap_name(&line.name, RECEIPT_WIDTH) {
+                out.push_str(&format!("{}\n", name));
+            }
+            out.push_str(&receipt_row(&format!("  {} x {}", quantity, price), total));
+        } else {
+            let mut name = wrap_name(&line.name, name_width).into_iter();
+            out.push_str(&columns(&name.next().unwrap_or_default(), [quantity, price, total]));
+            for rest in name {
+                out.push_str(&format!("{}\n", rest));
+            }
+        }
+        subtotal += line.total;
+    }
+
+    out.push_str(&format!("{}\n", rule));
+    out.push_str(&receipt_row("Subtotal", &show(subtotal)));
+    if discount > 0 {
+        out.push_str(&receipt_row("Discount", &show(-discount)));
+    }
+    if tax_rate > 0 {
+        let label = format!("Tax {}%", tax_rate as f64 / 100.0);
+        out.push_str(&receipt_row(&label, &show(tax)));
+    }
+    out.push_str(&receipt_row("TOTAL", &show(subtotal - discount + tax)));
+    out.push_str(&format!("{}\n", double_rule));
+    out
+}
+
+fn checkout(
+    fds: &Foods,
+    sls: &mut Sales,
+    id: i64,
+    items: &[(i64, Quantity)],
+    discount: &DiscountOpt,
+    tax_rate: i64,
+) -> Result<String, AppError> {
+    let timestamp = Local::now();
+    let mut sales = Vec::new();
+    let mut lines = Vec::new();
+    for (food_id, quantity) in items {
+        let food = match fds.inner.get(food_id) {
+            Some(food) => food,
+            None => return Err(AppError::NotFound(*food_id)),
+        };
+        let sale = Sale { timestamp, ..Sale::new(id, food, *quantity) };
+        lines.push(ReceiptLine {
+            name: food.name.clone(),
+            quantity: *quantity,
+            unit_price: food.price.cents(),
+            total: sale.total,
+        });
+        sales.push(sale);
+    }
+
+    let gross: i64 = sales.iter().map(|sale| sale.total).sum();
+    let discount = discount.amount(gross)?;
+    let totals: Vec<i64> = sales.iter().map(|sale| sale.total).collect();
+    for (sale, share) in sales.iter_mut().zip(split_amount(discount, &totals)) {
+        sale.net = sale.total - share;
+    }
+
+    let net = gross - discount;
+    let tax = ((net as i128 * tax_rate as i128 + 5_000) / 10_000) as i64;
+    let nets: Vec<i64> = sales.iter().map(|sale| sale.net).collect();
+    for (sale, share) in sales.iter_mut().zip(split_amount(tax, &nets)) {
+        sale.tax = share;
+    }
+
+    for sale in sales {
+        sls.add(sale);
+    }
+    Ok(render_receipt(id, timestamp, &lines, discount, tax, tax_rate, currency::format))
+}
+
+// Selling, orders, refunds and the reports on them.
+pub(super) fn execute(cmd: Command, context: &Context, store: &mut dyn Storage) -> Result<(), AppError> {
+    let (csv_opts, format) = (context.csv_opts, context.format);
+    let data_file = context.data_file.clone();
+    let sales_file = context.sales_file.clone();
+    let orders_file = context.orders_file.clone();
+    let totals_file = context.totals_file.clone();
+    let waste_file = context.waste_file.clone();
+    match cmd {
+        Command::Sell { id, quantity, sku, force, receipt_out, discount } => {
+            let mut fds = store.load_foods()?;
+            let mut sls = load_sales(sales_file.clone(), csv_opts.verbose)?;
+            let id = match sku {
+                true => fds.by_sku(&id)?.id,
+                false => id.parse::<i64>().map_err(|e| AppError::InvalidArgument(e.into()))?,
+            };
+            fds.sell(id, quantity, force)?;
+            let tax_rate = resolve_tax_rate(discount.tax_rate, &context.config)?;
+            let sale_id = reserve_sale_id(&sale_counter_file(&sales_file), &sls)?;
+            let receipt = checkout(&fds, &mut sls, sale_id, &[(id, quantity)], &discount, tax_rate)?;
+            write_all_or_nothing(&[&data_file, &sales_file], || {
+                store.save_foods(fds)?;
+                save_sales(sales_file.clone(), sls)?;
+                Ok(())
+            })?;
+
+            print!("{}", receipt);
+            if let Some(path) = receipt_out {
+                File::create(path)?.write_all(receipt.as_bytes())?;
+            }
+        }
+        Command::Order { cmd: Some(OrderCommand::New { food_id, quantity }), .. } => {
+            if !quantity.is_positive() {
+                return Err(AppError::InvalidQuantity(quantity));
+            }
+            let fds = store.load_foods()?;
+            let food = match fds.inner.get(&food_id) {
+                Some(food) => food,
+                None => return Err(AppError::NotFound(food_id)),
+            };
+            food.check_divisible(quantity)?;
+            let mut ords = load_orders(orders_file.clone(), csv_opts.verbose)?;
+            let id = ords.next_id();
+            ords.add(Order {
+                id,
+                food_id,
+                quantity,
+                status: OrderStatus::Pending,
+                created_at: Local::now(),
+            });
+            save_orders(orders_file, ords)?;
+            println!("Order #{} pending: {} x {}", id, quantity, food.name);
+        }
+        Command::Order { cmd: Some(OrderCommand::List { status }), .. } => {
+            let fds = store.load_foods()?;
+            let ords = load_orders(orders_file, csv_opts.verbose)?;
+            let orders = ords.with_status(status);
+            if orders.is_empty() {
+                println!("No orders found");
+            }
+            for order in orders {
+                let name = match fds.inner.get(&order.food_id) {
+                    Some(food) => food.name.clone(),
+                    None => format!("{} (deleted)", order.food_id),
+                };
+                println!(
+                    "#{:<5} {:<10} {:>4} x {:<24} {}",
+                    order.id,
+                    order.status.as_str(),
+                    order.quantity,
+                    name,
+                    order.created_at.format("%Y-%m-%d %H:%M")
+                );
+            }
+        }
+        Command::Order { cmd: Some(OrderCommand::Complete { id, force }), .. } => {
+            let mut fds = store.load_foods()?;
+            let mut sls = load_sales(sales_file.clone(), csv_opts.verbose)?;
+            let mut ords = load_orders(orders_file.clone(), csv_opts.verbose)?;
+            let order = ords.pending_mut(id)?;
+            let food = fds.sell(order.food_id, order.quantity, force)?;
+            println!("Order #{} completed: {} x {}", order.id, order.quantity, food.name);
+            let sale_id = reserve_sale_id(&sale_counter_file(&sales_file), &sls)?;
+            sls.add(Sale::new(sale_id, food, order.quantity));
+            order.status = OrderStatus::Completed;
+            write_all_or_nothing(&[&data_file, &sales_file, &orders_file], || {
+                store.save_foods(fds)?;
+                save_sales(sales_file.clone(), sls)?;
+                save_orders(orders_file.clone(), ords)?;
+                Ok(())
+            })?;
+        }
+        Command::Order { cmd: Some(OrderCommand::Cancel { id }), .. } => {
+            let mut ords = load_orders(orders_file.clone(), csv_opts.verbose)?;
+            ords.pending_mut(id)?.status = OrderStatus::Cancelled;
+            save_orders(orders_file, ords)?;
+            println!("Order #{} cancelled", id);
+        }
+        Command::Order { items, force, receipt_out, discount, cmd: None } => {
+            let items = parse_order_items(&items).map_err(AppError::InvalidArgument)?;
+            if items.is_empty() {
+                return Err(AppError::EmptyOrder);
+            }
+            let mut fds = store.load_foods()?;
+            let mut sls = load_sales(sales_file.clone(), csv_opts.verbose)?;
+            fds.sell_many(&items, force)?;
+            let tax_rate = resolve_tax_rate(discount.tax_rate, &context.config)?;
+            let sale_id = reserve_sale_id(&sale_counter_file(&sales_file), &sls)?;
+            let receipt = checkout(&fds, &mut sls, sale_id, &items, &discount, tax_rate)?;
+            write_all_or_nothing(&[&data_file, &sales_file], || {
+                store.save_foods(fds)?;
+                save_sales(sales_file.clone(), sls)?;
+                Ok(())
+            })?;
+
+            print!("{}", receipt);
+            if let Some(path) = receipt_out {
+                File::create(path)?.write_all(receipt.as_bytes())?;
+            }
+        }
+        Command::CloseDay { date, counted, force } => {
+            let date = match date {
+                Some(date) => parse_date(&date).map_err(AppError::InvalidArgument)?,
+                None => Local::now().date_naive(),
+            };
+            let mut dts = load_daily_totals(totals_file.clone(), csv_opts.verbose)?;
+            if dts.inner.contains_key(&date) && !force {
+                return Err(AppError::DayAlreadyClosed(date));
+            }
+
+            let sls = load_sales(sales_file, csv_opts.verbose)?;
+            let summary = summarize(&sls.on_date(date));
+            let refunds = summarize(&sls.refunded_on(date));
+            dts.add(DailyTotal {
+                date,
+                transactions: summary.transactions,
+                items: summary.items,
+                revenue: summary.income,
+                refunds: refunds.transactions,
+                refunded: refunds.income,
+            });
+            save_daily_totals(totals_file, dts)?;
+
+            println!("Close of day {}", date);
+            println!("{:<20} {:>14}", "Transactions", summary.transactions);
+            println!("{:<20} {:>14}", "Items sold", summary.items);
+            // Before discounts and refunds, so the lines below it add up to the net sales.
+            let discounts = summary.discounts + refunds.discounts;
+            println!("{:<20} {:>14}", "Gross sales", currency::format(summary.income + refunds.income + discounts));
+            println!("{:<20} {:>14}", "Discounts given", currency::format(-discounts));
+            println!("{:<20} {:>14}", "Refunds", refunds.transactions);
+            println!("{:<20} {:>14}", "Refunded amount", currency::format(-refunds.income));
+            println!("{:<20} {:>14}", "Net sales", currency::format(summary.income));
+            println!("{:<20} {:>14}", "Tax collected", currency::format(summary.tax));
+            println!("{:<20} {:>14}", "Expected in till", currency::format(summary.income + summary.tax));
+            if let Some(counted) = counted {
+                let difference = counted.cents() - summary.income - summary.tax;
+                println!("{:<20} {:>14}", "Counted", counted);
+                println!("{:<20} {:>14}", "Difference", currency::format(difference));
+            }
+        }
+        Command::Refund { sale_id } => {
+            let mut fds = store.load_foods()?;
+            let mut sls = load_sales(sales_file.clone(), csv_opts.verbose)?;
+            for sale in sls.refund(sale_id)? {
+                match fds.refund(sale.food_id, sale.quantity) {
+                    Ok(food) => println!(
+                        "Refunded {} x {}, stock is now {}",
+                        sale.quantity, food.name, food.stock
+                    ),
+                    Err(AppError::NotFound(id)) => println!(
+                        "Refunded {} x item {}, which no longer exists; stock not restored",
+                        sale.quantity, id
+                    ),
+                    Err(e) => return Err(e),
+                }
+            }
+            write_all_or_nothing(&[&data_file, &sales_file], || {
+                store.save_foods(fds)?;
+                save_sales(sales_file.clone(), sls)?;
+                Ok(())
+            })?;
+        }
+        Command::Report { date, from, to, kind: Some(ReportKind::Top { limit, chart }), .. } => {
+            let (from, to) = parse_range(date, from, to).map_err(AppError::InvalidArgument)?;
+            let fds = store.load_foods()?;
+            let sls = load_sales(sales_file, csv_opts.verbose)?;
+            let sellers = top_sellers(&sls.between(from, to), &fds, limit);
+            let names: Vec<String> = sellers
+                .iter()
+                .map(|seller| match &seller.name {
+                    Some(name) => name.clone(),
+                    None => format!("{} (deleted)", seller.food_id),
+                })
+                .collect();
+
+            if chart {
+                let bars: Vec<(String, i64)> = names
+                    .into_iter()
+                    .zip(sellers.iter())
+                    .map(|(name, seller)| (name, seller.quantity.0))
+                    .collect();
+                print!("{}", render_chart(&bars, output::terminal_width(), |value| Quantity(value).to_string()));
+                return Ok(());
+            }
+
+            println!("{:>4}  {:<24} {:>8} {:>14}", "#", "Item", "Sold", "Revenue");
+            for (rank, (seller, name)) in sellers.iter().zip(names).enumerate() {
+                println!(
+                    "{:>4}  {} {:>8} {:>14}",
+                    rank + 1, report_name(&name), seller.quantity, currency::format(seller.revenue)
+                );
+            }
+        }
+        Command::Report { date, from, to, kind: Some(ReportKind::Waste), .. } => {
+            let (from, to) = parse_range(date, from, to).map_err(AppError::InvalidArgument)?;
+            let fds = store.load_foods()?;
+            let wst = load_wastes(waste_file, csv_opts.verbose)?;
+
+            let mut totals: BTreeMap<i64, (Quantity, i64)> = BTreeMap::new();
+            for waste in wst.between(from, to) {
+                let entry = totals.entry(waste.food_id).or_insert((Quantity::default(), 0));
+                entry.0 += waste.quantity;
+                entry.1 += waste.quantity.times(waste.unit_price);
+            }
+
+            println!("{:<24} {:>8} {:>14}", "Item", "Wasted", "Lost value");
+            let (mut quantity, mut value) = (Quantity::default(), 0);
+            for (food_id, (wasted, lost)) in totals {
+                let name = match fds.inner.get(&food_id) {
+                    Some(food) => food.name.clone(),
+                    None => format!("{} (deleted)", food_id),
+                };
+                println!("{} {:>8} {:>14}", report_name(&name), wasted, currency::format(lost));
+                quantity += wasted;
+                value += lost;
+            }
+            println!("{:<24} {:>8} {:>14}", "Total", quantity, currency::format(value));
+        }
+        Command::Report { date, from, to, kind: Some(ReportKind::Profit), .. } => {
+            let (from, to) = parse_range(date, from, to).map_err(AppError::InvalidArgument)?;
+            let fds = store.load_foods()?;
+            let sls = load_sales(sales_file, csv_opts.verbose)?;
+            let mut rows: Vec<(TopSeller, Option<i64>)> = top_sellers(&sls.between(from, to), &fds, usize::MAX)
+                .into_iter()
+                .map(|seller| {
+                    let cost = fds
+                        .inner
+                        .get(&seller.food_id)
+                        .and_then(|food| food.cost)
+                        .map(|cost| seller.quantity.times(cost.cents()));
+                    (seller, cost)
+                })
+                .collect();
+            rows.sort_by_key(|(seller, cost)| std::cmp::Reverse(seller.revenue - cost.unwrap_or(0)));
+
+            println!(
+                "{:<24} {:>8} {:>14} {:>14} {:>14}",
+                "Item", "Sold", "Revenue", "Cost", "Profit"
+            );
+            let (mut revenue, mut cost_of_goods) = (0, 0);
+            for (seller, cost) in rows.iter() {
+                let name = match &seller.name {
+                    Some(name) => name.clone(),
+                    None => format!("{} (deleted)", seller.food_id),
+                };
+                let shown_cost = match cost {
+                    Some(cost) => currency::format(*cost),
+                    None => "?".to_owned(),
+                };
+                let losing = fds
+                    .inner
+                    .get(&seller.food_id)
+                    .is_some_and(|food| food.cost.is_some_and(|cost| cost > food.price));
+                println!(
+                    "{} {:>8} {:>14} {:>14} {:>14}{}",
+                    report_name(&name),
+                    seller.quantity,
+                    currency::format(seller.revenue),
+                    shown_cost,
+                    currency::format(seller.revenue - cost.unwrap_or(0)),
+                    if losing { "  ! cost above price" } else { "" }
+                );
+                revenue += seller.revenue;
+                cost_of_goods += cost.unwrap_or(0);
+            }
+            println!(
+                "{:<24} {:>8} {:>14} {:>14} {:>14}",
+                "Total",
+                "",
+                currency::format(revenue),
+                currency::format(cost_of_goods),
+                currency::format(revenue - cost_of_goods)
+            );
+
+            let mut losing: Vec<&Food> = fds
+                .inner
+                .values()
+                .filter(|food| food.cost.is_some_and(|cost| cost > food.price))
+                .collect();
+            losing.sort_by_key(|food| food.id);
+            if !losing.is_empty() {
+                println!();
+                println!("Items costing more than their price:");
+                for food in losing {
+                    println!(
+                        "  {} (id {}): price {}, cost {}",
+                        food.name,
+                        food.id,
+                        food.price,
+                        food.cost.unwrap_or_default()
+                    );
+                }
+            }
+            if rows.iter().any(|(_, cost)| cost.is_none()) {
+                println!();
+                println!("? = no cost recorded, counted as 0");
+            }
+        }
+        Command::Report { date, from, to, kind: Some(ReportKind::Summary), .. } => {
+            let (from, to) = parse_range(date, from, to).map_err(AppError::InvalidArgument)?;
+            let sls = load_sales(sales_file, csv_opts.verbose)?;
+            let sales = sls.between(from, to);
+            let total = summarize(&sales);
+            let average = match total.transactions {
+                0 => 0,
+                n => total.income / n as i64,
+            };
+
+            println!("{:<16} {:>14}", "Revenue", currency::format(total.income));
+            println!("{:<16} {:>14}", "Discounts", currency::format(total.discounts));
+            println!("{:<16} {:>14}", "Tax collected", currency::format(total.tax));
+            println!("{:<16} {:>14}", "Items sold", total.items);
+            println!("{:<16} {:>14}", "Transactions", total.transactions);
+            println!("{:<16} {:>14}", "Average ticket", currency::format(average));
+            match busiest_hour(&sales) {
+                Some((hour, count)) => println!(
+                    "{:<16} {:>14}",
+                    "Busiest hour",
+                    format!("{:02}:00-{:02}:00 ({})", hour, (hour + 1) % 24, count)
+                ),
+                None => println!("{:<16} {:>14}", "Busiest hour", "-"),
+            }
+
+            println!();
+            println!("{:<16} {:>8} {:>14}", "Day part", "Items", "Revenue");
+            for (name, start, end) in DAY_PARTS.iter() {
+                let part: Vec<&Sale> = sales
+                    .iter()
+                    .filter(|sale| day_part(sale.timestamp.hour()) == *name)
+                    .copied()
+                    .collect();
+                let summary = summarize(&part);
+                println!(
+                    "{:<16} {:>8} {:>14}",
+                    format!("{} {:02}-{:02}", name, start, end),
+                    summary.items,
+                    currency::format(summary.income)
+                );
+            }
+        }
+        Command::Report { date, from, to, group_by, out, overwrite, chart, kind: None } => {
+            let ranged = from.is_some() || to.is_some();
+            let (from, to) = match date {
+                Some(date) => parse_range(Some(date), None, None).map_err(AppError::InvalidArgument)?,
+                None if !ranged => {
+                    let today = Local::now().date_naive();
+                    (Some(today), Some(today))
+                }
+                None => parse_range(None, from, to).map_err(AppError::InvalidArgument)?,
+            };
+            let sls = load_sales(sales_file, csv_opts.verbose)?;
+            let sales = sls.between(from, to);
+            let total = summarize(&sales);
+
+            let mut buckets = group_sales(&sales, group_by);
+            if group_by != GroupBy::Day || !ranged {
+                fill_buckets(&mut buckets, group_by, from, to);
+            }
+            let rows: Vec<(String, SalesSummary)> = buckets
+                .into_iter()
+                .map(|(start, bucket)| (group_by.label(start), summarize(&bucket)))
+                .collect();
+            let heading = match group_by {
+                GroupBy::Day => "Date",
+                GroupBy::Week => "Week",
+                GroupBy::Month => "Month",
+            };
+
+            let text = match format {
+                _ if chart => {
+                    let bars: Vec<(String, i64)> = rows
+                        .iter()
+                        .map(|(label, summary)| (label.clone(), summary.income))
+                        .collect();
+                    render_chart(&bars, output::terminal_width(), currency::format)
+                }
+                Format::Csv => render_report_csv(&rows, heading),
+                Format::Json | Format::Jsonl | Format::Debug => {
+                    return Err(AppError::UnsupportedFormat { command: "report", format: format.name() })
+                }
+                _ if !ranged && group_by == GroupBy::Day => {
+                    let mut text = format!("Report for {}\n", rows[0].0);
+                    text.push_str(&format!("Items sold:   {}\n", total.items));
+                    text.push_str(&format!("Transactions: {}\n", total.transactions));
+                    text.push_str(&format!("Income:       {}\n", currency::format(total.income)));
+                    text.push_str(&format!("Discounts:    {}\n", currency::format(total.discounts)));
+                    text.push_str(&format!("Tax:          {}\n", currency::format(total.tax)));
+                    text
+                }
+                _ => {
+                    let mut text = match (from, to) {
+                        (Some(from), Some(to)) => format!("Report from {} to {}\n", from, to),
+                        (Some(from), None) => format!("Report from {}\n", from),
+                        (None, Some(to)) => format!("Report up to {}\n", to),
+                        (None, None) => String::new(),
+                    };
+                    text.push_str(&render_report_table(&rows, &total, heading));
+                    text
+                }
+            };
+            write_report(out, overwrite, &text)?;
+        }
+        cmd => unreachable!("{:?} is not a sales command", cmd),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::currency::Currency;
+    use crate::model::QUANTITY_SCALE;
+
+    fn chart(rows: &[(&str, i64)], width: usize, show: fn(i64) -> String) -> String {
+        let rows: Vec<(String, i64)> = rows.iter().map(|(label, value)| (label.to_string(), *value)).collect();
+        render_chart(&rows, width, show)
+    }
+
+    #[test]
+    fn report_names_fill_their_column_by_display_width() {
+        assert_eq!(report_name("bakso"), format!("bakso{}", " ".repeat(19)));
+        assert_eq!(report_name("拉面"), format!("拉面{}", " ".repeat(20)));
+        assert_eq!(report_name("Nasi Goreng Spesial Telur Dadar"), "Nasi Goreng Spesial Tel…");
+        let wide = report_name(&"鸡肉饭".repeat(6));
+        assert_eq!((wide.as_str(), wide.width()), ("鸡肉饭鸡肉饭鸡肉饭鸡肉… ", REPORT_NAME_WIDTH));
+    }
+
+    #[test]
+    fn chart_scales_bars_to_the_largest_value() {
+        assert_eq!(
+            chart(&[("bakso", 8), ("sate", 3), ("es", 0)], 30, |value| value.to_string()),
+            "bakso | ████████████████████ 8\n\
+             sate  | ███████▌             3\n\
+             es    |                      0\n"
+        );
+    }
+
+    #[test]
+    fn chart_pads_by_display_width() {
+        assert_eq!(
+            chart(&[("拉面", 1000), ("sate", 250)], 24, |value| Currency::Eur.format(value)),
+            "拉面 | ██████████ € 10,00\n\
+             sate | ██▌         € 2,50\n"
+        );
+    }
+
+    #[test]
+    fn chart_shows_a_sliver_for_tiny_values() {
+        assert_eq!(
+            chart(&[("a", 10000), ("b", 1)], 0, |value| value.to_string()),
+            "a | ██████████ 10000\n\
+             b | ▏              1\n"
+        );
+    }
+
+    // A receipt for (name, quantity, unit price in cents) lines at 11% tax
+    // with 5.00 off.
+    fn receipt(items: &[(&str, i64, i64)], show: fn(i64) -> String) -> String {
+        let lines: Vec<ReceiptLine> = items
+            .iter()
+            .map(|(name, quantity, price)| ReceiptLine {
+                name: name.to_string(),
+                quantity: Quantity(quantity * QUANTITY_SCALE),
+                unit_price: *price,
+                total: quantity * price,
+            })
+            .collect();
+        let gross: i64 = lines.iter().map(|line| line.total).sum();
+        let timestamp = Local.ymd(2024, 3, 5).and_hms(12, 30, 0);
+        render_receipt(7, timestamp, &lines, 500, (gross - 500) * 11 / 100, 1100, show)
+    }
+
+    fn too_wide(receipt: &str) -> Vec<&str> {
+        receipt.lines().filter(|line| line.width() > RECEIPT_WIDTH).collect()
+    }
+
+    #[test]
+    fn receipt_lines_stay_within_the_width() {
+        type Show = fn(i64) -> String;
+        let currencies: [(&str, Show); 4] = [
+            ("plain", |cents| Currency::Plain.format(cents)),
+            ("idr", |cents| Currency::Idr.format(cents)),
+            ("usd", |cents| Currency::Usd.format(cents)),
+            ("eur", |cents| Currency::Eur.format(cents)),
+        ];
+        let menus = [
+            &[("sate", 5, 2_200_000), ("bakso", 3, 1_800_000)][..],
+            &[("nasi goreng spesial dengan telur dan ayam", 120, 99_999_999)],
+            &[("拉面 with extra chashu and a soft egg", 999, 12_345_678_901)],
+            &[("es teh", 1, 999_999_999_999_999), ("a very long name that goes on and on", 1, 1)],
+        ];
+        for (currency, show) in currencies {
+            for items in menus {
+                let text = receipt(items, show);
+                assert!(too_wide(&text).is_empty(), "{}:\n{}", currency, text);
+            }
+        }
+    }
+
+    #[test]
+    fn receipt_columns_make_room_for_wide_amounts() {
+        assert_eq!(
+            receipt(&[("sate", 5, 2_200_000), ("bakso", 3, 1_800_000)], |cents| Currency::Idr.format(cents)),
+            concat!(
+                "========================================\n",
+                "             Dev Restaurant             \n",
+                "          2024-03-05 12:30:00           \n",
+                "               Receipt #7               \n",
+                "----------------------------------------\n",
+                "Item            Qty     Price      Total\n",
+                "sate              5 Rp 22.000 Rp 110.000\n",
+                "bakso             3 Rp 18.000  Rp 54.000\n",
+                "----------------------------------------\n",
+                "Subtotal                      Rp 164.000\n",
+                "Discount                           -Rp 5\n",
+                "Tax 11%                     Rp 18.039,45\n",
+                "TOTAL                      Rp 182.034,45\n",
+                "========================================\n",
+            )
+        );
+    }
+
+    #[test]
+    fn receipt_wraps_long_names_in_their_column() {
+        let text = receipt(&[("nasi goreng spesial pakai telur", 2, 2500), ("bakso", 1, 1800)], |cents| {
+            Currency::Plain.format(cents)
+        });
+        let items: Vec<&str> = text.lines().skip(5).take(5).collect();
+        assert_eq!(
+            items,
+            [
+                "Item               Qty    Price    Total",
+                "nasi goreng          2    25.00    50.00",
+                "spesial pakai",
+                "telur",
+                "bakso                1    18.00    18.00",
+            ]
+        );
+    }
+
+    #[test]
+    fn receipt_stacks_the_numbers_when_no_name_fits_beside_them() {
+        let text = receipt(&[("es teh", 1, 999_999_999_999_999)], |cents| Currency::Eur.format(cents));
+        let items: Vec<&str> = text.lines().skip(5).take(4).collect();
+        assert_eq!(
+            items,
+            [
+                "Item                               Total",
+                "es teh",
+                "  1 x € 9.999.999.999.999,99",
+                "                  € 9.999.999.999.999,99",
+            ]
+        );
+    }
+}
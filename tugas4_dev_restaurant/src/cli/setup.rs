@@ -0,0 +1,136 @@
+use std::path::{Path, PathBuf};
+
+use structopt::StructOpt;
+
+use crate::config;
+use crate::currency;
+use crate::error::AppError;
+use crate::model::Foods;
+use crate::shell;
+use crate::storage::{is_dry_run, Storage};
+#[cfg(feature = "tui")]
+use crate::tui;
+
+use super::{confirm, resolve_tax_rate, write_report, Command, ConfigCommand, Context, Opt, ProfileCommand};
+
+#[cfg(feature = "tui")]
+fn browse(foods: Foods) -> Result<Option<Foods>, AppError> {
+    tui::run(foods)
+}
+
+#[cfg(not(feature = "tui"))]
+fn browse(_: Foods) -> Result<Option<Foods>, AppError> {
+    Err(AppError::NotCompiledIn("tui"))
+}
+
+// Wraps the generated bash completion so the id of remove, edit and show
+// completes from the data file named on the command line.
+const BASH_ID_COMPLETION: &str = r#"
+_{name}_ids() {
+    local i data=()
+    for ((i = 1; i < COMP_CWORD; i++)); do
+        case "${COMP_WORDS[i]}" in
+            -d|--data-file) data=(-d "${COMP_WORDS[i + 1]}") ;;
+        esac
+    done
+    case "${COMP_WORDS[COMP_CWORD - 1]}" in
+        remove|edit|show)
+            if [[ "${COMP_WORDS[COMP_CWORD]}" != -* ]]; then
+                COMPREPLY=($(compgen -W "$({name} "${data[@]}" complete-ids 2>/dev/null)" -- "${COMP_WORDS[COMP_CWORD]}"))
+                return 0
+            fi
+            ;;
+    esac
+    _{name} "$@"
+}
+
+complete -F _{name}_ids -o bashdefault -o default {name}
+"#;
+
+// Profiles, the config file, completions and the interactive front ends.
+pub(super) fn execute(cmd: Command, context: &Context, store: &mut dyn Storage) -> Result<(), AppError> {
+    let format = context.format;
+    let data_file = context.data_file.clone();
+    let sales_file = context.sales_file.clone();
+    match cmd {
+        Command::Tui => {
+            if let Some(foods) = browse(store.load_foods()?)? {
+                store.save_foods(foods)?;
+            }
+        }
+        Command::Shell { save_on_quit } => {
+            shell::run(context, store, save_on_quit)?;
+        }
+        Command::Profile { cmd: ProfileCommand::List } => {
+            let profiles = config::profiles()?;
+            if profiles.is_empty() {
+                println!("No profiles yet, create one with profile create NAME");
+            }
+            for name in profiles {
+                let active = context.profile.as_deref() == Some(name.as_str());
+                println!("{}{}", name, if active { " (active)" } else { "" });
+            }
+        }
+        Command::Profile { cmd: ProfileCommand::Create { name } } => {
+            let dir = config::profile_dir(&name)?;
+            if dir.exists() {
+                return Err(AppError::ProfileExists(name));
+            }
+            if !is_dry_run() {
+                std::fs::create_dir_all(&dir)?;
+            }
+            println!("Created profile {} in {}", name, dir.display());
+        }
+        Command::Profile { cmd: ProfileCommand::Remove { name } } => {
+            let dir = config::profile_dir(&name)?;
+            if !dir.is_dir() {
+                return Err(AppError::ProfileNotFound(name));
+            }
+            let prompt = format!("Remove profile {} and every file in {}?", name, dir.display());
+            if !confirm(&prompt, context.yes)? {
+                println!("Profile kept");
+                return Ok(());
+            }
+            if !is_dry_run() {
+                std::fs::remove_dir_all(&dir)?;
+            }
+            println!("Removed profile {}", name);
+        }
+        Command::Config { cmd: ConfigCommand::Show } => {
+            match context.config.sources.as_slice() {
+                [] => println!("# no config file found"),
+                sources => {
+                    for source in sources {
+                        println!("# read {}", source.display());
+                    }
+                }
+            }
+            let path = |path: &Path| toml::Value::String(path.display().to_string());
+            let tax_rate = resolve_tax_rate(None, &context.config)?;
+            println!("data_file = {}", path(&data_file));
+            println!("sales_file = {}", path(&sales_file));
+            println!("currency = {}", toml::Value::String(currency::active().name().to_owned()));
+            println!("default_format = {}", toml::Value::String(format.name().to_owned()));
+            println!("tax_rate = {}", tax_rate as f64 / 100.0);
+        }
+        Command::Config { cmd: ConfigCommand::Init { path, overwrite } } => {
+            let path = path.or_else(config::user_file).unwrap_or_else(|| PathBuf::from(config::LOCAL_FILE));
+            if !is_dry_run() {
+                if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+                    std::fs::create_dir_all(dir)?;
+                }
+                write_report(Some(path.clone()), overwrite, config::STARTER)?;
+            }
+            println!("wrote {}", path.display());
+        }
+        Command::Completions { shell } => {
+            let name = env!("CARGO_PKG_NAME");
+            Opt::clap().gen_completions_to(name, shell, &mut std::io::stdout());
+            if let structopt::clap::Shell::Bash = shell {
+                print!("{}", BASH_ID_COMPLETION.replace("{name}", name));
+            }
+        }
+        cmd => unreachable!("{:?} is not a setup command", cmd),
+    }
+    Ok(())
+}
@@ -0,0 +1,103 @@
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+const RESET: &str = "\x1b[0m";
+const MATCH: &str = "\x1b[1;31m";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+impl std::str::FromStr for ColorChoice {
+    type Err = String;
+
+    fn from_str(choice: &str) -> Result<Self, Self::Err> {
+        match choice {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => Err(format!("unknown color choice: {}", choice)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Color {
+    Red,
+    Yellow,
+}
+
+impl Color {
+    fn code(self) -> &'static str {
+        match self {
+            Color::Red => "\x1b[31m",
+            Color::Yellow => "\x1b[33m",
+        }
+    }
+}
+
+// auto follows https://no-color.org: any non-empty NO_COLOR turns colors off.
+pub(crate) fn init(choice: ColorChoice) {
+    let enabled = match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => {
+            std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty()) && std::io::stdout().is_terminal()
+        }
+    };
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub(crate) fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+pub(crate) fn paint(text: &str, color: Option<Color>) -> String {
+    match color {
+        Some(color) if enabled() => format!("{}{}{}", color.code(), text, RESET),
+        _ => text.to_owned(),
+    }
+}
+
+// Marks every case-insensitive occurrence of needle. Inside a colored row the
+// row color is switched back on after each match.
+pub(crate) fn highlight(text: &str, needle: &str, row: Option<Color>) -> String {
+    let needle = needle.to_lowercase();
+    if !enabled() || needle.is_empty() {
+        return text.to_owned();
+    }
+    let resume = row.map_or("", Color::code);
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some((start, end)) = find_ignore_case(rest, &needle) {
+        out.push_str(&rest[..start]);
+        out.push_str(MATCH);
+        out.push_str(&rest[start..end]);
+        out.push_str(RESET);
+        out.push_str(resume);
+        rest = &rest[end..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn find_ignore_case(text: &str, needle: &str) -> Option<(usize, usize)> {
+    for (start, _) in text.char_indices() {
+        let mut lowered = String::new();
+        for (offset, c) in text[start..].char_indices() {
+            lowered.extend(c.to_lowercase());
+            if lowered == needle {
+                return Some((start, start + offset + c.len_utf8()));
+            }
+            if !needle.starts_with(&lowered) {
+                break;
+            }
+        }
+    }
+    None
+}
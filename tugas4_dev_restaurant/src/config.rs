@@ -0,0 +1,129 @@
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::currency::Currency;
+use crate::output::Format;
+use crate::error::AppError;
+
+// Looked up in the working directory, where it overrides the user config.
+pub(crate) const LOCAL_FILE: &str = "restaurant.toml";
+
+pub(crate) const STARTER: &str = r#"# Defaults for tugas4_dev_restaurant. Flags on the command line and the
+# RESTAURANT_DATA_FILE, RESTAURANT_SALES_FILE and RESTAURANT_FORMAT variables
+# win over these, and a restaurant.toml in the working directory wins over
+# this file.
+
+# The menu file, a .db file uses SQLite.
+# data_file = "/srv/restaurant/food.csv"
+
+# The sales log [default: sales.csv next to the data file].
+# sales_file = "/srv/restaurant/sales.csv"
+
+# How money is shown: plain, idr, usd or eur.
+# currency = "idr"
+
+# Output of list, search and show: table, csv, json, jsonl or debug.
+# default_format = "table"
+
+# Tax added to receipts, as a percentage like 11 or a fraction like 0.11.
+# tax_rate = 11
+"#;
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Config {
+    pub(crate) data_file: Option<PathBuf>,
+    pub(crate) sales_file: Option<PathBuf>,
+    pub(crate) currency: Option<Currency>,
+    pub(crate) default_format: Option<Format>,
+    pub(crate) tax_rate: Option<f64>,
+    // The files the values came from, lowest precedence first.
+    #[serde(skip)]
+    pub(crate) sources: Vec<PathBuf>,
+}
+
+impl Config {
+    // The user config, then restaurant.toml in the working directory on top.
+    pub(crate) fn load() -> Result<Config, AppError> {
+        let mut config = Config::default();
+        for path in user_file().into_iter().chain([PathBuf::from(LOCAL_FILE)]) {
+            if let Some(file) = load_file(&path)? {
+                config = config.or(file);
+                config.sources.push(path);
+            }
+        }
+        Ok(config)
+    }
+
+    // Keys set in `over` win.
+    fn or(self, over: Config) -> Config {
+        Config {
+            data_file: over.data_file.or(self.data_file),
+            sales_file: over.sales_file.or(self.sales_file),
+            currency: over.currency.or(self.currency),
+            default_format: over.default_format.or(self.default_format),
+            tax_rate: over.tax_rate.or(self.tax_rate),
+            sources: self.sources,
+        }
+    }
+}
+
+// $XDG_CONFIG_HOME/restaurant, falling back to ~/.config.
+fn dir() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME").filter(|dir| !dir.is_empty())?).join(".config"),
+    };
+    Some(base.join("restaurant"))
+}
+
+pub(crate) fn user_file() -> Option<PathBuf> {
+    Some(dir()?.join("config.toml"))
+}
+
+pub(crate) fn profiles_dir() -> Result<PathBuf, AppError> {
+    dir().map(|dir| dir.join("profiles")).ok_or(AppError::NoConfigDir)
+}
+
+// Each profile is a directory holding its own data file, sales log and so on.
+pub(crate) fn profile_dir(name: &str) -> Result<PathBuf, AppError> {
+    let valid = name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+    if name.is_empty() || !valid {
+        return Err(AppError::InvalidProfileName(name.to_owned()));
+    }
+    Ok(profiles_dir()?.join(name))
+}
+
+pub(crate) fn profiles() -> Result<Vec<String>, AppError> {
+    let entries = match std::fs::read_dir(profiles_dir()?) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut names = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            names.push(entry.file_name().to_string_lossy().into_owned());
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+fn load_file(path: &Path) -> Result<Option<Config>, AppError> {
+    let mut file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut buffer = String::new();
+    file.read_to_string(&mut buffer)?;
+
+    toml::from_str(&buffer)
+        .map(Some)
+        .map_err(|source| AppError::Config { path: path.to_path_buf(), source })
+}
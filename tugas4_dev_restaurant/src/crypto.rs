@@ -0,0 +1,72 @@
+use std::sync::OnceLock;
+
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+
+use crate::error::AppError;
+use crate::storage::ENCRYPTED_MAGIC;
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const PASSPHRASE_VAR: &str = "RESTAURANT_PASSPHRASE";
+
+static PASSPHRASE: OnceLock<String> = OnceLock::new();
+
+// The passphrase is asked for once per run and reused for every load and save.
+pub(crate) fn passphrase(confirm: bool) -> Result<&'static str, AppError> {
+    if let Some(passphrase) = PASSPHRASE.get() {
+        return Ok(passphrase);
+    }
+    let passphrase = match std::env::var(PASSPHRASE_VAR) {
+        Ok(passphrase) => passphrase,
+        Err(_) => {
+            let passphrase = rpassword::prompt_password("Passphrase: ")?;
+            if confirm && rpassword::prompt_password("Repeat passphrase: ")? != passphrase {
+                return Err(AppError::PassphraseMismatch);
+            }
+            passphrase
+        }
+    };
+    Ok(PASSPHRASE.get_or_init(|| passphrase))
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Key, AppError> {
+    let mut key = Key::default();
+    argon2::Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| AppError::Encryption(e.to_string()))?;
+    Ok(key)
+}
+
+// Layout: magic, salt, nonce, then the ciphertext with its tag. The magic and
+// salt are authenticated as associated data.
+pub(crate) fn encrypt(plain: &[u8], passphrase: &str) -> Result<Vec<u8>, AppError> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let mut data = ENCRYPTED_MAGIC.to_vec();
+    data.extend_from_slice(&salt);
+
+    let cipher = XChaCha20Poly1305::new(&derive_key(passphrase, &salt)?);
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let sealed = cipher
+        .encrypt(&nonce, Payload { msg: plain, aad: &data })
+        .map_err(|e| AppError::Encryption(e.to_string()))?;
+    data.extend_from_slice(&nonce);
+    data.extend(sealed);
+    Ok(data)
+}
+
+pub(crate) fn decrypt(data: &[u8], passphrase: &str) -> Result<Vec<u8>, AppError> {
+    let header_len = ENCRYPTED_MAGIC.len() + SALT_LEN;
+    if !data.starts_with(ENCRYPTED_MAGIC) || data.len() < header_len + NONCE_LEN {
+        return Err(AppError::WrongPassphrase);
+    }
+    let (header, rest) = data.split_at(header_len);
+    let (nonce, sealed) = rest.split_at(NONCE_LEN);
+
+    let cipher = XChaCha20Poly1305::new(&derive_key(passphrase, &header[ENCRYPTED_MAGIC.len()..])?);
+    cipher
+        .decrypt(XNonce::from_slice(nonce), Payload { msg: sealed, aad: header })
+        .map_err(|_| AppError::WrongPassphrase)
+}
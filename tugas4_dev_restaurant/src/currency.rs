@@ -0,0 +1,134 @@
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Deserializer};
+
+use crate::error::ParseError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Currency {
+    Plain,
+    Idr,
+    Usd,
+    Eur,
+}
+
+struct Style {
+    symbol: &'static str,
+    spaced: bool,
+    thousands: Option<char>,
+    decimal: char,
+    always_decimals: bool,
+}
+
+impl Currency {
+    fn style(self) -> Style {
+        match self {
+            Currency::Plain => Style { symbol: "", spaced: false, thousands: None, decimal: '.', always_decimals: true },
+            Currency::Idr => Style { symbol: "Rp", spaced: true, thousands: Some('.'), decimal: ',', always_decimals: false },
+            Currency::Usd => Style { symbol: "$", spaced: false, thousands: Some(','), decimal: '.', always_decimals: true },
+            Currency::Eur => Style { symbol: "€", spaced: true, thousands: Some('.'), decimal: ',', always_decimals: true },
+        }
+    }
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Currency::Plain => "plain",
+            Currency::Idr => "idr",
+            Currency::Usd => "usd",
+            Currency::Eur => "eur",
+        }
+    }
+
+    pub(crate) fn format(self, cents: i64) -> String {
+        let style = self.style();
+        let amount = cents.unsigned_abs();
+        let mut out = String::new();
+        if cents < 0 {
+            out.push('-');
+        }
+        out.push_str(style.symbol);
+        if style.spaced {
+            out.push(' ');
+        }
+
+        let units = (amount / 100).to_string();
+        for (i, digit) in units.chars().enumerate() {
+            if let Some(separator) = style.thousands.filter(|_| i > 0 && (units.len() - i).is_multiple_of(3)) {
+                out.push(separator);
+            }
+            out.push(digit);
+        }
+        if style.always_decimals || !amount.is_multiple_of(100) {
+            out.push(style.decimal);
+            out.push_str(&format!("{:02}", amount % 100));
+        }
+        out
+    }
+}
+
+impl std::str::FromStr for Currency {
+    type Err = ParseError;
+
+    fn from_str(currency: &str) -> Result<Self, Self::Err> {
+        match currency.to_lowercase().as_str() {
+            "plain" | "none" => Ok(Currency::Plain),
+            "idr" | "rp" => Ok(Currency::Idr),
+            "usd" | "$" => Ok(Currency::Usd),
+            "eur" | "€" => Ok(Currency::Eur),
+            _ => Err(ParseError::InvalidCurrency(currency.to_owned())),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Currency {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+static ACTIVE: OnceLock<Currency> = OnceLock::new();
+
+pub(crate) fn active() -> Currency {
+    ACTIVE.get().copied().unwrap_or(Currency::Plain)
+}
+
+pub(crate) fn set_active(currency: Currency) {
+    let _ = ACTIVE.set(currency);
+}
+
+pub(crate) fn format(cents: i64) -> String {
+    active().format(cents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_of_zero() {
+        assert_eq!(Currency::Plain.format(0), "0.00");
+        assert_eq!(Currency::Idr.format(0), "Rp 0");
+        assert_eq!(Currency::Usd.format(0), "$0.00");
+        assert_eq!(Currency::Eur.format(0), "€ 0,00");
+    }
+
+    #[test]
+    fn format_groups_thousands() {
+        assert_eq!(Currency::Plain.format(123456789), "1234567.89");
+        assert_eq!(Currency::Idr.format(123456700), "Rp 1.234.567");
+        assert_eq!(Currency::Idr.format(123456789), "Rp 1.234.567,89");
+        assert_eq!(Currency::Usd.format(123456789), "$1,234,567.89");
+        assert_eq!(Currency::Eur.format(123456789), "€ 1.234.567,89");
+        assert_eq!(Currency::Usd.format(99999), "$999.99");
+        assert_eq!(Currency::Usd.format(100000), "$1,000.00");
+    }
+
+    #[test]
+    fn format_of_negative_amounts() {
+        assert_eq!(Currency::Plain.format(-1850), "-18.50");
+        assert_eq!(Currency::Idr.format(-1800000), "-Rp 18.000");
+        assert_eq!(Currency::Usd.format(-5), "-$0.05");
+        assert_eq!(Currency::Eur.format(-123456789), "-€ 1.234.567,89");
+        assert_eq!(Currency::Usd.format(i64::MIN), "-$92,233,720,368,547,758.08");
+    }
+}
@@ -0,0 +1,397 @@
+use std::path::PathBuf;
+
+use chrono::prelude::*;
+use thiserror::Error;
+
+use crate::model::{format_ids, Money, Quantity};
+use crate::storage::{SCHEMA_PREFIX, SCHEMA_VERSION};
+
+#[derive(Error, Debug)]
+pub enum ParseError {
+    #[error("id must be a number: {0}")]
+    InvalidId(#[from] std::num::ParseIntError),
+    
+    #[error("empty record")]
+    EmptyRecord,
+    
+    #[error("missing field: {0}")]
+    MissingField(String),
+
+    #[error("invalid timestamp: {0}")]
+    InvalidTimestamp(#[from] chrono::ParseError),
+
+    #[error("invalid date {0:?}, expected YYYY-MM-DD")]
+    InvalidDate(String),
+
+    #[error("invalid status: {0}")]
+    InvalidStatus(String),
+
+    #[error("invalid order item {0:?}, expected ID:QTY")]
+    InvalidOrderItem(String),
+
+    #[error("invalid price {0:?}, expected at most two decimals like 4.50 or cents like 450c")]
+    InvalidPrice(String),
+
+    #[error("price {0:?} is out of range, at most 9999999999999.99 either way")]
+    PriceOutOfRange(String),
+
+    #[error("unknown currency {0:?}, expected one of plain, idr, usd, eur")]
+    InvalidCurrency(String),
+
+    #[error("invalid quantity {0:?}, expected a number with at most three decimals")]
+    InvalidQuantity(String),
+
+    #[error("quantity {0:?} is out of range, at most 999999999999.999 either way")]
+    QuantityOutOfRange(String),
+
+    #[error("unterminated quoted field")]
+    UnterminatedQuote,
+
+    #[error("invalid expiry date {0:?}, expected YYYY-MM-DD")]
+    InvalidExpiry(String),
+
+    #[error("invalid flag {0:?}, expected 1 or 0")]
+    InvalidFlag(String),
+
+    #[error("invalid tax rate {0:?}, expected a percentage like 10 or a fraction like 0.10")]
+    InvalidTaxRate(String),
+
+    #[error("tax rate {0:?} could be a percentage or a fraction, write 1% as 0.01 and 50% as 50")]
+    AmbiguousTaxRate(String),
+
+    #[error("invalid id range {0:?}, expected START-END with START <= END and at most 10000 ids")]
+    InvalidIdRange(String),
+
+    #[error("{field}: {message}")]
+    InvalidField { field: String, message: String },
+
+    #[error("{0}")]
+    Csv(String),
+
+    #[error("header {0:?} does not match the expected id,name,stock,price,... columns")]
+    SchemaMismatch(String),
+
+    #[error("record {record} (line {line}): {source}")]
+    Record {
+        record: usize,
+        line: u64,
+        text: String,
+        source: Box<ParseError>,
+    },
+
+    #[error("{log} line number {line}: {source}")]
+    Line {
+        log: &'static str,
+        line: usize,
+        text: String,
+        source: Box<ParseError>,
+    },
+
+    #[error("invalid delimiter {0:?}, expected a single character or \\t for tab")]
+    InvalidDelimiter(String),
+
+    #[error("{} bad record(s) in the data file:{}", .0.len(), .0.iter().map(|e| format!("\n  {}", e)).collect::<String>())]
+    BadRecords(Vec<ParseError>),
+
+    #[error("duplicate id {id} on lines {first} and {line}")]
+    DuplicateRecord {
+        id: i64,
+        first: u64,
+        line: u64,
+        // The record on `line`, the one that is kept.
+        kept: String,
+    },
+
+    #[error("could not read data file: {0}")]
+    Read(String),
+
+    #[error("invalid schema line {0:?}, expected {SCHEMA_PREFIX}N")]
+    InvalidSchema(String),
+
+    #[error("data file uses schema {0}, but this version only understands up to schema {SCHEMA_VERSION}; upgrade the program")]
+    UnsupportedSchema(u32),
+
+    #[error("data file is not valid UTF-8 at byte {0}, use --lossy to replace bad bytes")]
+    InvalidUtf8(u64),
+
+    #[error("replaced invalid UTF-8 at byte {0}")]
+    ReplacedUtf8(u64),
+
+    #[error("{0} was changed outside of this program since it was last saved; check it, then run with --accept-external-changes")]
+    ExternalChange(PathBuf),
+}
+
+// A data file record that was skipped, or dropped for a later one with the
+// same id, while reading the rest of the file. Problems with the file as a
+// whole, such as an odd header, come without a line.
+#[derive(Debug)]
+pub struct ParseWarning {
+    pub line: usize,
+    pub raw: String,
+    pub error: ParseError,
+}
+
+impl From<ParseError> for ParseWarning {
+    fn from(error: ParseError) -> Self {
+        match &error {
+            ParseError::Record { line, text, .. } => ParseWarning { line: *line as usize, raw: text.clone(), error },
+            ParseError::Line { line, text, .. } => ParseWarning { line: *line, raw: text.clone(), error },
+            _ => ParseWarning { line: 0, raw: String::new(), error },
+        }
+    }
+}
+
+impl ParseError {
+    pub(crate) fn is_fatal(&self) -> bool {
+        matches!(self, ParseError::InvalidUtf8(_) | ParseError::Read(_))
+    }
+
+    pub(crate) fn from_csv(err: csv::Error, headers: &csv::StringRecord) -> Self {
+        match err.kind() {
+            csv::ErrorKind::Deserialize { err, .. } => {
+                match err.field().and_then(|field| headers.get(field as usize)) {
+                    Some(field) => ParseError::InvalidField {
+                        field: field.to_owned(),
+                        message: err.kind().to_string(),
+                    },
+                    None => ParseError::Csv(err.kind().to_string()),
+                }
+            }
+            csv::ErrorKind::Utf8 { err, .. } => ParseError::InvalidField {
+                field: headers.get(err.field()).unwrap_or_default().to_owned(),
+                message: "invalid UTF-8".to_owned(),
+            },
+            _ => ParseError::Csv(err.to_string()),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum AppError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+
+    // A value given on the command line rather than read from a file.
+    #[error(transparent)]
+    InvalidArgument(ParseError),
+
+    #[error("no item with id {0}")]
+    NotFound(i64),
+
+    #[error("nothing to change, pass at least one field such as --name, --stock or --price \
+             (without an id, --name only picks the item)")]
+    NothingToEdit,
+
+    #[error("no item with id {}, nothing was removed (pass --missing-ok to remove the rest)", format_ids(.0))]
+    MissingIds(Vec<i64>),
+
+    #[error("item {0} does not exist, so --upsert needs --name, --stock and --price to create it")]
+    UpsertIncomplete(i64),
+
+    #[error("{command} does not support --format {format}")]
+    UnsupportedFormat { command: &'static str, format: &'static str },
+
+    #[error("no sale with id {0}")]
+    SaleNotFound(i64),
+
+    #[error("sale {0} has already been refunded")]
+    AlreadyRefunded(i64),
+
+    #[error("no order with id {0}")]
+    OrderNotFound(i64),
+
+    #[error("no items given, expected ID:QTY pairs or an order subcommand")]
+    EmptyOrder,
+
+    #[error("{0} is already closed, use --force to overwrite it")]
+    DayAlreadyClosed(NaiveDate),
+
+    #[error("{0} already exists, use --overwrite to replace it")]
+    OutputExists(PathBuf),
+
+    #[error("invalid discount: {0}")]
+    InvalidDiscount(String),
+
+    #[error("invalid config file {}: {source}", path.display())]
+    Config { path: PathBuf, source: toml::de::Error },
+
+    #[error("invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("invalid data file: {0}")]
+    Csv(#[from] csv::Error),
+
+    #[cfg(feature = "sqlite")]
+    #[error("database error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+
+    #[cfg(not(all(feature = "sqlite", feature = "encryption", feature = "tui")))]
+    #[error("{0} support is not compiled in, rebuild with --features {0}")]
+    NotCompiledIn(&'static str),
+
+    #[cfg(feature = "encryption")]
+    #[error("wrong passphrase, or the encrypted data file is damaged")]
+    WrongPassphrase,
+
+    #[cfg(feature = "encryption")]
+    #[error("the passphrases do not match")]
+    PassphraseMismatch,
+
+    #[cfg(feature = "encryption")]
+    #[error("encryption failed: {0}")]
+    Encryption(String),
+
+    #[error("{0} already has menu items")]
+    StoreNotEmpty(PathBuf),
+
+    #[error("no backup named {0:?}, see the backups command")]
+    BackupNotFound(String),
+
+    #[error("no backups in {0}")]
+    NoBackups(PathBuf),
+
+    #[error("another process is using the data file {0}, try again in a moment")]
+    Locked(PathBuf),
+
+    #[error("no data file yet at {0}, add an item to create it")]
+    NoDataFile(PathBuf),
+
+    #[error("no profile named {0:?}, see profile list or create it with profile create")]
+    ProfileNotFound(String),
+
+    #[error("profile {0:?} already exists")]
+    ProfileExists(String),
+
+    #[error("invalid profile name {0:?}, use letters, digits, - and _")]
+    InvalidProfileName(String),
+
+    #[error("--profile and -d both pick a data file, use one of them")]
+    ProfileWithDataFile,
+
+    #[error("cannot find the config directory, set HOME or XDG_CONFIG_HOME")]
+    NoConfigDir,
+
+    #[error("-d - reads the items from stdin, which only works with commands that write no files")]
+    StdinDataFile,
+
+    #[error("id {0} appears more than once in the import")]
+    DuplicateId(i64),
+
+    #[error("order {id} is {status}, not pending")]
+    OrderNotPending { id: i64, status: &'static str },
+
+    #[error("quantity must be positive: {0}")]
+    InvalidQuantity(Quantity),
+
+    #[error("{name} is counted in {unit}, which cannot be split: {quantity}")]
+    FractionalQuantity {
+        name: String,
+        unit: String,
+        quantity: Quantity,
+    },
+
+    #[error("cost cannot be negative: {0}")]
+    InvalidCost(Money),
+
+    #[error("repricing would make {name} cost {price}, nothing was changed")]
+    NegativePrice { name: String, price: Money },
+
+    #[error("no item with SKU {0:?}")]
+    SkuNotFound(String),
+
+    #[error("batch not added: {rejected} rejected row(s) and {duplicates} duplicate name(s) with --strict")]
+    BatchRejected { rejected: usize, duplicates: usize },
+
+    #[error("no item named {0:?}")]
+    NameNotFound(String),
+
+    #[error("the name cannot be empty")]
+    EmptyName,
+
+    #[error("invalid regular expression: {0}")]
+    InvalidPattern(String),
+
+    #[error("item {id} is already called {name:?}; change it with edit or restock {id}, \
+             or pass --merge-stock or --allow-duplicate")]
+    DuplicateItem { name: String, id: i64 },
+
+    #[error("item {id} is already called {name:?}, pass a different name or run without --strict")]
+    DuplicateName { name: String, id: i64 },
+
+    #[error("{name:?} matches more than one item: {candidates}; use the id instead")]
+    AmbiguousName { name: String, candidates: String },
+
+    #[error("SKU {sku:?} is already used by item {id}")]
+    DuplicateSku {
+        sku: String,
+        id: i64,
+    },
+
+    #[error("{0} item(s) at or below minimum stock")]
+    LowStock(usize),
+
+    #[error("{0} item(s) out of stock")]
+    OutOfStock(usize),
+
+    #[error("{0} issue(s) found")]
+    ValidationFailed(usize),
+
+    #[error("{0} is 86'd and cannot be sold")]
+    Unavailable(String),
+
+    #[error("not enough stock for {name}: {stock} left, {requested} requested")]
+    InsufficientStock {
+        name: String,
+        stock: Quantity,
+        requested: Quantity,
+    },
+}
+
+pub const EXIT_FAILURE: i32 = 1;
+pub const EXIT_USAGE: i32 = 2;
+pub const EXIT_NOT_FOUND: i32 = 3;
+pub const EXIT_DATA_FILE: i32 = 4;
+pub const EXIT_STOCK_ALERT: i32 = 5;
+
+impl AppError {
+    // Scripts can tell a typo in the arguments from a missing item or a
+    // broken data file without parsing the message.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            AppError::NothingToEdit
+            | AppError::EmptyName
+            | AppError::InvalidPattern(_)
+            | AppError::UnsupportedFormat { .. }
+            | AppError::UpsertIncomplete(_)
+            | AppError::EmptyOrder
+            | AppError::InvalidDiscount(_)
+            | AppError::InvalidArgument(_)
+            | AppError::StdinDataFile
+            | AppError::InvalidProfileName(_)
+            | AppError::ProfileWithDataFile
+            | AppError::AmbiguousName { .. } => EXIT_USAGE,
+            AppError::NotFound(_)
+            | AppError::MissingIds(_)
+            | AppError::SaleNotFound(_)
+            | AppError::OrderNotFound(_)
+            | AppError::SkuNotFound(_)
+            | AppError::NameNotFound(_)
+            | AppError::BackupNotFound(_)
+            | AppError::ProfileNotFound(_) => EXIT_NOT_FOUND,
+            AppError::Io(_)
+            | AppError::Parse(_)
+            | AppError::Csv(_)
+            | AppError::NoDataFile(_)
+            | AppError::Locked(_) => EXIT_DATA_FILE,
+            AppError::LowStock(_) | AppError::OutOfStock(_) => EXIT_STOCK_ALERT,
+            #[cfg(feature = "sqlite")]
+            AppError::Sqlite(_) => EXIT_DATA_FILE,
+            #[cfg(not(all(feature = "sqlite", feature = "encryption", feature = "tui")))]
+            AppError::NotCompiledIn(_) => EXIT_USAGE,
+            _ => EXIT_FAILURE,
+        }
+    }
+}
@@ -0,0 +1,99 @@
+// Scores below this are not shown as fuzzy matches or suggestions.
+pub(crate) const THRESHOLD: f64 = 0.6;
+
+// How close query is to name, from 0 to 1, ignoring case. The whole strings
+// are compared, and so is each query word with its closest word in the name,
+// so a misspelt "gorng" still finds "Nasi Goreng" and "Mie Goreng".
+pub(crate) fn score(query: &str, name: &str) -> f64 {
+    let (query, name) = (query.trim().to_lowercase(), name.trim().to_lowercase());
+    if query.is_empty() || name.is_empty() {
+        return 0.0;
+    }
+    let whole = similarity(&query, &name);
+    let words: Vec<&str> = name.split_whitespace().collect();
+    let (mut total, mut count) = (0.0, 0);
+    for word in query.split_whitespace() {
+        total += words.iter().map(|candidate| similarity(word, candidate)).fold(0.0, f64::max);
+        count += 1;
+    }
+    // Matching fewer words than the name has counts for a little less.
+    let coverage = count.min(words.len()) as f64 / words.len() as f64;
+    let by_word = total / count as f64 * (0.5 + coverage / 2.0);
+    whole.max(by_word)
+}
+
+// One minus the edit distance over the longer length.
+fn similarity(a: &str, b: &str) -> f64 {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+    1.0 - distance(&a, &b) as f64 / longest as f64
+}
+
+// Levenshtein distance where swapping two neighbouring letters, as in "teh"
+// and "the", costs one edit rather than two.
+fn distance(a: &[char], b: &[char]) -> usize {
+    let mut rows = vec![(0..=b.len()).collect::<Vec<usize>>()];
+    for (i, ca) in a.iter().enumerate() {
+        let mut row = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let above = &rows[i];
+            let mut best = (above[j] + usize::from(ca != cb)).min(above[j + 1] + 1).min(row[j] + 1);
+            if i > 0 && j > 0 && *ca == b[j - 1] && a[i - 1] == *cb {
+                best = best.min(rows[i - 1][j - 1] + 1);
+            }
+            row[j + 1] = best;
+        }
+        rows.push(row);
+    }
+    rows[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MENU: [&str; 6] = ["bakso", "bakso urat", "sate ayam", "nasi goreng", "mie goreng", "es teh"];
+
+    // The menu names at or above the threshold, best first, the way search ranks them.
+    fn ranked(query: &str) -> Vec<&'static str> {
+        let mut scored: Vec<(f64, &str)> = MENU
+            .iter()
+            .map(|name| (score(query, name), *name))
+            .filter(|(score, _)| *score >= THRESHOLD)
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        scored.into_iter().map(|(_, name)| name).collect()
+    }
+
+    #[test]
+    fn a_dropped_letter_ranks_the_item_first() {
+        assert_eq!(ranked("bkso"), ["bakso", "bakso urat"]);
+    }
+
+    #[test]
+    fn swapped_letters_count_as_one_edit() {
+        assert_eq!(distance(&['e', 's'], &['s', 'e']), 1);
+        assert_eq!(ranked("stae ayam")[0], "sate ayam");
+    }
+
+    #[test]
+    fn a_misspelt_word_finds_every_name_with_it() {
+        let found = ranked("gorng");
+        assert_eq!(found.len(), 2, "{:?}", found);
+        assert!(found.contains(&"nasi goreng") && found.contains(&"mie goreng"));
+    }
+
+    #[test]
+    fn case_and_spaces_are_ignored() {
+        assert_eq!(score("  BAKSO ", "Bakso"), 1.0);
+    }
+
+    #[test]
+    fn below_the_threshold_nothing_matches() {
+        assert!(ranked("rendang").is_empty());
+        assert!(ranked("").is_empty());
+    }
+}
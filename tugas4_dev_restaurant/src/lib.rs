@@ -0,0 +1,19 @@
+#[cfg(feature = "encryption")]
+mod crypto;
+mod color;
+mod config;
+mod currency;
+mod fuzzy;
+mod output;
+mod shell;
+mod table;
+#[cfg(feature = "tui")]
+mod tui;
+
+pub mod cli;
+pub mod error;
+pub mod model;
+pub mod storage;
+
+pub use error::{AppError, ParseError, ParseWarning};
+pub use model::{Food, Foods, Money, Quantity};
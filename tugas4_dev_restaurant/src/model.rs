@@ -0,0 +1,2094 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use chrono::prelude::*;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::error::{AppError, ParseError};
+use crate::{currency, fuzzy};
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Money(pub(crate) i64);
+
+impl Money {
+    pub fn cents(self) -> i64 {
+        self.0
+    }
+}
+
+impl std::fmt::Display for Money {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.pad(&currency::format(self.0))
+    }
+}
+
+impl std::fmt::Debug for Money {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.pad(&currency::format(self.0))
+    }
+}
+
+impl std::str::FromStr for Money {
+    type Err = ParseError;
+
+    fn from_str(price: &str) -> Result<Self, Self::Err> {
+        parse_price(price).map(Money)
+    }
+}
+
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format_price(self.0))
+    }
+}
+
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(NumberVisitor(std::marker::PhantomData))
+    }
+}
+
+struct NumberVisitor<T>(std::marker::PhantomData<T>);
+
+impl<'de, T: std::str::FromStr<Err = ParseError>> serde::de::Visitor<'de> for NumberVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("a number")
+    }
+
+    fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<T, E> {
+        value.parse().map_err(E::custom)
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<T, E> {
+        self.visit_str(&value.to_string())
+    }
+
+    fn visit_i64<E: serde::de::Error>(self, value: i64) -> Result<T, E> {
+        self.visit_str(&value.to_string())
+    }
+
+    fn visit_f64<E: serde::de::Error>(self, value: f64) -> Result<T, E> {
+        self.visit_str(&value.to_string())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Quantity(pub(crate) i64);
+
+pub(crate) const QUANTITY_SCALE: i64 = 1000;
+
+impl Quantity {
+    fn is_whole(self) -> bool {
+        self.0 % QUANTITY_SCALE == 0
+    }
+
+    pub(crate) fn is_positive(self) -> bool {
+        self.0 > 0
+    }
+
+    pub(crate) fn times(self, cents: i64) -> i64 {
+        let scale = QUANTITY_SCALE as i128;
+        ((self.0 as i128 * cents as i128 * 2 + scale) / (scale * 2)) as i64
+    }
+}
+
+impl std::fmt::Display for Quantity {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let amount = self.0.unsigned_abs();
+        let scale = QUANTITY_SCALE as u64;
+        let shown = match amount % scale {
+            0 => format!("{}{}", sign, amount / scale),
+            fraction => {
+                let fraction = format!("{:03}", fraction);
+                format!("{}{}.{}", sign, amount / scale, fraction.trim_end_matches('0'))
+            }
+        };
+        f.pad(&shown)
+    }
+}
+
+impl std::fmt::Debug for Quantity {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        std::fmt::Display::fmt(self, f)
+    }
+}
+
+impl std::str::FromStr for Quantity {
+    type Err = ParseError;
+
+    fn from_str(quantity: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseError::InvalidQuantity(quantity.to_owned());
+        let trimmed = quantity.trim();
+        let (negative, digits) = match trimmed.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, trimmed),
+        };
+        let (units, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        let all_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+        if units.is_empty() || !all_digits(units) || !all_digits(fraction) || fraction.len() > 3 {
+            return Err(invalid());
+        }
+        let units = units.parse::<i128>().map_err(|_| invalid())?;
+        let fraction = match fraction {
+            "" => 0,
+            fraction => format!("{:0<3}", fraction).parse::<i128>().map_err(|_| invalid())?,
+        };
+        let amount = units
+            .checked_mul(QUANTITY_SCALE as i128)
+            .and_then(|amount| amount.checked_add(fraction))
+            .ok_or_else(invalid)?;
+        signed(negative, amount).map(Quantity).ok_or_else(|| ParseError::QuantityOutOfRange(quantity.to_owned()))
+    }
+}
+
+impl Serialize for Quantity {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.is_whole() {
+            serializer.serialize_i64(self.0 / QUANTITY_SCALE)
+        } else {
+            serializer.serialize_f64(self.0 as f64 / QUANTITY_SCALE as f64)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Quantity {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(NumberVisitor(std::marker::PhantomData))
+    }
+}
+
+impl std::ops::Add for Quantity {
+    type Output = Quantity;
+
+    fn add(self, other: Quantity) -> Quantity {
+        Quantity(self.0 + other.0)
+    }
+}
+
+impl std::ops::Sub for Quantity {
+    type Output = Quantity;
+
+    fn sub(self, other: Quantity) -> Quantity {
+        Quantity(self.0 - other.0)
+    }
+}
+
+impl std::ops::AddAssign for Quantity {
+    fn add_assign(&mut self, other: Quantity) {
+        self.0 += other.0;
+    }
+}
+
+impl std::ops::SubAssign for Quantity {
+    fn sub_assign(&mut self, other: Quantity) {
+        self.0 -= other.0;
+    }
+}
+
+impl std::iter::Sum for Quantity {
+    fn sum<I: Iterator<Item = Quantity>>(iter: I) -> Quantity {
+        Quantity(iter.map(|quantity| quantity.0).sum())
+    }
+}
+
+const DEFAULT_UNIT: &str = "pcs";
+const DIVISIBLE_UNITS: [&str; 4] = ["kg", "g", "l", "ml"];
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Food {
+    pub id: i64,
+    pub name: String,
+    pub stock: Quantity,
+    pub price: Money,
+    #[serde(default, deserialize_with = "or_default")]
+    pub sold_count: Quantity,
+    #[serde(default)]
+    pub cost: Option<Money>,
+    #[serde(default)]
+    pub category: String,
+    #[serde(default = "available_default", serialize_with = "serialize_flag", deserialize_with = "deserialize_flag")]
+    pub available: bool,
+    #[serde(default, deserialize_with = "or_default")]
+    pub min_stock: Quantity,
+    #[serde(default, deserialize_with = "deserialize_expiry")]
+    pub expires: Option<NaiveDate>,
+    #[serde(default)]
+    pub supplier: String,
+    #[serde(default)]
+    pub sku: Option<String>,
+    #[serde(default, serialize_with = "serialize_tags", deserialize_with = "deserialize_tags")]
+    pub allergens: BTreeSet<String>,
+    #[serde(default)]
+    pub unit: String,
+    #[serde(default)]
+    pub notes: String,
+    #[serde(default)]
+    pub created_at: Option<DateTime<Local>>,
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Local>>,
+    #[serde(default, serialize_with = "serialize_tags", deserialize_with = "deserialize_tags")]
+    pub tags: BTreeSet<String>,
+    #[serde(default, deserialize_with = "deserialize_date")]
+    pub special_until: Option<NaiveDate>,
+    #[serde(skip)]
+    pub(crate) extra: Vec<(String, String)>,
+}
+
+pub(crate) const FOOD_HEADER: [&str; 19] = [
+    "id", "name", "stock", "price", "sold_count", "cost", "category", "available", "min_stock", "expires",
+    "supplier", "sku", "allergens", "unit", "notes", "created_at", "updated_at", "tags", "special_until",
+];
+
+pub(crate) fn or_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Default,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+fn available_default() -> bool {
+    true
+}
+
+fn serialize_flag<S: Serializer>(flag: &bool, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u8(if *flag { 1 } else { 0 })
+}
+
+struct FlagVisitor;
+
+impl<'de> serde::de::Visitor<'de> for FlagVisitor {
+    type Value = bool;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("1 or 0")
+    }
+
+    fn visit_bool<E: serde::de::Error>(self, value: bool) -> Result<bool, E> {
+        Ok(value)
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<bool, E> {
+        self.visit_str(&value.to_string())
+    }
+
+    fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<bool, E> {
+        match value {
+            "" | "1" => Ok(true),
+            "0" => Ok(false),
+            _ => Err(E::custom(ParseError::InvalidFlag(value.to_owned()))),
+        }
+    }
+}
+
+fn deserialize_flag<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    deserializer.deserialize_any(FlagVisitor)
+}
+
+fn deserialize_expiry<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<NaiveDate>, D::Error> {
+    match Option::<String>::deserialize(deserializer)?.filter(|expires| !expires.is_empty()) {
+        Some(expires) => match NaiveDate::parse_from_str(&expires, "%Y-%m-%d") {
+            Ok(expires) => Ok(Some(expires)),
+            Err(_) => Err(serde::de::Error::custom(ParseError::InvalidExpiry(expires))),
+        },
+        None => Ok(None),
+    }
+}
+
+fn deserialize_date<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<NaiveDate>, D::Error> {
+    match Option::<String>::deserialize(deserializer)?.filter(|date| !date.is_empty()) {
+        Some(date) => parse_date(&date).map(Some).map_err(serde::de::Error::custom),
+        None => Ok(None),
+    }
+}
+
+fn serialize_tags<S: Serializer>(tags: &BTreeSet<String>, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&join_tags(tags))
+}
+
+fn deserialize_tags<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BTreeSet<String>, D::Error> {
+    Ok(parse_tags(&Option::<String>::deserialize(deserializer)?.unwrap_or_default()))
+}
+
+// An item as json and jsonl have it. The data file flattens prices, flags and
+// tag lists into text, here they are numbers, booleans and arrays. Importing
+// still takes the flattened forms, as older exports wrote them.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct JsonFood {
+    id: i64,
+    name: String,
+    stock: Quantity,
+    #[serde(serialize_with = "serialize_amount")]
+    price: Money,
+    #[serde(default, deserialize_with = "or_default")]
+    sold_count: Quantity,
+    #[serde(default, serialize_with = "serialize_cost")]
+    cost: Option<Money>,
+    #[serde(default)]
+    category: String,
+    #[serde(default = "available_default", deserialize_with = "deserialize_flag")]
+    available: bool,
+    #[serde(default, deserialize_with = "or_default")]
+    min_stock: Quantity,
+    #[serde(default, deserialize_with = "deserialize_expiry")]
+    expires: Option<NaiveDate>,
+    #[serde(default)]
+    supplier: String,
+    #[serde(default)]
+    sku: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_tag_list")]
+    allergens: BTreeSet<String>,
+    #[serde(default)]
+    unit: String,
+    #[serde(default)]
+    notes: String,
+    #[serde(default)]
+    created_at: Option<DateTime<Local>>,
+    #[serde(default)]
+    updated_at: Option<DateTime<Local>>,
+    #[serde(default, deserialize_with = "deserialize_tag_list")]
+    tags: BTreeSet<String>,
+    #[serde(default, deserialize_with = "deserialize_date")]
+    special_until: Option<NaiveDate>,
+}
+
+impl From<&Food> for JsonFood {
+    fn from(food: &Food) -> Self {
+        JsonFood {
+            id: food.id,
+            name: food.name.clone(),
+            stock: food.stock,
+            price: food.price,
+            sold_count: food.sold_count,
+            cost: food.cost,
+            category: food.category.clone(),
+            available: food.available,
+            min_stock: food.min_stock,
+            expires: food.expires,
+            supplier: food.supplier.clone(),
+            sku: food.sku.clone(),
+            allergens: food.allergens.clone(),
+            unit: food.unit.clone(),
+            notes: food.notes.clone(),
+            created_at: food.created_at,
+            updated_at: food.updated_at,
+            tags: food.tags.clone(),
+            special_until: food.special_until,
+        }
+    }
+}
+
+impl From<JsonFood> for Food {
+    fn from(food: JsonFood) -> Self {
+        Food {
+            id: food.id,
+            name: food.name,
+            stock: food.stock,
+            price: food.price,
+            sold_count: food.sold_count,
+            cost: food.cost,
+            category: food.category,
+            available: food.available,
+            min_stock: food.min_stock,
+            expires: food.expires,
+            supplier: food.supplier,
+            sku: food.sku,
+            allergens: food.allergens,
+            unit: food.unit,
+            notes: food.notes,
+            created_at: food.created_at,
+            updated_at: food.updated_at,
+            tags: food.tags,
+            special_until: food.special_until,
+            extra: Vec::new(),
+        }
+    }
+}
+
+// Whole amounts as integers and the rest with their cents, like Quantity.
+fn serialize_amount<S: Serializer>(money: &Money, serializer: S) -> Result<S::Ok, S::Error> {
+    match money.cents() {
+        cents if cents % 100 == 0 => serializer.serialize_i64(cents / 100),
+        cents => serializer.serialize_f64(cents as f64 / 100.0),
+    }
+}
+
+fn serialize_cost<S: Serializer>(cost: &Option<Money>, serializer: S) -> Result<S::Ok, S::Error> {
+    match cost {
+        Some(cost) => serialize_amount(cost, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+struct TagListVisitor;
+
+impl<'de> serde::de::Visitor<'de> for TagListVisitor {
+    type Value = BTreeSet<String>;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("a list of tags")
+    }
+
+    fn visit_unit<E: serde::de::Error>(self) -> Result<Self::Value, E> {
+        Ok(BTreeSet::new())
+    }
+
+    fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        Ok(parse_tags(value))
+    }
+
+    fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut tags = Vec::new();
+        while let Some(tag) = seq.next_element::<String>()? {
+            tags.push(tag);
+        }
+        Ok(parse_tags(&tags.join(";")))
+    }
+}
+
+fn deserialize_tag_list<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BTreeSet<String>, D::Error> {
+    deserializer.deserialize_any(TagListVisitor)
+}
+
+impl std::fmt::Debug for Food {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Food")
+            .field("id", &self.id)
+            .field("name", &self.name)
+            .field("stock", &format_args!("{} {}", self.stock, self.unit))
+            .field("price", &self.price)
+            .field("sold_count", &self.sold_count)
+            .field("cost", &self.cost)
+            .field("category", &self.category)
+            .field("available", &self.available)
+            .field("min_stock", &self.min_stock)
+            .field("expires", &self.expires)
+            .field("supplier", &self.supplier)
+            .field("sku", &self.sku)
+            .field("allergens", &self.allergens)
+            .field("tags", &self.tags)
+            .finish()
+    }
+}
+
+const DEFAULT_CATEGORY: &str = "uncategorized";
+
+impl Food {
+    pub fn new(id: i64, name: &str, stock: Quantity, price: Money) -> Self {
+        Self {
+            id,
+            name: normalize_name(name),
+            stock,
+            price,
+            sold_count: Quantity::default(),
+            cost: None,
+            category: DEFAULT_CATEGORY.to_string(),
+            available: true,
+            min_stock: Quantity::default(),
+            expires: None,
+            supplier: String::new(),
+            sku: None,
+            allergens: BTreeSet::new(),
+            unit: DEFAULT_UNIT.to_string(),
+            notes: String::new(),
+            created_at: None,
+            updated_at: None,
+            tags: BTreeSet::new(),
+            special_until: None,
+            extra: Vec::new(),
+        }
+    }
+
+    pub(crate) fn normalize(mut self) -> Result<Self, ParseError> {
+        if self.name.is_empty() {
+            return Err(ParseError::MissingField("name".to_owned()));
+        }
+        if self.category.is_empty() {
+            self.category = DEFAULT_CATEGORY.to_string();
+        }
+        self.unit = match self.unit.to_lowercase() {
+            unit if unit.is_empty() => DEFAULT_UNIT.to_string(),
+            unit => unit,
+        };
+        self.sku = self.sku.filter(|sku| !sku.is_empty());
+        Ok(self)
+    }
+
+    pub(crate) fn matches(&self, mode: &SearchMode, fields: &[SearchField]) -> bool {
+        fields.iter().any(|field| match field {
+            SearchField::Name => mode.matches(self),
+            SearchField::Category => mode.matches_text(&self.category),
+            SearchField::Notes => mode.matches_text(&self.notes),
+        })
+    }
+
+    pub(crate) fn stamp_created(&mut self) {
+        let now = Local::now();
+        self.created_at = Some(now);
+        self.updated_at = Some(now);
+    }
+
+    pub(crate) fn is_special(&self, today: NaiveDate) -> bool {
+        self.special_until.is_some_and(|until| until >= today)
+    }
+
+    pub(crate) fn has_tag(&self, tag: &str) -> bool {
+        self.tags.contains(&tag.trim().to_lowercase())
+    }
+
+    pub(crate) fn check_divisible(&self, quantity: Quantity) -> Result<(), AppError> {
+        if !quantity.is_whole() && !DIVISIBLE_UNITS.contains(&self.unit.as_str()) {
+            return Err(AppError::FractionalQuantity {
+                name: self.name.clone(),
+                unit: self.unit.clone(),
+                quantity,
+            });
+        }
+        Ok(())
+    }
+
+    pub(crate) fn margin(&self) -> Option<i64> {
+        self.cost.map(|cost| self.price.cents() - cost.cents())
+    }
+
+    pub(crate) fn margin_percent(&self) -> Option<f64> {
+        match self.margin() {
+            Some(margin) if self.price.cents() != 0 => Some(margin as f64 * 100.0 / self.price.cents() as f64),
+            _ => None,
+        }
+    }
+}
+#[derive(Debug, Default)]
+pub(crate) struct FoodIndex {
+    pub(crate) next_id: i64,
+    pub(crate) skus: HashMap<String, i64>,
+    // name_key -> the first item with that name
+    pub(crate) names: HashMap<String, (i64, String)>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Foods {
+    pub(crate) inner: HashMap<i64, Food>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SortKey {
+    Id,
+    Name,
+    Price,
+    Stock,
+    Popularity,
+    Margin,
+    Updated,
+    File,
+}
+
+impl SortKey {
+    // Popularity, margin and updated list the most/highest/newest first unless asked otherwise.
+    pub(crate) fn default_order(self) -> SortOrder {
+        match self {
+            SortKey::Popularity | SortKey::Margin | SortKey::Updated => SortOrder::Desc,
+            _ => SortOrder::Asc,
+        }
+    }
+}
+
+impl std::str::FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(key: &str) -> Result<Self, Self::Err> {
+        match key {
+            "id" => Ok(SortKey::Id),
+            "name" => Ok(SortKey::Name),
+            "price" => Ok(SortKey::Price),
+            "stock" => Ok(SortKey::Stock),
+            "popularity" => Ok(SortKey::Popularity),
+            "margin" => Ok(SortKey::Margin),
+            "updated" => Ok(SortKey::Updated),
+            "file" => Ok(SortKey::File),
+            _ => Err(format!("unknown sort key: {}", key)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SortOrder {
+    Asc,
+    Desc,
+}
+
+// Ties always fall back to ascending id, whatever the order.
+pub(crate) fn sort_foods(foods: &mut [&Food], key: SortKey, order: SortOrder) {
+    foods.sort_by(|a, b| {
+        let ordering = match key {
+            SortKey::Id | SortKey::File => a.id.cmp(&b.id),
+            SortKey::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            SortKey::Price => a.price.cmp(&b.price),
+            SortKey::Stock => a.stock.cmp(&b.stock),
+            SortKey::Popularity => a.sold_count.cmp(&b.sold_count),
+            SortKey::Margin => a.margin().unwrap_or(i64::MIN).cmp(&b.margin().unwrap_or(i64::MIN)),
+            SortKey::Updated => a.updated_at.cmp(&b.updated_at),
+        };
+        let ordering = if order == SortOrder::Desc { ordering.reverse() } else { ordering };
+        ordering.then(a.id.cmp(&b.id))
+    });
+}
+
+impl FromIterator<Food> for Foods {
+    fn from_iter<I: IntoIterator<Item = Food>>(foods: I) -> Self {
+        let mut fds = Foods::new();
+        for food in foods {
+            fds.insert(food);
+        }
+        fds
+    }
+}
+
+impl Serialize for Foods {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.sorted())
+    }
+}
+
+impl<'de> Deserialize<'de> for Foods {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut fds = Foods::new();
+        for food in Vec::<Food>::deserialize(deserializer)? {
+            let food = food.normalize().map_err(serde::de::Error::custom)?;
+            if fds.inner.contains_key(&food.id) {
+                return Err(serde::de::Error::custom(format!("duplicate id {}", food.id)));
+            }
+            fds.insert(food);
+        }
+        Ok(fds)
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct PricedItem {
+    pub(crate) id: i64,
+    pub(crate) name: String,
+    pub(crate) price: Money,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct InventoryStats {
+    pub(crate) items: usize,
+    pub(crate) units: Quantity,
+    pub(crate) value: Money,
+    average_price: Money,
+    cheapest: Option<PricedItem>,
+    most_expensive: Option<PricedItem>,
+    pub(crate) out_of_stock: usize,
+}
+
+impl InventoryStats {
+    pub(crate) fn rows(&self) -> Vec<(&'static str, String)> {
+        let item = |item: &Option<PricedItem>| match item {
+            Some(item) => format!("{} (id {}) {}", item.name, item.id, currency::format(item.price.0)),
+            None => "-".to_owned(),
+        };
+        vec![
+            ("Items", self.items.to_string()),
+            ("Units in stock", self.units.to_string()),
+            ("Stock value", currency::format(self.value.0)),
+            ("Average price", currency::format(self.average_price.0)),
+            ("Cheapest", item(&self.cheapest)),
+            ("Most expensive", item(&self.most_expensive)),
+            ("Out of stock", self.out_of_stock.to_string()),
+        ]
+    }
+}
+
+impl Foods {
+    pub fn new() -> Self{ 
+        Self{
+            inner: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn edit(
+        &mut self,
+        id: i64,
+        name: Option<String>,
+        stock: Option<Quantity>,
+        price: Option<Money>,
+        details: impl FnOnce(&mut Food),
+    ) -> Result<&Food, AppError> {
+        let food = match self.inner.get_mut(&id) {
+            Some(food) => food,
+            None => return Err(AppError::NotFound(id)),
+        };
+        if let Some(name) = name {
+            let name = normalize_name(&name);
+            if name.is_empty() {
+                return Err(AppError::EmptyName);
+            }
+            food.name = name;
+        }
+        if let Some(stock) = stock {
+            food.stock = stock;
+        }
+        if let Some(price) = price {
+            food.price = price;
+        }
+        details(food);
+        food.updated_at = Some(Local::now());
+        Ok(food)
+    }
+
+    pub(crate) fn expiring_between(&self, from: Option<NaiveDate>, to: NaiveDate) -> Vec<&Food> {
+        let mut foods: Vec<&Food> = self
+            .inner
+            .values()
+            .filter(|food| food.expires.is_some_and(|expires| expires <= to && from.is_none_or(|from| expires >= from)))
+            .collect();
+        foods.sort_by_key(|food| (food.expires, food.id));
+        foods
+    }
+
+    // Exact, case-insensitive; more than one match is an error listing them.
+    pub fn by_name(&self, name: &str) -> Result<&Food, AppError> {
+        let wanted = name_key(name);
+        let found: Vec<&Food> = self.sorted().into_iter().filter(|food| name_key(&food.name) == wanted).collect();
+        match found.as_slice() {
+            [] => Err(AppError::NameNotFound(name.to_owned())),
+            [food] => Ok(food),
+            foods => Err(AppError::AmbiguousName {
+                name: name.to_owned(),
+                candidates: foods.iter().map(|food| format!("{} (id {})", food.name, food.id)).collect::<Vec<_>>().join(", "),
+            }),
+        }
+    }
+
+    pub fn by_sku(&self, sku: &str) -> Result<&Food, AppError> {
+        match self.inner.values().find(|food| food.sku.as_deref() == Some(sku)) {
+            Some(food) => Ok(food),
+            None => Err(AppError::SkuNotFound(sku.to_owned())),
+        }
+    }
+
+    pub(crate) fn retag(&mut self, id: i64, add: &[String], remove: &[String]) -> Result<&Food, AppError> {
+        let food = match self.inner.get_mut(&id) {
+            Some(food) => food,
+            None => return Err(AppError::NotFound(id)),
+        };
+        for tag in add {
+            food.tags.extend(parse_tags(tag));
+        }
+        for tag in remove {
+            for tag in parse_tags(tag) {
+                food.tags.remove(&tag);
+            }
+        }
+        food.updated_at = Some(Local::now());
+        Ok(food)
+    }
+
+    pub(crate) fn set_special(&mut self, id: i64, until: NaiveDate) -> Result<&Food, AppError> {
+        let food = match self.inner.get_mut(&id) {
+            Some(food) => food,
+            None => return Err(AppError::NotFound(id)),
+        };
+        food.special_until = Some(until);
+        food.updated_at = Some(Local::now());
+        Ok(food)
+    }
+
+    pub(crate) fn tag_counts(&self) -> BTreeMap<&str, usize> {
+        let mut counts = BTreeMap::new();
+        for tag in self.inner.values().flat_map(|food| food.tags.iter()) {
+            *counts.entry(tag.as_str()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    pub(crate) fn with_allergen(&self, allergen: &str) -> Vec<&Food> {
+        let allergen = allergen.trim().to_lowercase();
+        let mut foods: Vec<&Food> = self.inner.values().filter(|food| food.allergens.contains(&allergen)).collect();
+        foods.sort_by_key(|food| food.id);
+        foods
+    }
+
+    // At or below the threshold when one is given, otherwise each item's own minimum.
+    pub fn low_stock(&self, threshold: Option<Quantity>) -> Vec<&Food> {
+        let min = |food: &Food| threshold.unwrap_or(food.min_stock);
+        let mut foods: Vec<&Food> = self.inner.values().filter(|food| food.stock <= min(food)).collect();
+        foods.sort_by_key(|food| (food.stock - min(food), food.id));
+        foods
+    }
+
+    pub fn out_of_stock(&self) -> Vec<&Food> {
+        let mut foods: Vec<&Food> = self.inner.values().filter(|food| food.stock <= Quantity::default()).collect();
+        foods.sort_by_key(|food| food.id);
+        foods
+    }
+
+    pub fn next_id(&self) -> i64 {
+        let mut ids: Vec<_> = self.inner.keys().collect();
+        ids.sort();
+        match ids.pop() {
+            Some(id) => id + 1,
+            None => 1,
+        }
+    }
+
+    pub fn add(&mut self, mut food: Food) {
+        food.stamp_created();
+        self.insert(food);
+    }
+
+    pub(crate) fn index(&self) -> FoodIndex {
+        FoodIndex {
+            next_id: self.next_id(),
+            skus: self
+                .inner
+                .values()
+                .filter_map(|food| food.sku.clone().map(|sku| (sku, food.id)))
+                .collect(),
+            names: self
+                .sorted()
+                .into_iter()
+                .rev()
+                .map(|food| (name_key(&food.name), (food.id, food.name.clone())))
+                .collect(),
+        }
+    }
+
+    // Groups of two or more items sharing a name, each sorted by id.
+    pub(crate) fn duplicate_names(&self) -> Vec<Vec<&Food>> {
+        let mut groups: BTreeMap<String, Vec<&Food>> = BTreeMap::new();
+        for food in self.sorted() {
+            groups.entry(name_key(&food.name)).or_default().push(food);
+        }
+        let mut groups: Vec<Vec<&Food>> = groups.into_values().filter(|group| group.len() > 1).collect();
+        groups.sort_by_key(|group| group[0].id);
+        groups
+    }
+
+    pub fn insert(&mut self, food: Food) {
+        self.inner.insert(food.id, food);
+    }
+
+    pub(crate) fn import(&mut self, foods: Vec<Food>, replace: bool) -> Result<(usize, usize), AppError> {
+        if replace {
+            self.inner.clear();
+        }
+        let (mut added, mut skipped) = (0, 0);
+        for food in foods {
+            if self.inner.contains_key(&food.id) {
+                skipped += 1;
+            } else if food.created_at.is_some() {
+                self.insert(food);
+                added += 1;
+            } else {
+                self.add(food);
+                added += 1;
+            }
+        }
+
+        let mut skus = HashMap::new();
+        for food in self.sorted() {
+            if let Some(sku) = &food.sku {
+                if let Some(id) = skus.insert(sku.as_str(), food.id) {
+                    return Err(AppError::DuplicateSku { sku: sku.clone(), id });
+                }
+            }
+        }
+        Ok((added, skipped))
+    }
+
+    pub fn sorted(&self) -> Vec<&Food> {
+        self.sorted_vec(SortKey::Id, SortOrder::Asc)
+    }
+
+    // Sums are taken in i128 and saturate, a menu worth more than i64 cents
+    // is not worth failing over.
+    pub(crate) fn stats(&self) -> InventoryStats {
+        let saturate = |sum: i128| sum.clamp(i64::MIN as i128, i64::MAX as i128) as i64;
+        let foods = self.sorted();
+        let units: i128 = foods.iter().map(|food| food.stock.0 as i128).sum();
+        let value: i128 = foods
+            .iter()
+            .map(|food| food.stock.0 as i128 * food.price.0 as i128 / QUANTITY_SCALE as i128)
+            .sum();
+        let prices: i128 = foods.iter().map(|food| food.price.0 as i128).sum();
+        let priced = |food: &&Food| PricedItem { id: food.id, name: food.name.clone(), price: food.price };
+        InventoryStats {
+            items: foods.len(),
+            units: Quantity(saturate(units)),
+            value: Money(saturate(value)),
+            average_price: Money(saturate(prices / foods.len().max(1) as i128)),
+            cheapest: foods.iter().min_by_key(|food| food.price).map(priced),
+            most_expensive: foods.iter().max_by_key(|food| food.price).map(priced),
+            out_of_stock: foods.iter().filter(|food| food.stock <= Quantity::default()).count(),
+        }
+    }
+
+    fn sorted_vec(&self, key: SortKey, order: SortOrder) -> Vec<&Food> {
+        let mut foods: Vec<&Food> = self.inner.values().collect();
+        sort_foods(&mut foods, key, order);
+        foods
+    }
+
+    pub fn get(&self, id: i64) -> Option<&Food> {
+        self.inner.get(&id)
+    }
+
+    // Items whose name contains query, ignoring case, best matches first.
+    pub fn search(&self, query: &str) -> Vec<&Food> {
+        let mode = SearchMode::substring(query);
+        let mut foods: Vec<&Food> = self.sorted().into_iter().filter(|food| mode.matches(food)).collect();
+        foods.sort_by_key(|food| mode.rank(&food.name));
+        foods
+    }
+
+    pub(crate) fn filter(&self, filter: impl Fn(&Food) -> bool) -> Vec<&Food> {
+        let mut foods: Vec<&Food> = self.inner.values().filter(|food| filter(food)).collect();
+        foods.sort_by_key(|food| food.id);
+        foods
+    }
+
+    pub fn into_vec(mut self) -> Vec<Food> {
+        let mut foods: Vec<_> = self.inner.drain().map(|kv| kv.1).collect();
+        foods.sort_by_key(|fd| fd.id);
+        foods
+    }
+
+    pub fn remove(&mut self, id: i64) -> Option<Food> {
+        self.inner.remove(&id)
+    }
+
+    fn check_stock(&self, id: i64, quantity: Quantity, force: bool) -> Result<&Food, AppError> {
+        if !quantity.is_positive() {
+            return Err(AppError::InvalidQuantity(quantity));
+        }
+        let food = match self.inner.get(&id) {
+            Some(food) => food,
+            None => return Err(AppError::NotFound(id)),
+        };
+        food.check_divisible(quantity)?;
+        if food.stock < quantity && !force {
+            return Err(AppError::InsufficientStock {
+                name: food.name.clone(),
+                stock: food.stock,
+                requested: quantity,
+            });
+        }
+        Ok(food)
+    }
+
+    fn check_sellable(&self, id: i64, quantity: Quantity, force: bool) -> Result<&Food, AppError> {
+        let food = self.check_stock(id, quantity, force)?;
+        if !food.available {
+            return Err(AppError::Unavailable(food.name.clone()));
+        }
+        Ok(food)
+    }
+
+    pub(crate) fn set_available(&mut self, id: i64, available: bool) -> Result<&Food, AppError> {
+        let food = match self.inner.get_mut(&id) {
+            Some(food) => food,
+            None => return Err(AppError::NotFound(id)),
+        };
+        food.available = available;
+        food.updated_at = Some(Local::now());
+        Ok(food)
+    }
+
+    pub fn sell(&mut self, id: i64, quantity: Quantity, force: bool) -> Result<&Food, AppError> {
+        self.check_sellable(id, quantity, force)?;
+        let food = match self.inner.get_mut(&id) {
+            Some(food) => food,
+            None => return Err(AppError::NotFound(id)),
+        };
+        food.stock -= quantity;
+        food.sold_count += quantity;
+        Ok(food)
+    }
+
+    // Returns the stock before the delivery.
+    pub fn restock(&mut self, id: i64, quantity: Quantity) -> Result<(Quantity, &Food), AppError> {
+        if !quantity.is_positive() {
+            return Err(AppError::InvalidQuantity(quantity));
+        }
+        let food = match self.inner.get_mut(&id) {
+            Some(food) => food,
+            None => return Err(AppError::NotFound(id)),
+        };
+        food.check_divisible(quantity)?;
+        let before = food.stock;
+        food.stock += quantity;
+        food.updated_at = Some(Local::now());
+        Ok((before, food))
+    }
+
+    pub(crate) fn waste(&mut self, id: i64, quantity: Quantity) -> Result<&Food, AppError> {
+        self.check_stock(id, quantity, false)?;
+        let food = match self.inner.get_mut(&id) {
+            Some(food) => food,
+            None => return Err(AppError::NotFound(id)),
+        };
+        food.stock -= quantity;
+        Ok(food)
+    }
+
+    pub(crate) fn sell_many(&mut self, items: &[(i64, Quantity)], force: bool) -> Result<(), AppError> {
+        for (id, quantity) in items {
+            self.check_sellable(*id, *quantity, force)?;
+        }
+        for (id, quantity) in items {
+            self.sell(*id, *quantity, force)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn refund(&mut self, id: i64, quantity: Quantity) -> Result<&Food, AppError> {
+        let food = match self.inner.get_mut(&id) {
+            Some(food) => food,
+            None => return Err(AppError::NotFound(id)),
+        };
+        food.stock += quantity;
+        food.sold_count = (food.sold_count - quantity).max(Quantity::default());
+        Ok(food)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Sale {
+    pub(crate) id: i64,
+    pub(crate) food_id: i64,
+    pub(crate) quantity: Quantity,
+    pub(crate) unit_price: i64,
+    pub(crate) total: i64,
+    pub(crate) timestamp: DateTime<Local>,
+    pub(crate) refunded: bool,
+    pub(crate) net: i64,
+    pub(crate) tax: i64,
+}
+
+impl Sale {
+    pub(crate) fn new(id: i64, food: &Food, quantity: Quantity) -> Self {
+        let total = quantity.times(food.price.cents());
+        Self {
+            id,
+            food_id: food.id,
+            quantity,
+            unit_price: food.price.cents(),
+            total,
+            timestamp: Local::now(),
+            refunded: false,
+            net: total,
+            tax: 0,
+        }
+    }
+}
+
+pub(crate) fn split_amount(amount: i64, weights: &[i64]) -> Vec<i64> {
+    let total: i64 = weights.iter().sum();
+    if total <= 0 || amount <= 0 {
+        return vec![0; weights.len()];
+    }
+    let amount = amount.min(total);
+
+    let mut shares: Vec<i64> = weights
+        .iter()
+        .map(|weight| (amount as i128 * *weight as i128 / total as i128) as i64)
+        .collect();
+    let mut remaining = amount - shares.iter().sum::<i64>();
+    for (share, weight) in shares.iter_mut().zip(weights) {
+        if remaining == 0 {
+            break;
+        }
+        let extra = remaining.min(weight - *share);
+        *share += extra;
+        remaining -= extra;
+    }
+    shares
+}
+
+#[derive(Debug)]
+pub(crate) struct Sales {
+    pub(crate) inner: Vec<Sale>,
+}
+
+impl Sales {
+    pub(crate) fn new() -> Self {
+        Self { inner: Vec::new() }
+    }
+
+    pub(crate) fn next_id(&self) -> i64 {
+        match self.inner.iter().map(|sale| sale.id).max() {
+            Some(id) => id + 1,
+            None => 1,
+        }
+    }
+
+    pub(crate) fn add(&mut self, sale: Sale) {
+        self.inner.push(sale);
+    }
+
+    pub(crate) fn refund(&mut self, id: i64) -> Result<Vec<&Sale>, AppError> {
+        let mut lines: Vec<&mut Sale> = self.inner.iter_mut().filter(|sale| sale.id == id).collect();
+        if lines.is_empty() {
+            return Err(AppError::SaleNotFound(id));
+        }
+        if lines.iter().any(|sale| sale.refunded) {
+            return Err(AppError::AlreadyRefunded(id));
+        }
+        for sale in lines.iter_mut() {
+            sale.refunded = true;
+        }
+        Ok(lines.into_iter().map(|sale| &*sale).collect())
+    }
+
+    pub(crate) fn on_date(&self, date: NaiveDate) -> Vec<&Sale> {
+        self.inner
+            .iter()
+            .filter(|sale| !sale.refunded && sale.timestamp.date_naive() == date)
+            .collect()
+    }
+
+    pub(crate) fn refunded_on(&self, date: NaiveDate) -> Vec<&Sale> {
+        self.inner
+            .iter()
+            .filter(|sale| sale.refunded && sale.timestamp.date_naive() == date)
+            .collect()
+    }
+
+    pub(crate) fn between(&self, from: Option<NaiveDate>, to: Option<NaiveDate>) -> Vec<&Sale> {
+        self.inner
+            .iter()
+            .filter(|sale| !sale.refunded)
+            .filter(|sale| {
+                let date = sale.timestamp.date_naive();
+                from.is_none_or(|from| date >= from) && to.is_none_or(|to| date <= to)
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum OrderStatus {
+    Pending,
+    Completed,
+    Cancelled,
+}
+
+impl OrderStatus {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            OrderStatus::Pending => "pending",
+            OrderStatus::Completed => "completed",
+            OrderStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+impl std::str::FromStr for OrderStatus {
+    type Err = ParseError;
+
+    fn from_str(status: &str) -> Result<Self, Self::Err> {
+        match status {
+            "pending" => Ok(OrderStatus::Pending),
+            "completed" => Ok(OrderStatus::Completed),
+            "cancelled" => Ok(OrderStatus::Cancelled),
+            _ => Err(ParseError::InvalidStatus(status.to_owned())),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Order {
+    pub(crate) id: i64,
+    pub(crate) food_id: i64,
+    pub(crate) quantity: Quantity,
+    pub(crate) status: OrderStatus,
+    pub(crate) created_at: DateTime<Local>,
+}
+#[derive(Debug)]
+pub(crate) struct Orders {
+    pub(crate) inner: HashMap<i64, Order>,
+}
+
+impl Orders {
+    pub(crate) fn new() -> Self {
+        Self { inner: HashMap::new() }
+    }
+
+    pub(crate) fn next_id(&self) -> i64 {
+        match self.inner.keys().max() {
+            Some(id) => id + 1,
+            None => 1,
+        }
+    }
+
+    pub(crate) fn add(&mut self, order: Order) {
+        self.inner.insert(order.id, order);
+    }
+
+    pub(crate) fn with_status(&self, status: Option<OrderStatus>) -> Vec<&Order> {
+        let mut orders: Vec<&Order> = self
+            .inner
+            .values()
+            .filter(|order| status.is_none_or(|status| order.status == status))
+            .collect();
+        orders.sort_by_key(|order| order.id);
+        orders
+    }
+
+    pub(crate) fn pending_mut(&mut self, id: i64) -> Result<&mut Order, AppError> {
+        let order = match self.inner.get_mut(&id) {
+            Some(order) => order,
+            None => return Err(AppError::OrderNotFound(id)),
+        };
+        if order.status != OrderStatus::Pending {
+            return Err(AppError::OrderNotPending { id, status: order.status.as_str() });
+        }
+        Ok(order)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Waste {
+    pub(crate) id: i64,
+    pub(crate) food_id: i64,
+    pub(crate) quantity: Quantity,
+    pub(crate) unit_price: i64,
+    pub(crate) timestamp: DateTime<Local>,
+    pub(crate) reason: String,
+}
+#[derive(Debug)]
+pub(crate) struct Wastes {
+    pub(crate) inner: Vec<Waste>,
+}
+
+impl Wastes {
+    pub(crate) fn new() -> Self {
+        Self { inner: Vec::new() }
+    }
+
+    pub(crate) fn next_id(&self) -> i64 {
+        match self.inner.iter().map(|waste| waste.id).max() {
+            Some(id) => id + 1,
+            None => 1,
+        }
+    }
+
+    pub(crate) fn add(&mut self, waste: Waste) {
+        self.inner.push(waste);
+    }
+
+    pub(crate) fn between(&self, from: Option<NaiveDate>, to: Option<NaiveDate>) -> Vec<&Waste> {
+        self.inner
+            .iter()
+            .filter(|waste| {
+                let date = waste.timestamp.date_naive();
+                from.is_none_or(|from| date >= from) && to.is_none_or(|to| date <= to)
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct DailyTotal {
+    pub(crate) date: NaiveDate,
+    pub(crate) transactions: usize,
+    pub(crate) items: Quantity,
+    pub(crate) revenue: i64,
+    pub(crate) refunds: usize,
+    pub(crate) refunded: i64,
+}
+#[derive(Debug)]
+pub(crate) struct DailyTotals {
+    pub(crate) inner: BTreeMap<NaiveDate, DailyTotal>,
+}
+
+impl DailyTotals {
+    pub(crate) fn new() -> Self {
+        Self { inner: BTreeMap::new() }
+    }
+
+    pub(crate) fn add(&mut self, total: DailyTotal) {
+        self.inner.insert(total.date, total);
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct SalesSummary {
+    pub(crate) items: Quantity,
+    pub(crate) transactions: usize,
+    pub(crate) income: i64,
+    pub(crate) discounts: i64,
+    pub(crate) tax: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum GroupBy {
+    Day,
+    Week,
+    Month,
+}
+
+impl std::str::FromStr for GroupBy {
+    type Err = String;
+
+    fn from_str(group: &str) -> Result<Self, Self::Err> {
+        match group {
+            "day" => Ok(GroupBy::Day),
+            "week" => Ok(GroupBy::Week),
+            "month" => Ok(GroupBy::Month),
+            _ => Err(format!("unknown grouping: {}", group)),
+        }
+    }
+}
+
+impl GroupBy {
+    fn bucket_start(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            GroupBy::Day => date,
+            GroupBy::Week => date - chrono::Duration::days(date.weekday().num_days_from_monday() as i64),
+            GroupBy::Month => date.with_day(1).unwrap_or(date),
+        }
+    }
+
+    fn next_bucket(&self, start: NaiveDate) -> Option<NaiveDate> {
+        match self {
+            GroupBy::Day => start.succ_opt(),
+            GroupBy::Week => start.checked_add_signed(chrono::Duration::days(7)),
+            GroupBy::Month if start.month() == 12 => NaiveDate::from_ymd_opt(start.year() + 1, 1, 1),
+            GroupBy::Month => NaiveDate::from_ymd_opt(start.year(), start.month() + 1, 1),
+        }
+    }
+
+    pub(crate) fn label(&self, start: NaiveDate) -> String {
+        match self {
+            GroupBy::Day => start.to_string(),
+            GroupBy::Week => start.format("%G-W%V").to_string(),
+            GroupBy::Month => start.format("%Y-%m").to_string(),
+        }
+    }
+}
+
+pub(crate) fn group_sales<'a>(sales: &[&'a Sale], group: GroupBy) -> BTreeMap<NaiveDate, Vec<&'a Sale>> {
+    let mut buckets: BTreeMap<NaiveDate, Vec<&Sale>> = BTreeMap::new();
+    for sale in sales {
+        buckets
+            .entry(group.bucket_start(sale.timestamp.date_naive()))
+            .or_default()
+            .push(sale);
+    }
+    buckets
+}
+
+pub(crate) fn fill_buckets(
+    buckets: &mut BTreeMap<NaiveDate, Vec<&Sale>>,
+    group: GroupBy,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) {
+    let first = match from.map(|from| group.bucket_start(from)).or(buckets.keys().next().copied()) {
+        Some(first) => first,
+        None => return,
+    };
+    let last = match to.map(|to| group.bucket_start(to)).or(buckets.keys().last().copied()) {
+        Some(last) => last,
+        None => return,
+    };
+
+    let mut current = Some(first);
+    while let Some(start) = current.filter(|start| *start <= last) {
+        buckets.entry(start).or_default();
+        current = group.next_bucket(start);
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct TopSeller {
+    pub(crate) food_id: i64,
+    pub(crate) name: Option<String>,
+    pub(crate) quantity: Quantity,
+    pub(crate) revenue: i64,
+}
+
+pub(crate) fn top_sellers(sales: &[&Sale], foods: &Foods, limit: usize) -> Vec<TopSeller> {
+    let mut totals: HashMap<i64, (Quantity, i64)> = HashMap::new();
+    for sale in sales {
+        let entry = totals.entry(sale.food_id).or_insert((Quantity::default(), 0));
+        entry.0 += sale.quantity;
+        entry.1 += sale.net;
+    }
+
+    let mut sellers: Vec<TopSeller> = totals
+        .into_iter()
+        .map(|(food_id, (quantity, revenue))| TopSeller {
+            food_id,
+            name: foods.inner.get(&food_id).map(|food| food.name.clone()),
+            quantity,
+            revenue,
+        })
+        .collect();
+    sellers.sort_by(|a, b| {
+        b.quantity
+            .cmp(&a.quantity)
+            .then(b.revenue.cmp(&a.revenue))
+            .then(a.food_id.cmp(&b.food_id))
+    });
+    sellers.truncate(limit);
+    sellers
+}
+
+pub(crate) const DAY_PARTS: [(&str, u32, u32); 5] = [
+    ("Night", 0, 6),
+    ("Morning", 6, 11),
+    ("Lunch", 11, 15),
+    ("Afternoon", 15, 18),
+    ("Evening", 18, 24),
+];
+
+pub(crate) fn day_part(hour: u32) -> &'static str {
+    DAY_PARTS
+        .iter()
+        .find(|(_, start, end)| hour >= *start && hour < *end)
+        .map(|(name, _, _)| *name)
+        .unwrap_or("Night")
+}
+
+pub(crate) fn busiest_hour(sales: &[&Sale]) -> Option<(u32, usize)> {
+    let mut hours: BTreeMap<u32, HashSet<i64>> = BTreeMap::new();
+    for sale in sales {
+        hours.entry(sale.timestamp.hour()).or_default().insert(sale.id);
+    }
+    hours
+        .into_iter()
+        .map(|(hour, ids)| (hour, ids.len()))
+        .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)))
+}
+
+pub(crate) fn summarize(sales: &[&Sale]) -> SalesSummary {
+    let ids: HashSet<i64> = sales.iter().map(|sale| sale.id).collect();
+    SalesSummary {
+        items: sales.iter().map(|sale| sale.quantity).sum(),
+        transactions: ids.len(),
+        income: sales.iter().map(|sale| sale.net).sum(),
+        discounts: sales.iter().map(|sale| sale.total - sale.net).sum(),
+        tax: sales.iter().map(|sale| sale.tax).sum(),
+    }
+}
+
+// Names are stored trimmed with runs of whitespace collapsed to one space.
+pub(crate) fn normalize_name(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// Two names are the same item name when their keys are equal.
+pub(crate) fn name_key(name: &str) -> String {
+    normalize_name(name).to_lowercase()
+}
+
+pub(crate) fn parse_tags(tags: &str) -> BTreeSet<String> {
+    tags.split(';')
+        .map(|tag| tag.trim().to_lowercase())
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+pub(crate) fn join_tags(tags: &BTreeSet<String>) -> String {
+    tags.iter().map(String::as_str).collect::<Vec<_>>().join(";")
+}
+
+// What turning one menu into another does, one line per item. Dry runs,
+// restore and import --replace all show it the same way.
+#[derive(Debug, Default)]
+pub(crate) struct FoodChanges {
+    pub(crate) added: usize,
+    pub(crate) removed: usize,
+    changed: usize,
+    pub(crate) lines: Vec<String>,
+}
+
+impl FoodChanges {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+}
+
+impl std::fmt::Display for FoodChanges {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for line in &self.lines {
+            writeln!(f, "  {}", line)?;
+        }
+        write!(f, "{} added, {} removed, {} changed", self.added, self.removed, self.changed)
+    }
+}
+
+// Timestamps change on every edit, so they are left out of the field list.
+const UNDIFFED_FIELDS: [&str; 2] = ["created_at", "updated_at"];
+
+pub(crate) fn diff_foods(current: &Foods, other: &Foods) -> serde_json::Result<FoodChanges> {
+    let show = |value: &serde_json::Value| match value {
+        serde_json::Value::String(text) if text.is_empty() => "\"\"".to_owned(),
+        serde_json::Value::String(text) => text.clone(),
+        value => value.to_string(),
+    };
+    let mut changes = FoodChanges::default();
+    let mut ids: Vec<&i64> = current.inner.keys().chain(other.inner.keys()).collect();
+    ids.sort();
+    ids.dedup();
+    for id in ids {
+        match (current.inner.get(id), other.inner.get(id)) {
+            (None, Some(food)) => {
+                changes.added += 1;
+                changes.lines.push(format!("+ #{} {}: stock {}, price {}", food.id, food.name, food.stock, food.price));
+            }
+            (Some(food), None) => {
+                changes.removed += 1;
+                changes.lines.push(format!("- #{} {}", food.id, food.name));
+            }
+            (Some(before), Some(after)) => {
+                let (old, new) = (serde_json::to_value(before)?, serde_json::to_value(after)?);
+                let fields: Vec<String> = match (old.as_object(), new.as_object()) {
+                    (Some(old), Some(new)) => new
+                        .iter()
+                        .filter(|(field, value)| !UNDIFFED_FIELDS.contains(&field.as_str()) && old.get(*field) != Some(*value))
+                        .map(|(field, value)| {
+                            format!("{} {} -> {}", field, old.get(field).map_or("-".to_owned(), show), show(value))
+                        })
+                        .collect(),
+                    _ => Vec::new(),
+                };
+                if !fields.is_empty() {
+                    changes.changed += 1;
+                    changes.lines.push(format!("~ #{} {}: {}", after.id, before.name, fields.join(", ")));
+                }
+            }
+            (None, None) => {}
+        }
+    }
+    Ok(changes)
+}
+
+pub(crate) fn parse_date(date: &str) -> Result<NaiveDate, ParseError> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| ParseError::InvalidDate(date.to_owned()))
+}
+
+pub(crate) fn parse_range(
+    date: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<(Option<NaiveDate>, Option<NaiveDate>), ParseError> {
+    if let Some(date) = date {
+        let date = parse_date(&date)?;
+        return Ok((Some(date), Some(date)));
+    }
+    Ok((
+        from.map(|from| parse_date(&from)).transpose()?,
+        to.map(|to| parse_date(&to)).transpose()?,
+    ))
+}
+
+pub(crate) fn parse_price(price: &str) -> Result<i64, ParseError> {
+    let invalid = || ParseError::InvalidPrice(price.to_owned());
+    let trimmed = price.trim();
+    let (negative, digits) = match trimmed.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, trimmed),
+    };
+
+    let cents = if let Some(cents) = digits.strip_suffix('c') {
+        if cents.is_empty() || !cents.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        cents.parse::<i128>().map_err(|_| invalid())?
+    } else {
+        let (units, fraction) = match digits.split_once('.') {
+            Some((units, fraction)) => (units, fraction),
+            None => (digits, ""),
+        };
+        let all_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+        if (units.is_empty() && fraction.is_empty()) || !all_digits(units) || !all_digits(fraction) {
+            return Err(invalid());
+        }
+        if fraction.len() > 2 {
+            return Err(invalid());
+        }
+        let units = match units {
+            "" => 0,
+            units => units.parse::<i128>().map_err(|_| invalid())?,
+        };
+        let fraction = match fraction.len() {
+            0 => 0,
+            1 => fraction.parse::<i128>().map_err(|_| invalid())? * 10,
+            _ => fraction.parse::<i128>().map_err(|_| invalid())?,
+        };
+        units.checked_mul(100).and_then(|cents| cents.checked_add(fraction)).ok_or_else(invalid)?
+    };
+
+    signed(negative, cents).ok_or_else(|| ParseError::PriceOutOfRange(price.to_owned()))
+}
+
+// Quantities and prices are kept to 15 digits, as many as the CSV reader and
+// JSON carry through an f64 without changing any.
+pub(crate) const MAX_AMOUNT: i64 = 1_000_000_000_000_000;
+
+fn signed(negative: bool, magnitude: i128) -> Option<i64> {
+    let amount = i64::try_from(magnitude).ok().filter(|amount| *amount < MAX_AMOUNT)?;
+    Some(if negative { -amount } else { amount })
+}
+
+pub(crate) fn format_price(cents: i64) -> String {
+    let sign = if cents < 0 { "-" } else { "" };
+    let cents = cents.unsigned_abs();
+    format!("{}{}.{:02}", sign, cents / 100, cents % 100)
+}
+
+// The rate in hundredths of a percent. Above 1 it is a percentage and below
+// 0.5 a fraction; in between, 0.5 could be half a percent or half the price
+// and 1 could be one percent or all of it, so those are refused.
+pub(crate) fn normalize_tax_rate(rate: f64) -> Result<i64, ParseError> {
+    let percent = match rate {
+        rate if !rate.is_finite() || !(0.0..=100.0).contains(&rate) => {
+            return Err(ParseError::InvalidTaxRate(rate.to_string()))
+        }
+        rate if rate < 0.5 => rate * 100.0,
+        rate if rate <= 1.0 => return Err(ParseError::AmbiguousTaxRate(rate.to_string())),
+        rate => rate,
+    };
+    Ok((percent * 100.0).round() as i64)
+}
+
+const MAX_ID_RANGE: i64 = 10_000;
+
+pub(crate) fn parse_id_range(ids: &str) -> Result<std::ops::RangeInclusive<i64>, ParseError> {
+    let invalid = || ParseError::InvalidIdRange(ids.to_owned());
+    let (start, end) = match ids.split_once('-') {
+        Some((start, end)) => (start.trim().parse::<i64>()?, end.trim().parse::<i64>()?),
+        None => {
+            let id = ids.trim().parse::<i64>()?;
+            (id, id)
+        }
+    };
+    if start > end || end - start >= MAX_ID_RANGE {
+        return Err(invalid());
+    }
+    Ok(start..=end)
+}
+
+// Collapses runs of consecutive ids, e.g. [3, 4, 5, 9] becomes "3-5, 9".
+pub(crate) fn format_ids(ids: &[i64]) -> String {
+    let mut ids = ids.to_vec();
+    ids.sort();
+    let mut runs: Vec<(i64, i64)> = Vec::new();
+    for id in ids {
+        match runs.last_mut() {
+            Some((_, end)) if *end + 1 == id => *end = id,
+            _ => runs.push((id, id)),
+        }
+    }
+    runs.iter()
+        .map(|&(start, end)| if start == end { start.to_string() } else { format!("{}-{}", start, end) })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+pub(crate) fn parse_tax_rate(rate: &str) -> Result<i64, ParseError> {
+    match rate.trim().parse::<f64>() {
+        Ok(value) => normalize_tax_rate(value).map_err(|e| match e {
+            ParseError::AmbiguousTaxRate(_) => ParseError::AmbiguousTaxRate(rate.to_owned()),
+            _ => ParseError::InvalidTaxRate(rate.to_owned()),
+        }),
+        Err(_) => Err(ParseError::InvalidTaxRate(rate.to_owned())),
+    }
+}
+
+// How a search query is compared with a name. Unless case_sensitive, both
+// sides are lowercased first; a regex is compiled case-insensitive instead.
+#[derive(Debug, Clone)]
+pub(crate) enum SearchMode {
+    Substring { query: String, case_sensitive: bool },
+    Exact { query: String, case_sensitive: bool },
+    Prefix { query: String, case_sensitive: bool },
+    Regex(regex::Regex),
+    // Always ignores case.
+    Fuzzy(String),
+}
+
+// The most items search --fuzzy lists.
+pub(crate) const FUZZY_LIMIT: usize = 10;
+
+impl SearchMode {
+    pub(crate) fn new(
+        query: &str,
+        exact: bool,
+        prefix: bool,
+        regex: bool,
+        fuzzy: bool,
+        case_sensitive: bool,
+    ) -> Result<SearchMode, AppError> {
+        if fuzzy {
+            return Ok(SearchMode::Fuzzy(query.to_owned()));
+        }
+        if regex {
+            return regex::RegexBuilder::new(query)
+                .case_insensitive(!case_sensitive)
+                .build()
+                .map(SearchMode::Regex)
+                .map_err(|e| AppError::InvalidPattern(e.to_string()));
+        }
+        let query = match case_sensitive {
+            true => query.to_owned(),
+            false => query.to_lowercase(),
+        };
+        Ok(match (exact, prefix) {
+            (true, _) => SearchMode::Exact { query, case_sensitive },
+            (_, true) => SearchMode::Prefix { query, case_sensitive },
+            _ => SearchMode::Substring { query, case_sensitive },
+        })
+    }
+
+    pub(crate) fn substring(query: &str) -> SearchMode {
+        SearchMode::Substring { query: query.to_lowercase(), case_sensitive: false }
+    }
+
+    pub(crate) fn matches(&self, food: &Food) -> bool {
+        self.matches_text(&food.name)
+    }
+
+    fn matches_text(&self, text: &str) -> bool {
+        let fold = |case_sensitive: bool| match case_sensitive {
+            true => std::borrow::Cow::Borrowed(text),
+            false => std::borrow::Cow::Owned(text.to_lowercase()),
+        };
+        match self {
+            SearchMode::Substring { query, case_sensitive } => fold(*case_sensitive).contains(query.as_str()),
+            SearchMode::Exact { query, case_sensitive } => fold(*case_sensitive) == query.as_str(),
+            SearchMode::Prefix { query, case_sensitive } => fold(*case_sensitive).starts_with(query.as_str()),
+            SearchMode::Regex(regex) => regex.is_match(text),
+            SearchMode::Fuzzy(query) => fuzzy::score(query, text) >= fuzzy::THRESHOLD,
+        }
+    }
+
+    // Lower is a better match: the whole name, its start, anywhere in it, then
+    // only in another field.
+    pub(crate) fn rank(&self, name: &str) -> u8 {
+        let (query, case_sensitive) = match self {
+            SearchMode::Substring { query, case_sensitive }
+            | SearchMode::Exact { query, case_sensitive }
+            | SearchMode::Prefix { query, case_sensitive } => (query, *case_sensitive),
+            SearchMode::Regex(_) | SearchMode::Fuzzy(_) => return if self.matches_text(name) { 2 } else { 3 },
+        };
+        let name = match case_sensitive {
+            true => name.to_owned(),
+            false => name.to_lowercase(),
+        };
+        match () {
+            _ if name == *query => 0,
+            _ if name.starts_with(query.as_str()) => 1,
+            _ if name.contains(query.as_str()) => 2,
+            _ => 3,
+        }
+    }
+
+    // The text to mark in table output, when a literal one is known.
+    pub(crate) fn highlight(&self) -> Option<&str> {
+        match self {
+            SearchMode::Substring { query, .. } | SearchMode::Exact { query, .. } | SearchMode::Prefix { query, .. } => {
+                Some(query)
+            }
+            SearchMode::Regex(_) | SearchMode::Fuzzy(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SearchField {
+    Name,
+    Category,
+    Notes,
+}
+
+impl std::str::FromStr for SearchField {
+    type Err = String;
+
+    fn from_str(field: &str) -> Result<Self, Self::Err> {
+        match field.trim() {
+            "name" => Ok(SearchField::Name),
+            "category" => Ok(SearchField::Category),
+            "notes" => Ok(SearchField::Notes),
+            _ => Err(format!("unknown field: {} (expected name, category or notes)", field)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn foods(items: &[(i64, &str)]) -> Foods {
+        let mut foods = Foods::new();
+        for (id, name) in items {
+            foods.insert(Food::new(*id, name, Quantity(QUANTITY_SCALE), Money(100)));
+        }
+        foods
+    }
+
+    fn ids(found: Vec<&Food>) -> Vec<i64> {
+        found.into_iter().map(|food| food.id).collect()
+    }
+
+    #[test]
+    fn next_id_of_nothing_is_one() {
+        assert_eq!(Foods::new().next_id(), 1);
+    }
+
+    #[test]
+    fn next_id_follows_the_highest_id() {
+        assert_eq!(foods(&[(1, "bakso"), (2, "sate")]).next_id(), 3);
+        // Gaps below the highest id are left alone, only the highest id counts.
+        assert_eq!(foods(&[(7, "bakso"), (2, "sate"), (40, "risoles")]).next_id(), 41);
+    }
+
+    #[test]
+    fn next_id_after_removing_the_highest() {
+        let mut fds = foods(&[(1, "bakso"), (5, "sate")]);
+        // Once the highest id is gone, numbering carries on from the highest one left.
+        fds.remove(5);
+        assert_eq!(fds.next_id(), 2);
+    }
+
+    #[test]
+    fn search_ignores_case() {
+        let fds = foods(&[(1, "Nasi Goreng"), (2, "mie goreng"), (3, "Sate Ayam")]);
+        assert_eq!(ids(fds.search("GORENG")), [1, 2]);
+        assert_eq!(ids(fds.search("sate")), [3]);
+        assert_eq!(ids(fds.search("nAsI")), [1]);
+    }
+
+    #[test]
+    fn search_puts_better_matches_first() {
+        let fds = foods(&[(1, "es teh manis"), (2, "teh"), (3, "teh tarik")]);
+        assert_eq!(ids(fds.search("teh")), [2, 3, 1]);
+    }
+
+    #[test]
+    fn search_without_a_match() {
+        let fds = foods(&[(1, "bakso")]);
+        assert!(fds.search("soto").is_empty());
+    }
+
+    #[test]
+    fn allergens_are_a_set_of_trimmed_lowercase_names() {
+        let allergens = parse_tags(" Nuts ;dairy;NUTS;; \tDairy\t; gluten");
+        assert_eq!(allergens.iter().map(String::as_str).collect::<Vec<_>>(), ["dairy", "gluten", "nuts"]);
+        assert_eq!(join_tags(&allergens), "dairy;gluten;nuts");
+        assert!(parse_tags(" ; ;").is_empty());
+
+        let mut fds = foods(&[(1, "bakso"), (2, "es teh")]);
+        fds.inner.get_mut(&1).unwrap().allergens = allergens;
+        assert_eq!(ids(fds.with_allergen(" NUTS ")), [1]);
+        assert!(fds.with_allergen("egg").is_empty());
+    }
+
+    #[test]
+    fn stats_add_up_the_menu() {
+        let mut fds = Foods::new();
+        fds.insert(Food::new(1, "bakso", "54".parse().unwrap(), Money(1_800_000)));
+        fds.insert(Food::new(2, "sate", "0".parse().unwrap(), Money(2_200_000)));
+        fds.insert(Food::new(3, "beras", "2.5".parse().unwrap(), Money(1_000_050)));
+        let stats = fds.stats();
+
+        assert_eq!(stats.items, 3);
+        assert_eq!(stats.units, "56.5".parse().unwrap());
+        // 54 x 18000.00 and 2.5 x 10000.50, nothing for the sold out sate.
+        assert_eq!(stats.value, Money(97_200_000 + 2_500_125));
+        assert_eq!(stats.average_price, Money(5_000_050 / 3));
+        assert_eq!(stats.cheapest.map(|item| item.id), Some(3));
+        assert_eq!(stats.most_expensive.map(|item| item.id), Some(2));
+        assert_eq!(stats.out_of_stock, 1);
+    }
+
+    #[test]
+    fn stats_of_an_empty_menu() {
+        let stats = Foods::new().stats();
+        assert_eq!((stats.items, stats.units, stats.value, stats.average_price), (0, Quantity(0), Money(0), Money(0)));
+        assert!(stats.cheapest.is_none() && stats.most_expensive.is_none());
+        assert_eq!(stats.out_of_stock, 0);
+    }
+
+    fn matching(fds: &Foods, mode: SearchMode) -> Vec<i64> {
+        ids(fds.filter(|food| mode.matches(food)))
+    }
+
+    const GORENG: [(i64, &str); 3] = [(1, "Nasi Goreng"), (2, "mie goreng"), (3, "Sate Ayam")];
+
+    #[test]
+    fn substring_search_folds_case_unless_asked_not_to() {
+        let fds = foods(&GORENG);
+        let mode = |query, case_sensitive| SearchMode::new(query, false, false, false, false, case_sensitive).unwrap();
+        assert_eq!(matching(&fds, mode("GORENG", false)), [1, 2]);
+        assert_eq!(matching(&fds, mode("Goreng", true)), [1]);
+        assert_eq!(matching(&fds, mode("goreng", true)), [2]);
+        assert_eq!(matching(&fds, SearchMode::substring("aYaM")), [3]);
+    }
+
+    #[test]
+    fn exact_and_prefix_search_compare_the_whole_name_or_its_start() {
+        let fds = foods(&GORENG);
+        let exact = |query| SearchMode::new(query, true, false, false, false, false).unwrap();
+        let prefix = |query| SearchMode::new(query, false, true, false, false, false).unwrap();
+        assert_eq!(matching(&fds, exact("sate ayam")), [3]);
+        assert!(matching(&fds, exact("sate")).is_empty());
+        assert_eq!(matching(&fds, prefix("SATE")), [3]);
+        assert!(matching(&fds, prefix("goreng")).is_empty());
+    }
+
+    #[test]
+    fn regex_search_ignores_case_unless_asked_not_to() {
+        let fds = foods(&GORENG);
+        let regex = |query, case_sensitive| SearchMode::new(query, false, false, true, false, case_sensitive);
+        assert_eq!(matching(&fds, regex("^(nasi|mie) goreng$", false).unwrap()), [1, 2]);
+        assert_eq!(matching(&fds, regex("^[a-z]", true).unwrap()), [2]);
+        assert!(matches!(regex("(goreng", false), Err(AppError::InvalidPattern(_))));
+    }
+
+    #[test]
+    fn fuzzy_search_finds_misspelt_names_in_any_case() {
+        let fds = foods(&GORENG);
+        let fuzzy = |query| SearchMode::new(query, false, false, false, true, true).unwrap();
+        assert_eq!(matching(&fds, fuzzy("gorng")), [1, 2]);
+        assert_eq!(matching(&fds, fuzzy("STAE AYAM")), [3]);
+        assert!(matching(&fds, fuzzy("bakso")).is_empty());
+    }
+
+    fn week_of(year: i32, month: u32, day: u32) -> String {
+        let group = GroupBy::Week;
+        group.label(group.bucket_start(NaiveDate::from_ymd_opt(year, month, day).unwrap()))
+    }
+
+    #[test]
+    fn weeks_are_iso_weeks_across_the_new_year() {
+        assert_eq!(week_of(2020, 12, 31), "2020-W53");
+        assert_eq!(week_of(2021, 1, 3), "2020-W53");
+        assert_eq!(week_of(2021, 1, 4), "2021-W01");
+        assert_eq!(week_of(2024, 12, 29), "2024-W52");
+        assert_eq!(week_of(2024, 12, 30), "2025-W01");
+        assert_eq!(week_of(2025, 1, 1), "2025-W01");
+    }
+
+    #[test]
+    fn fill_buckets_adds_every_week_across_the_new_year() {
+        let date = |year, month, day| NaiveDate::from_ymd_opt(year, month, day);
+        let mut buckets = BTreeMap::new();
+        fill_buckets(&mut buckets, GroupBy::Week, date(2020, 12, 24), date(2021, 1, 12));
+        let labels: Vec<String> = buckets.keys().map(|start| GroupBy::Week.label(*start)).collect();
+        assert_eq!(labels, ["2020-W52", "2020-W53", "2021-W01", "2021-W02"]);
+
+        let mut buckets = BTreeMap::new();
+        fill_buckets(&mut buckets, GroupBy::Week, date(2024, 12, 25), date(2025, 1, 6));
+        let labels: Vec<String> = buckets.keys().map(|start| GroupBy::Week.label(*start)).collect();
+        assert_eq!(labels, ["2024-W52", "2025-W01", "2025-W02"]);
+    }
+
+    #[test]
+    fn split_amount_follows_the_weights() {
+        assert_eq!(split_amount(300, &[1000, 2000]), [100, 200]);
+        assert_eq!(split_amount(0, &[1000, 2000]), [0, 0]);
+        assert_eq!(split_amount(300, &[]), Vec::<i64>::new());
+    }
+
+    #[test]
+    fn split_amount_gives_the_remainder_to_the_first_lines() {
+        // A third of a cent each: the spare cent goes on the first line, every time.
+        assert_eq!(split_amount(100, &[333, 333, 334]), [34, 33, 33]);
+        assert_eq!(split_amount(2, &[333, 333, 334]), [2, 0, 0]);
+        // A line is never given more than its own weight.
+        assert_eq!(split_amount(3, &[1, 1000, 1000]), [1, 1, 1]);
+        let shares = split_amount(1_999, &[1_234, 5_677, 3]);
+        assert_eq!(shares.iter().sum::<i64>(), 1_999);
+    }
+
+    #[test]
+    fn split_amount_caps_at_the_total() {
+        assert_eq!(split_amount(5_000, &[1_000, 999]), [1_000, 999]);
+    }
+
+    #[test]
+    fn sort_foods_breaks_ties_by_id() {
+        let items = [
+            Food::new(4, "Sate", Quantity(QUANTITY_SCALE), Money(2000)),
+            Food::new(2, "sate", Quantity(QUANTITY_SCALE), Money(1000)),
+            Food::new(3, "bakso", Quantity(2 * QUANTITY_SCALE), Money(2000)),
+            Food::new(1, "bakso", Quantity(QUANTITY_SCALE), Money(1000)),
+        ];
+        let sorted = |key, order| {
+            let mut foods: Vec<&Food> = items.iter().collect();
+            sort_foods(&mut foods, key, order);
+            ids(foods)
+        };
+        assert_eq!(sorted(SortKey::Name, SortOrder::Asc), [1, 3, 2, 4]);
+        assert_eq!(sorted(SortKey::Price, SortOrder::Asc), [1, 2, 3, 4]);
+        // Reversing the order leaves equal items in id order.
+        assert_eq!(sorted(SortKey::Price, SortOrder::Desc), [3, 4, 1, 2]);
+        assert_eq!(sorted(SortKey::Stock, SortOrder::Desc), [3, 1, 2, 4]);
+        assert_eq!(sorted(SortKey::Margin, SortOrder::Asc), [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn tax_rates_as_percentages_or_fractions() {
+        for (rate, hundredths) in [("10", 1000), ("10.0", 1000), ("0.10", 1000), ("0.1", 1000), ("12.5", 1250)] {
+            assert_eq!(parse_tax_rate(rate).unwrap(), hundredths, "{}", rate);
+        }
+        assert_eq!(parse_tax_rate("0").unwrap(), 0);
+        assert_eq!(parse_tax_rate("0.01").unwrap(), 100);
+        assert_eq!(parse_tax_rate("0.005").unwrap(), 50);
+        assert_eq!(parse_tax_rate("1.5").unwrap(), 150);
+        assert_eq!(parse_tax_rate("100").unwrap(), 10_000);
+        assert_eq!(normalize_tax_rate(0.11).unwrap(), 1100);
+    }
+
+    #[test]
+    fn ambiguous_tax_rates_are_refused() {
+        for rate in ["0.5", "0.75", "1", "1.0"] {
+            assert!(matches!(parse_tax_rate(rate), Err(ParseError::AmbiguousTaxRate(r)) if r == rate), "{}", rate);
+        }
+        assert!(matches!(normalize_tax_rate(1.0), Err(ParseError::AmbiguousTaxRate(_))));
+    }
+
+    #[test]
+    fn bad_tax_rates_are_refused() {
+        for rate in ["-1", "100.5", "ten", "", "NaN", "inf"] {
+            assert!(matches!(parse_tax_rate(rate), Err(ParseError::InvalidTaxRate(_))), "{}", rate);
+        }
+    }
+
+    #[test]
+    fn prices_in_units_decimals_or_cents() {
+        assert_eq!(parse_price("4").unwrap(), 400);
+        assert_eq!(parse_price("4.5").unwrap(), 450);
+        assert_eq!(parse_price("4.50").unwrap(), 450);
+        assert_eq!(parse_price("450c").unwrap(), 450);
+        assert_eq!(parse_price(".05").unwrap(), 5);
+        assert_eq!(parse_price("-0.01").unwrap(), -1);
+        assert_eq!(format_price(parse_price("4.5").unwrap()), "4.50");
+    }
+
+    #[test]
+    fn half_cents_are_refused_rather_than_rounded() {
+        // Rounding 4.505 either way would change the price without a word.
+        for price in ["4.505", "0.005", "-4.995"] {
+            assert!(matches!(parse_price(price), Err(ParseError::InvalidPrice(p)) if p == price), "{}", price);
+        }
+    }
+
+    #[test]
+    fn more_than_two_decimals_are_refused() {
+        for price in ["4.500", "4.5001", "1.234", "0.000"] {
+            assert!(matches!(parse_price(price), Err(ParseError::InvalidPrice(_))), "{}", price);
+        }
+        for price in ["", ".", "4.5.0", "4,50", "4.5c", "c", "1e3"] {
+            assert!(parse_price(price).is_err(), "{}", price);
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use std::io::IsTerminal;
+
+use chrono::{Local, NaiveDate};
+use serde::{Deserialize, Deserializer};
+
+use crate::color::Color;
+use crate::error::AppError;
+use crate::model::{Food, Money, Quantity};
+use crate::storage::{foods_to_json, foods_to_jsonl, write_foods_csv, CsvOptions};
+use crate::table::{Align, Table};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Format {
+    Table,
+    Csv,
+    Json,
+    Jsonl,
+    Debug,
+}
+
+impl Format {
+    // csv, json and jsonl are meant for other programs, so notes and footers
+    // go to stderr and empty results are printed as empty data.
+    pub(crate) fn is_data(self) -> bool {
+        matches!(self, Format::Csv | Format::Json | Format::Jsonl)
+    }
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Format::Table => "table",
+            Format::Csv => "csv",
+            Format::Json => "json",
+            Format::Jsonl => "jsonl",
+            Format::Debug => "debug",
+        }
+    }
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "table" => Ok(Format::Table),
+            "csv" => Ok(Format::Csv),
+            "json" => Ok(Format::Json),
+            "jsonl" => Ok(Format::Jsonl),
+            "debug" => Ok(Format::Debug),
+            _ => Err(format!("unknown format: {}", format)),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Format {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+pub(crate) fn render(foods: &[&Food], format: Format, margin: bool) -> Result<String, AppError> {
+    render_matches(foods, format, margin, None)
+}
+
+// Like render, but tables mark where query matched in each name.
+pub(crate) fn render_matches(
+    foods: &[&Food],
+    format: Format,
+    margin: bool,
+    query: Option<&str>,
+) -> Result<String, AppError> {
+    let today = Local::now().date_naive();
+    match format {
+        Format::Table => {
+            let mut table = food_table(margin);
+            if let Some(query) = query {
+                table = table.highlight(1, query);
+            }
+            for food in foods {
+                table.push_colored(food_row(food, margin, today), stock_color(food));
+            }
+            Ok(table.render(table_width()))
+        }
+        Format::Csv => {
+            let mut out = Vec::new();
+            write_foods_csv(foods, &mut out, &CsvOptions::default())?;
+            Ok(String::from_utf8_lossy(&out).into_owned())
+        }
+        Format::Json => Ok(foods_to_json(foods.iter().copied())?),
+        Format::Jsonl => Ok(foods_to_jsonl(foods.iter().copied())?),
+        Format::Debug => Ok(foods.iter().map(|food| debug_line(food, margin, today) + "\n").collect()),
+    }
+}
+
+pub(crate) fn debug_line(food: &Food, margin: bool, today: NaiveDate) -> String {
+    let marker = match (food.available, food.is_special(today)) {
+        (false, _) => "[86] ",
+        (true, true) => "[special] ",
+        (true, false) => "",
+    };
+    if !margin {
+        return format!("{}{:?}", marker, food);
+    }
+    match (food.margin(), food.margin_percent()) {
+        (Some(amount), Some(percent)) => format!("{}{:?} margin: {} ({:.1}%)", marker, food, Money(amount), percent),
+        (Some(amount), None) => format!("{}{:?} margin: {}", marker, food, Money(amount)),
+        _ => format!("{}{:?} margin: ?", marker, food),
+    }
+}
+
+// Out of stock is red, at or below the minimum is yellow.
+pub(crate) fn stock_color(food: &Food) -> Option<Color> {
+    if food.stock <= Quantity::default() {
+        Some(Color::Red)
+    } else if food.stock <= food.min_stock {
+        Some(Color::Yellow)
+    } else {
+        None
+    }
+}
+
+fn food_table(margin: bool) -> Table {
+    let mut columns = vec![
+        ("Id", Align::Right),
+        ("Name", Align::Left),
+        ("Category", Align::Left),
+        ("Stock", Align::Right),
+        ("Price", Align::Right),
+    ];
+    if margin {
+        columns.push(("Margin", Align::Right));
+    }
+    columns.push(("Status", Align::Left));
+    Table::new(&columns).shrink(1)
+}
+
+fn food_row(food: &Food, margin: bool, today: NaiveDate) -> Vec<String> {
+    let mut row = vec![
+        food.id.to_string(),
+        food.name.clone(),
+        food.category.clone(),
+        format!("{} {}", food.stock, food.unit),
+        food.price.to_string(),
+    ];
+    if margin {
+        row.push(match (food.margin(), food.margin_percent()) {
+            (Some(amount), Some(percent)) => format!("{} ({:.1}%)", Money(amount), percent),
+            (Some(amount), None) => Money(amount).to_string(),
+            _ => "?".to_owned(),
+        });
+    }
+    row.push(match (food.available, food.is_special(today)) {
+        (false, _) => "86".to_owned(),
+        (true, true) => "special".to_owned(),
+        (true, false) => String::new(),
+    });
+    row
+}
+
+pub(crate) fn terminal_width() -> usize {
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse::<usize>().ok())
+        .filter(|columns| *columns > 0)
+        .or_else(|| terminal_size::terminal_size().map(|(terminal_size::Width(width), _)| width as usize))
+        .unwrap_or(80)
+}
+
+// Tables are only cut to fit when a person is looking at them.
+fn table_width() -> Option<usize> {
+    std::io::stdout().is_terminal().then(terminal_width)
+}
@@ -0,0 +1,100 @@
+use std::io::{BufRead, IsTerminal, Write};
+
+use structopt::StructOpt;
+
+use crate::cli::{execute, Command, Context};
+use crate::error::AppError;
+use crate::storage::{MemoryStore, Storage};
+
+pub(crate) fn run(context: &Context, store: &mut dyn Storage, save_on_quit: bool) -> Result<(), AppError> {
+    let mut memory = MemoryStore::new(store)?;
+    let interactive = std::io::stdin().is_terminal();
+    if interactive {
+        println!("Type a command such as list or sell 3 2, help for all of them, quit to leave");
+    }
+    let mut line = String::new();
+    loop {
+        if interactive {
+            print!("> ");
+            std::io::stdout().flush()?;
+        }
+        line.clear();
+        if std::io::stdin().lock().read_line(&mut line)? == 0 {
+            break;
+        }
+        let words = match split_words(&line) {
+            Some(words) => words,
+            None => {
+                eprintln!("unterminated quote");
+                continue;
+            }
+        };
+        match words.first().map(String::as_str) {
+            None => continue,
+            Some("quit" | "exit") => break,
+            Some("save") => {
+                memory.flush()?;
+                println!("saved");
+                continue;
+            }
+            _ => {}
+        }
+        let cmd = match Command::from_iter_safe(std::iter::once(String::new()).chain(words)) {
+            Ok(Command::Shell { .. }) => {
+                println!("already in the shell");
+                continue;
+            }
+            Ok(cmd) => cmd,
+            Err(e) if !e.use_stderr() => {
+                println!("{}", e.message);
+                continue;
+            }
+            Err(e) => {
+                eprintln!("{}", e.message);
+                continue;
+            }
+        };
+
+        let result = match cmd.uses_data_file() {
+            true => memory.flush().and_then(|_| execute(cmd, context, &mut memory)).and_then(|_| memory.reload()),
+            false => execute(cmd, context, &mut memory),
+        };
+        if let Err(e) = result {
+            eprintln!("An error occurred: {}", e);
+        }
+        if !save_on_quit {
+            memory.flush()?;
+        }
+    }
+    if interactive && memory.is_dirty() {
+        println!("saving changes");
+    }
+    memory.flush()
+}
+
+// Splits like a shell would for the simple cases: whitespace separates words,
+// single and double quotes group them and a backslash escapes one character.
+fn split_words(line: &str) -> Option<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quote = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(open), c) if c == open => quote = None,
+            (Some('"') | None, '\\') => word.get_or_insert_with(String::new).extend(chars.next()),
+            (Some(_), c) => word.get_or_insert_with(String::new).push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                word.get_or_insert_with(String::new);
+            }
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (None, c) => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if quote.is_some() {
+        return None;
+    }
+    words.extend(word);
+    Some(words)
+}
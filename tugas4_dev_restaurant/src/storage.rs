@@ -0,0 +1,2466 @@
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use chrono::prelude::*;
+use serde::Serialize;
+
+use crate::error::{AppError, ParseError, ParseWarning};
+use crate::model::{
+    format_price, parse_date, parse_price, DailyTotal, DailyTotals, Food, FoodIndex, Foods, JsonFood, Order, OrderStatus, Orders,
+    Quantity, Sale, Sales, Waste, Wastes, FOOD_HEADER,
+};
+#[cfg(feature = "encryption")]
+use crate::crypto;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Backend {
+    Csv,
+    Sqlite,
+}
+
+impl Backend {
+    pub(crate) fn detect(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("db" | "sqlite" | "sqlite3") => Backend::Sqlite,
+            _ => Backend::Csv,
+        }
+    }
+}
+
+impl std::str::FromStr for Backend {
+    type Err = String;
+
+    fn from_str(backend: &str) -> Result<Self, Self::Err> {
+        match backend {
+            "csv" => Ok(Backend::Csv),
+            "sqlite" => Ok(Backend::Sqlite),
+            _ => Err(format!("unknown backend: {}", backend)),
+        }
+    }
+}
+
+pub(crate) trait Storage {
+    fn load_foods(&self) -> Result<Foods, AppError>;
+    fn save_foods(&mut self, foods: Foods) -> Result<(), AppError>;
+    fn save_food(&mut self, food: &Food) -> Result<(), AppError>;
+    // Removes every id that exists and returns them with the ids that did not.
+    // Unless missing_ok, a missing id fails with nothing removed.
+    fn remove_foods(&mut self, ids: &[i64], missing_ok: bool) -> Result<(Vec<Food>, Vec<i64>), AppError>;
+
+    fn index(&self) -> Result<FoodIndex, AppError> {
+        Ok(self.load_foods()?.index())
+    }
+
+    fn stream_foods(&self, visit: &mut dyn FnMut(Food) -> Result<(), AppError>) -> Result<(), AppError> {
+        for food in self.load_foods()?.into_vec() {
+            visit(food)?;
+        }
+        Ok(())
+    }
+
+    fn add_food(&mut self, food: Food) -> Result<(), AppError> {
+        let mut fds = self.load_foods()?;
+        fds.add(food);
+        self.save_foods(fds)
+    }
+}
+
+pub(crate) fn open(
+    path: &Path,
+    backend: Option<Backend>,
+    opts: CsvOptions,
+    create: bool,
+) -> Result<Box<dyn Storage>, AppError> {
+    match backend.unwrap_or_else(|| Backend::detect(path)) {
+        Backend::Csv => Ok(Box::new(CsvStore { path: path.to_path_buf(), opts, create, verified: Cell::new(false) })),
+        #[cfg(feature = "sqlite")]
+        Backend::Sqlite => Ok(Box::new(sqlite::SqliteStore::open(path)?)),
+        #[cfg(not(feature = "sqlite"))]
+        Backend::Sqlite => Err(AppError::NotCompiledIn("sqlite")),
+    }
+}
+
+struct CsvStore {
+    path: PathBuf,
+    opts: CsvOptions,
+    create: bool,
+    verified: Cell<bool>,
+}
+
+type FileReader = csv::Reader<TrimUnquoted<BufReader<File>>>;
+
+impl CsvStore {
+    fn reader(&self, file: File) -> Result<(u32, FileReader), AppError> {
+        let mut file = BufReader::new(file);
+        let (schema, _) = read_preamble(&mut file)?;
+        let reader = csv::ReaderBuilder::new()
+            .flexible(true)
+            .delimiter(self.opts.delimiter)
+            .from_reader(TrimUnquoted::new(file, self.opts.delimiter));
+        Ok((schema, reader))
+    }
+
+    fn check_exists(&self) -> Result<bool, AppError> {
+        match self.path.exists() || is_stdio(&self.path) {
+            true => Ok(true),
+            false if self.create => Ok(false),
+            false => Err(AppError::NoDataFile(self.path.clone())),
+        }
+    }
+
+    fn verify(&self) -> Result<(), AppError> {
+        if self.verified.replace(true) || is_stdio(&self.path) || !self.path.exists() {
+            return Ok(());
+        }
+        match crate::storage::check_checksum(&self.path)? {
+            Some(false) if self.opts.accept_changes => crate::storage::write_checksum(&self.path)?,
+            Some(false) if self.opts.strict => return Err(ParseError::ExternalChange(self.path.clone()).into()),
+            Some(false) => self.opts.report(&[ParseError::ExternalChange(self.path.clone()).into()]),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn is_appendable(&self) -> Result<bool, AppError> {
+        if self.opts.delimiter != self.opts.save_delimiter
+            || is_gzip(&self.path)
+            || self.opts.encrypt
+            || !self.path.exists()
+        {
+            return Ok(false);
+        }
+        let mut file = File::open(&self.path)?;
+        let mut last = [0u8];
+        if file.seek(SeekFrom::End(-1)).is_err() || file.read_exact(&mut last).is_err() || last[0] != b'\n' {
+            return Ok(false);
+        }
+        file.rewind()?;
+        let (schema, mut reader) = self.reader(file)?;
+        Ok(schema == SCHEMA_VERSION && reader.headers().is_ok_and(|headers| headers.iter().eq(FOOD_HEADER)))
+    }
+
+    fn scan(&self) -> Result<FoodIndex, AppError> {
+        let (_, mut reader) = self.reader(File::open(&self.path)?)?;
+        let headers = reader.byte_headers()?.clone();
+        let column = |name: &str| headers.iter().position(|header| header == name.as_bytes());
+        let (id_column, sku_column, name_column) = (column("id"), column("sku"), column("name"));
+
+        let mut index = FoodIndex { next_id: 1, ..FoodIndex::default() };
+        let mut record = csv::ByteRecord::new();
+        fn field(record: &csv::ByteRecord, column: Option<usize>) -> Option<std::borrow::Cow<'_, str>> {
+            column.and_then(|column| record.get(column)).map(String::from_utf8_lossy)
+        }
+        while reader.read_byte_record(&mut record)? {
+            let id = match field(&record, id_column).map(|id| id.parse::<i64>()) {
+                Some(Ok(id)) => id,
+                _ => continue,
+            };
+            index.next_id = index.next_id.max(id + 1);
+            if let Some(sku) = field(&record, sku_column).filter(|sku| !sku.is_empty()) {
+                index.skus.insert(sku.into_owned(), id);
+            }
+            if let Some(name) = field(&record, name_column) {
+                let name = crate::model::normalize_name(&name);
+                let first = index.names.entry(crate::model::name_key(&name)).or_insert((id, name.clone()));
+                if id < first.0 {
+                    *first = (id, name);
+                }
+            }
+        }
+        Ok(index)
+    }
+}
+
+impl Storage for CsvStore {
+    fn load_foods(&self) -> Result<Foods, AppError> {
+        if !self.check_exists()? {
+            return Ok(Foods::new());
+        }
+        self.verify()?;
+        let (foods, warnings) = crate::storage::load_foods(self.path.clone(), &self.opts)?;
+        self.opts.report(&warnings);
+        Ok(foods)
+    }
+
+    fn save_foods(&mut self, foods: Foods) -> Result<(), AppError> {
+        crate::storage::save_foods(self.path.clone(), foods, &self.opts)
+    }
+
+    fn save_food(&mut self, food: &Food) -> Result<(), AppError> {
+        let mut fds = self.load_foods()?;
+        fds.insert(food.clone());
+        self.save_foods(fds)
+    }
+
+    fn remove_foods(&mut self, ids: &[i64], missing_ok: bool) -> Result<(Vec<Food>, Vec<i64>), AppError> {
+        let mut fds = self.load_foods()?;
+        let missing: Vec<i64> = ids.iter().copied().filter(|id| !fds.inner.contains_key(id)).collect();
+        if !missing.is_empty() && !missing_ok {
+            return Err(AppError::MissingIds(missing));
+        }
+        let removed: Vec<Food> = ids.iter().filter_map(|id| fds.remove(*id)).collect();
+        if !removed.is_empty() {
+            self.save_foods(fds)?;
+        }
+        Ok((removed, missing))
+    }
+
+    fn stream_foods(&self, visit: &mut dyn FnMut(Food) -> Result<(), AppError>) -> Result<(), AppError> {
+        if !self.check_exists()? {
+            return Ok(());
+        }
+        self.verify()?;
+        let file = open_data_file(&self.path)?;
+        let mut errors = Vec::new();
+        let mut records = FoodRecords::new(file, &self.opts)?;
+        self.opts.report(&std::mem::take(&mut records.warnings));
+        while let Some(food) = records.next() {
+            self.opts.report(&std::mem::take(&mut records.warnings));
+            match food {
+                Ok(food) => visit(food)?,
+                Err(e) if e.is_fatal() => return Err(e.into()),
+                Err(e) if self.opts.strict => errors.push(e),
+                Err(e) => self.opts.report(&[ParseWarning::from(e)]),
+            }
+        }
+        if !errors.is_empty() {
+            return Err(ParseError::BadRecords(errors).into());
+        }
+        Ok(())
+    }
+
+    fn index(&self) -> Result<FoodIndex, AppError> {
+        if self.is_appendable()? {
+            self.scan()
+        } else {
+            Ok(self.load_foods()?.index())
+        }
+    }
+
+    fn add_food(&mut self, mut food: Food) -> Result<(), AppError> {
+        if !self.is_appendable()? {
+            let mut fds = self.load_foods()?;
+            fds.add(food);
+            return self.save_foods(fds);
+        }
+
+        self.verify()?;
+        if self.opts.keep_backups > 0 {
+            crate::storage::backup_file(&self.path, self.opts.keep_backups)?;
+        }
+        food.stamp_created();
+        let file = OpenOptions::new().append(true).open(&self.path)?;
+        let mut writer = self.opts.writer(file);
+        self.opts.write_row(&mut writer, &food)?;
+        writer.flush()?;
+        file_sync(writer)?;
+        Ok(crate::storage::write_checksum(&self.path)?)
+    }
+}
+
+fn file_sync(writer: csv::Writer<File>) -> Result<(), AppError> {
+    let file = writer.into_inner().map_err(|e| e.into_error())?;
+    file.sync_all()?;
+    Ok(())
+}
+
+// Keeps the menu in memory for the shell and only writes it back to the
+// wrapped store on flush.
+pub(crate) struct MemoryStore<'a> {
+    inner: &'a mut dyn Storage,
+    foods: Foods,
+    dirty: bool,
+}
+
+impl<'a> MemoryStore<'a> {
+    pub(crate) fn new(inner: &'a mut dyn Storage) -> Result<Self, AppError> {
+        let foods = inner.load_foods()?;
+        Ok(MemoryStore { inner, foods, dirty: false })
+    }
+
+    pub(crate) fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub(crate) fn flush(&mut self) -> Result<(), AppError> {
+        if self.dirty {
+            self.inner.save_foods(self.foods.clone())?;
+            self.dirty = false;
+        }
+        Ok(())
+    }
+
+    // For commands that work on the data file itself rather than the store.
+    pub(crate) fn reload(&mut self) -> Result<(), AppError> {
+        self.foods = self.inner.load_foods()?;
+        self.dirty = false;
+        Ok(())
+    }
+}
+
+impl Storage for MemoryStore<'_> {
+    fn load_foods(&self) -> Result<Foods, AppError> {
+        Ok(self.foods.clone())
+    }
+
+    fn save_foods(&mut self, foods: Foods) -> Result<(), AppError> {
+        self.foods = foods;
+        self.dirty = true;
+        Ok(())
+    }
+
+    fn save_food(&mut self, food: &Food) -> Result<(), AppError> {
+        self.foods.insert(food.clone());
+        self.dirty = true;
+        Ok(())
+    }
+
+    fn remove_foods(&mut self, ids: &[i64], missing_ok: bool) -> Result<(Vec<Food>, Vec<i64>), AppError> {
+        let missing: Vec<i64> = ids.iter().copied().filter(|id| !self.foods.inner.contains_key(id)).collect();
+        if !missing.is_empty() && !missing_ok {
+            return Err(AppError::MissingIds(missing));
+        }
+        let removed: Vec<Food> = ids.iter().filter_map(|id| self.foods.remove(*id)).collect();
+        self.dirty |= !removed.is_empty();
+        Ok((removed, missing))
+    }
+
+    fn add_food(&mut self, food: Food) -> Result<(), AppError> {
+        self.foods.add(food);
+        self.dirty = true;
+        Ok(())
+    }
+}
+
+impl Foods {
+    pub(crate) fn from_reader<R: Read>(reader: R, opts: &CsvOptions) -> Result<(Foods, Vec<ParseWarning>), ParseError> {
+        let mut fds = Foods::new();
+        let mut lines = HashMap::new();
+        let mut errors = Vec::new();
+        let mut warnings = Vec::new();
+        let mut records = FoodRecords::new(std::io::BufReader::new(reader), opts)?;
+        warnings.append(&mut records.warnings);
+        while let Some(food) = records.next() {
+            warnings.append(&mut records.warnings);
+            let food = match food {
+                Ok(food) => food,
+                Err(e) if e.is_fatal() => return Err(e),
+                Err(e) if opts.strict => {
+                    errors.push(e);
+                    continue;
+                }
+                Err(e) => {
+                    warnings.push(ParseWarning::from(e));
+                    continue;
+                }
+            };
+
+            let line = records.record.position().map(|position| position.line()).unwrap_or_default();
+            let kept = records.text();
+            if let Some((first, raw)) = lines.insert(food.id, (line, kept.clone())) {
+                let error = ParseError::DuplicateRecord { id: food.id, first, line, kept };
+                if opts.strict {
+                    errors.push(error);
+                    continue;
+                }
+                // The warning is about the record being dropped.
+                warnings.push(ParseWarning { line: first as usize, raw, error });
+            }
+            fds.insert(food);
+        }
+        if !errors.is_empty() {
+            return Err(ParseError::BadRecords(errors));
+        }
+        Ok((fds, warnings))
+    }
+}
+
+pub(crate) struct FoodRecords<R: BufRead> {
+    pub(crate) reader: csv::Reader<TrimUnquoted<R>>,
+    pub(crate) headers: csv::StringRecord,
+    pub(crate) record: csv::StringRecord,
+    raw: csv::ByteRecord,
+    pub(crate) pending: Option<csv::StringRecord>,
+    pub(crate) count: usize,
+    fields: usize,
+    done: bool,
+    pub(crate) schema: u32,
+    pub(crate) skipped: u64,
+    pub(crate) lossy: bool,
+    // Replaced bytes and header oddities since the caller last took them.
+    pub(crate) warnings: Vec<ParseWarning>,
+}
+
+const REQUIRED_COLUMNS: [&str; 4] = ["id", "name", "stock", "price"];
+
+impl<R: BufRead> FoodRecords<R> {
+    pub(crate) fn new(mut reader: R, opts: &CsvOptions) -> Result<Self, ParseError> {
+        let (schema, skipped) = read_preamble(&mut reader)?;
+        let mut reader = csv::ReaderBuilder::new()
+            .flexible(true)
+            .delimiter(opts.delimiter)
+            .from_reader(TrimUnquoted::new(reader, opts.delimiter));
+        let mut first = csv::StringRecord::new();
+        let mut warnings = Vec::new();
+        match reader.byte_headers() {
+            Ok(headers) => decode_record(headers, &mut first, skipped, opts.lossy, &mut warnings)?,
+            Err(e) => return Err(ParseError::from_csv(e, &csv::StringRecord::new())),
+        }
+
+        let headerless = first.get(0).is_some_and(|id| id.parse::<i64>().is_ok());
+        let (headers, pending) = if headerless {
+            (csv::StringRecord::from(FOOD_HEADER.to_vec()), Some(first))
+        } else {
+            (first, None)
+        };
+        if !headers.is_empty() {
+            warnings.extend(check_header(&headers)?.map(ParseWarning::from));
+        }
+
+        Ok(Self {
+            reader,
+            headers,
+            record: csv::StringRecord::new(),
+            raw: csv::ByteRecord::new(),
+            pending,
+            count: 0,
+            fields: 0,
+            done: false,
+            schema,
+            skipped,
+            lossy: opts.lossy,
+            warnings,
+        })
+    }
+
+    // The current record as read, without the columns filled in for short rows.
+    pub(crate) fn text(&self) -> String {
+        self.record.iter().take(self.fields).collect::<Vec<_>>().join(",")
+    }
+}
+
+pub(crate) const SCHEMA_VERSION: u32 = 2;
+pub(crate) const SCHEMA_PREFIX: &str = "# schema=";
+
+// Skips a UTF-8 BOM and the schema line, returning the schema version and
+// how many bytes were consumed. Files without a schema line are version 1.
+fn read_preamble<R: BufRead>(reader: &mut R) -> Result<(u32, u64), ParseError> {
+    const BOM: &[u8] = b"\xEF\xBB\xBF";
+    let io_error = |e: std::io::Error| ParseError::Read(e.to_string());
+    let mut skipped = 0;
+    if reader.fill_buf().map_err(io_error)?.starts_with(BOM) {
+        reader.consume(BOM.len());
+        skipped += BOM.len() as u64;
+    }
+    if !reader.fill_buf().map_err(io_error)?.starts_with(SCHEMA_PREFIX.as_bytes()) {
+        return Ok((1, skipped));
+    }
+
+    let mut line = String::new();
+    skipped += reader.read_line(&mut line).map_err(io_error)? as u64;
+    let schema = line[SCHEMA_PREFIX.len()..]
+        .trim()
+        .parse::<u32>()
+        .map_err(|_| ParseError::InvalidSchema(line.trim().to_owned()))?;
+    if schema > SCHEMA_VERSION {
+        return Err(ParseError::UnsupportedSchema(schema));
+    }
+    Ok((schema, skipped))
+}
+
+// Drops the spaces and tabs around unquoted fields before the CSV reader sees
+// them. csv::Trim would strip them inside quotes too, where they are part of
+// the value.
+pub(crate) struct TrimUnquoted<R> {
+    inner: R,
+    delimiter: u8,
+    quoted: bool,
+    field_start: bool,
+    // Spaces and tabs held back until it is clear they are inside a field.
+    blanks: Vec<u8>,
+    out: Vec<u8>,
+    read: usize,
+}
+
+impl<R: BufRead> TrimUnquoted<R> {
+    fn new(inner: R, delimiter: u8) -> Self {
+        Self { inner, delimiter, quoted: false, field_start: true, blanks: Vec::new(), out: Vec::new(), read: 0 }
+    }
+
+    fn push(&mut self, byte: u8) {
+        if self.quoted {
+            self.quoted = byte != b'"';
+            self.out.push(byte);
+        } else if byte == self.delimiter || byte == b'\n' || byte == b'\r' {
+            self.blanks.clear();
+            self.out.push(byte);
+            self.field_start = true;
+        } else if byte == b' ' || byte == b'\t' {
+            if !self.field_start {
+                self.blanks.push(byte);
+            }
+        } else {
+            self.out.append(&mut self.blanks);
+            self.out.push(byte);
+            self.field_start = false;
+            self.quoted = byte == b'"';
+        }
+    }
+}
+
+impl<R: BufRead> Read for TrimUnquoted<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.read == self.out.len() {
+            self.out.clear();
+            self.read = 0;
+            let chunk = self.inner.fill_buf()?.to_vec();
+            if chunk.is_empty() {
+                return Ok(0);
+            }
+            self.inner.consume(chunk.len());
+            for byte in chunk {
+                self.push(byte);
+            }
+        }
+        let n = buf.len().min(self.out.len() - self.read);
+        buf[..n].copy_from_slice(&self.out[self.read..self.read + n]);
+        self.read += n;
+        Ok(n)
+    }
+}
+
+fn decode_record(
+    raw: &csv::ByteRecord,
+    record: &mut csv::StringRecord,
+    skipped: u64,
+    lossy: bool,
+    warnings: &mut Vec<ParseWarning>,
+) -> Result<(), ParseError> {
+    record.clear();
+    let mut offset = skipped + raw.position().map_or(0, |position| position.byte());
+    for field in raw {
+        match std::str::from_utf8(field) {
+            Ok(field) => record.push_field(field),
+            Err(e) if lossy => {
+                warnings.push(ParseError::ReplacedUtf8(offset + e.valid_up_to() as u64).into());
+                record.push_field(&String::from_utf8_lossy(field));
+            }
+            Err(e) => return Err(ParseError::InvalidUtf8(offset + e.valid_up_to() as u64)),
+        }
+        offset += field.len() as u64 + 1;
+    }
+    record.set_position(raw.position().cloned());
+    Ok(())
+}
+
+// A header missing a required column is an error. One with the known columns
+// out of order still reads, and comes back as a warning.
+fn check_header(headers: &csv::StringRecord) -> Result<Option<ParseError>, ParseError> {
+    let mismatch = || ParseError::SchemaMismatch(headers.iter().collect::<Vec<_>>().join(","));
+    if REQUIRED_COLUMNS.iter().any(|column| !headers.iter().any(|header| header == *column)) {
+        return Err(mismatch());
+    }
+    let known = headers.iter().filter(|header| FOOD_HEADER.contains(header));
+    Ok((!known.zip(FOOD_HEADER).all(|(header, column)| header == column)).then(mismatch))
+}
+
+impl<R: BufRead> Iterator for FoodRecords<R> {
+    type Item = Result<Food, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let (read, fresh) = match self.pending.take() {
+            Some(first) => {
+                self.record = first;
+                (Ok(true), false)
+            }
+            None => (self.reader.read_byte_record(&mut self.raw), true),
+        };
+        let record = match read {
+            Ok(false) => return None,
+            Ok(true) => self.count + 1,
+            Err(e) if e.is_io_error() => {
+                self.done = true;
+                return Some(Err(ParseError::Read(e.to_string())));
+            }
+            Err(e) => {
+                self.count += 1;
+                return Some(Err(ParseError::Record {
+                    record: self.count,
+                    line: e.position().map(|position| position.line()).unwrap_or_default(),
+                    text: String::new(),
+                    source: Box::new(ParseError::from_csv(e, &self.headers)),
+                }));
+            }
+        };
+        self.count = record;
+        if fresh {
+            if let Err(e) = decode_record(&self.raw, &mut self.record, self.skipped, self.lossy, &mut self.warnings) {
+                self.done = true;
+                return Some(Err(e));
+            }
+        }
+
+        self.fields = self.record.len();
+        while self.record.len() < self.headers.len() {
+            self.record.push_field("");
+        }
+        let food = self
+            .record
+            .deserialize::<Food>(Some(&self.headers))
+            .map_err(|e| ParseError::from_csv(e, &self.headers))
+            .and_then(Food::normalize)
+            .map(|mut food| {
+                food.extra = self
+                    .headers
+                    .iter()
+                    .zip(&self.record)
+                    .filter(|(header, _)| !FOOD_HEADER.contains(header))
+                    .map(|(header, value)| (header.to_owned(), value.to_owned()))
+                    .collect();
+                food
+            });
+        Some(food.map_err(|e| ParseError::Record {
+            record,
+            line: self.record.position().map(|position| position.line()).unwrap_or_default(),
+            text: self.text(),
+            source: Box::new(e),
+        }))
+    }
+}
+
+fn report_bad_record(e: &ParseError, verbose: bool) {
+    if !verbose {
+        return;
+    }
+    match e {
+        ParseError::Line { text, .. } => eprintln!("Error on {}\n > \"{}\"\n", e, text),
+        _ => eprintln!("\nError on {}\n", e),
+    }
+}
+
+// Parses each record of a log on its own, skipping the header line the log
+// is saved with. Bad records are skipped too and handed back with their line.
+fn parse_log<T>(
+    log: &'static str,
+    header: &str,
+    records: Vec<(usize, &str)>,
+    parse: fn(&str) -> Result<T, ParseError>,
+) -> (Vec<T>, Vec<ParseError>) {
+    let mut records = records.into_iter().filter(|(_, text)| !text.is_empty()).peekable();
+    // Older logs were saved without a header, so only a line that starts like
+    // one counts as one.
+    let first_column = |text: &str| text.split(',').next().map(str::trim).unwrap_or_default().to_owned();
+    if records.peek().is_some_and(|(_, text)| first_column(text) == first_column(header)) {
+        records.next();
+    }
+    let (mut parsed, mut errors) = (Vec::new(), Vec::new());
+    for (line, text) in records {
+        match parse(text) {
+            Ok(record) => parsed.push(record),
+            Err(e) => errors.push(ParseError::Line { log, line, text: text.to_owned(), source: Box::new(e) }),
+        }
+    }
+    (parsed, errors)
+}
+
+fn report_bad_records(errors: &[ParseError], verbose: bool) {
+    for e in errors {
+        report_bad_record(e, verbose);
+    }
+}
+
+fn numbered_lines(text: &str) -> Vec<(usize, &str)> {
+    text.lines().enumerate().map(|(num, line)| (num + 1, line)).collect()
+}
+
+fn split_records(text: &str) -> Vec<(usize, &str)> {
+    let mut records = Vec::new();
+    let (mut start, mut line, mut start_line) = (0, 1, 1);
+    let mut quoted = false;
+    for (i, c) in text.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '\n' if !quoted => {
+                records.push((start_line, text[start..i].trim_end_matches('\r')));
+                start = i + 1;
+                line += 1;
+                start_line = line;
+            }
+            '\n' => line += 1,
+            _ => {}
+        }
+    }
+    if start < text.len() {
+        records.push((start_line, text[start..].trim_end_matches('\r')));
+    }
+    records
+}
+
+fn split_record(record: &str) -> Result<Vec<String>, ParseError> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = record.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => quoted = true,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(ParseError::UnterminatedQuote);
+    }
+    fields.push(field);
+    Ok(fields)
+}
+
+fn quote_field(field: &str, delimiter: u8) -> String {
+    if field.contains([char::from(delimiter), '"', '\n', '\r']) || field.trim() != field {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CsvOptions {
+    pub(crate) delimiter: u8,
+    pub(crate) save_delimiter: u8,
+    pub(crate) crlf: bool,
+    pub(crate) keep_backups: usize,
+    pub(crate) lossy: bool,
+    pub(crate) strict: bool,
+    pub(crate) accept_changes: bool,
+    pub(crate) encrypt: bool,
+    pub(crate) verbose: bool,
+    // Shows what was skipped while reading, or not.
+    pub(crate) on_warning: fn(&ParseWarning, bool),
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            delimiter: b',',
+            save_delimiter: b',',
+            crlf: false,
+            keep_backups: 0,
+            lossy: false,
+            strict: false,
+            accept_changes: false,
+            encrypt: false,
+            verbose: false,
+            on_warning: |_, _| {},
+        }
+    }
+}
+
+impl CsvOptions {
+    pub(crate) fn report(&self, warnings: &[ParseWarning]) {
+        for warning in warnings {
+            (self.on_warning)(warning, self.verbose);
+        }
+    }
+
+    // Quoting is left to write_fields, so the writer must not quote again.
+    pub(crate) fn writer<W: Write>(&self, writer: W) -> csv::Writer<W> {
+        let terminator = if self.crlf { csv::Terminator::CRLF } else { csv::Terminator::Any(b'\n') };
+        csv::WriterBuilder::new()
+            .has_headers(false)
+            .delimiter(self.save_delimiter)
+            .terminator(terminator)
+            .quote_style(csv::QuoteStyle::Never)
+            .from_writer(writer)
+    }
+
+    // csv only quotes what it needs to split the record, but reading trims the
+    // spaces around unquoted fields, so a value starting or ending in one is
+    // quoted here too.
+    pub(crate) fn write_fields<'a, W: Write>(
+        &self,
+        writer: &mut csv::Writer<W>,
+        fields: impl IntoIterator<Item = &'a str>,
+    ) -> csv::Result<()> {
+        writer.write_record(fields.into_iter().map(|field| quote_field(field, self.save_delimiter)))
+    }
+
+    // Lays the row out with serde the way csv would, then writes its fields.
+    pub(crate) fn write_row<W: Write>(&self, writer: &mut csv::Writer<W>, row: impl Serialize) -> csv::Result<()> {
+        let mut layout = csv::WriterBuilder::new().has_headers(false).from_writer(Vec::new());
+        layout.serialize(row)?;
+        let line = layout.into_inner().map_err(|e| e.into_error())?;
+        let mut record = csv::StringRecord::new();
+        csv::ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader(line.as_slice())
+            .read_record(&mut record)?;
+        self.write_fields(writer, &record)
+    }
+}
+
+pub(crate) fn write_foods_csv<W: Write>(foods: &[&Food], mut writer: W, opts: &CsvOptions) -> std::io::Result<()> {
+    let newline = if opts.crlf { "\r\n" } else { "\n" };
+    write!(writer, "{}{}{}", SCHEMA_PREFIX, SCHEMA_VERSION, newline)?;
+
+    let mut extra: Vec<&str> = Vec::new();
+    for (column, _) in foods.iter().flat_map(|food| &food.extra) {
+        if !extra.contains(&column.as_str()) {
+            extra.push(column);
+        }
+    }
+
+    let mut writer = opts.writer(writer);
+    opts.write_fields(&mut writer, FOOD_HEADER.iter().copied().chain(extra.iter().copied()))?;
+    for food in foods {
+        let values: Vec<&str> = extra
+            .iter()
+            .map(|column| food.extra.iter().find(|(name, _)| name == column).map_or("", |(_, value)| value))
+            .collect();
+        opts.write_row(&mut writer, (food, values))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub(crate) fn parse_delimiter(delimiter: &str) -> Result<u8, ParseError> {
+    match delimiter {
+        "\\t" | "tab" => Ok(b'\t'),
+        _ if delimiter.len() == 1 && delimiter != "\"" && delimiter != "\n" => Ok(delimiter.as_bytes()[0]),
+        _ => Err(ParseError::InvalidDelimiter(delimiter.to_owned())),
+    }
+}
+
+fn is_gzip(file_name: &Path) -> bool {
+    file_name.extension().is_some_and(|ext| ext == "gz")
+}
+
+struct Gunzip<R: BufRead>(flate2::bufread::MultiGzDecoder<R>);
+
+impl<R: BufRead> Read for Gunzip<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0
+            .read(buf)
+            .map_err(|e| std::io::Error::new(e.kind(), format!("corrupt gzip stream: {}", e)))
+    }
+}
+
+pub(crate) fn is_stdio(file_name: &Path) -> bool {
+    file_name.as_os_str() == "-"
+}
+
+pub(crate) fn open_data_file(file_name: &Path) -> Result<Box<dyn BufRead>, AppError> {
+    if is_stdio(file_name) {
+        return Ok(Box::new(std::io::stdin().lock()));
+    }
+    let mut file: Box<dyn BufRead> = Box::new(std::io::BufReader::new(File::open(file_name)?));
+    if file.fill_buf()?.starts_with(ENCRYPTED_MAGIC) {
+        let mut data = Vec::new();
+        file.read_to_end(&mut data)?;
+        file = Box::new(std::io::Cursor::new(decrypt_data(&data)?));
+    }
+    if is_gzip(file_name) {
+        file = Box::new(std::io::BufReader::new(Gunzip(flate2::bufread::MultiGzDecoder::new(file))));
+    }
+    Ok(file)
+}
+
+pub fn load_foods(file_name: PathBuf, opts: &CsvOptions) -> Result<(Foods, Vec<ParseWarning>), AppError> {
+    let file = open_data_file(&file_name)?;
+    Ok(Foods::from_reader(file, opts)?)
+}
+
+// Reads items CSV with or without its header. Bad records are skipped the way
+// loading the data file does, and returned as warnings along with the items.
+pub fn parse_foods<R: Read>(reader: R) -> Result<(Foods, Vec<ParseWarning>), ParseError> {
+    Foods::from_reader(reader, &CsvOptions::default())
+}
+
+// One record without a header, its columns in data file order, e.g. "1,bakso,54,18000".
+pub fn parse_food(record: &str) -> Result<Food, ParseError> {
+    let mut records = FoodRecords::new(record.as_bytes(), &CsvOptions::default())?;
+    records.next().unwrap_or(Err(ParseError::EmptyRecord))
+}
+
+// The record parse_food reads back, without extra columns or a line ending.
+pub fn format_food(food: &Food) -> std::io::Result<String> {
+    let opts = CsvOptions::default();
+    let mut writer = opts.writer(Vec::new());
+    opts.write_row(&mut writer, food)?;
+    let record = writer.into_inner().map_err(|e| e.into_error())?;
+    Ok(String::from_utf8_lossy(&record).trim_end_matches('\n').to_owned())
+}
+
+pub(crate) fn foods_to_json<'a>(foods: impl IntoIterator<Item = &'a Food>) -> serde_json::Result<String> {
+    let foods: Vec<JsonFood> = foods.into_iter().map(JsonFood::from).collect();
+    Ok(serde_json::to_string_pretty(&foods)? + "\n")
+}
+
+// A single item as an object rather than an array of one.
+pub(crate) fn food_to_json(food: &Food) -> serde_json::Result<String> {
+    serde_json::to_string_pretty(&JsonFood::from(food))
+}
+
+pub(crate) fn foods_to_jsonl<'a>(foods: impl IntoIterator<Item = &'a Food>) -> serde_json::Result<String> {
+    let mut out = String::new();
+    for food in foods {
+        out.push_str(&serde_json::to_string(&JsonFood::from(food))?);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+pub(crate) fn foods_from_jsonl(text: &str) -> Result<Vec<Food>, AppError> {
+    let foods = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str::<JsonFood>(line).map(Food::from))
+        .collect::<Result<Vec<Food>, _>>()?;
+    check_imported(foods)
+}
+
+pub(crate) fn foods_from_json(text: &str) -> Result<Vec<Food>, AppError> {
+    let foods: Vec<JsonFood> = serde_json::from_str(text)?;
+    check_imported(foods.into_iter().map(Food::from).collect())
+}
+
+fn check_imported(foods: Vec<Food>) -> Result<Vec<Food>, AppError> {
+    let mut ids = HashSet::new();
+    foods
+        .into_iter()
+        .map(|food| {
+            let food = food.normalize()?;
+            if !ids.insert(food.id) {
+                return Err(AppError::DuplicateId(food.id));
+            }
+            if let Some(cost) = food.cost.filter(|cost| cost.cents() < 0) {
+                return Err(AppError::InvalidCost(cost));
+            }
+            Ok(food)
+        })
+        .collect()
+}
+
+pub fn save_foods(file_name: PathBuf, foods:Foods, opts: &CsvOptions) -> Result<(), AppError> {
+    if opts.keep_backups > 0 && file_name.exists() {
+        backup_file(&file_name, opts.keep_backups)?;
+    }
+    let encrypted = is_encrypted(&file_name);
+    match (opts.encrypt || encrypted, is_gzip(&file_name)) {
+        (false, false) => save_atomically(&file_name, |file| write_foods(file, &foods, opts))?,
+        (false, true) => save_atomically(&file_name, |file| write_gzip(file, &foods, opts))?,
+        (true, gzip) => {
+            let mut data = Vec::new();
+            if gzip {
+                write_gzip(&mut data, &foods, opts)?;
+            } else {
+                write_foods(&mut data, &foods, opts)?;
+            }
+            let data = encrypt_data(&data, !encrypted)?;
+            save_atomically(&file_name, |file| file.write_all(&data))?;
+        }
+    }
+    Ok(write_checksum(&file_name)?)
+}
+
+// The data file contents, schema line and header included, as save_foods
+// writes them before any compression or encryption.
+pub fn write_foods<W: Write>(writer: W, foods: &Foods, opts: &CsvOptions) -> std::io::Result<()> {
+    write_foods_csv(&foods.sorted(), writer, opts)
+}
+
+fn write_gzip<W: Write>(writer: W, foods: &Foods, opts: &CsvOptions) -> std::io::Result<()> {
+    let mut encoder = flate2::write::GzEncoder::new(writer, flate2::Compression::default());
+    write_foods(&mut encoder, foods, opts)?;
+    encoder.finish()?;
+    Ok(())
+}
+
+pub(crate) const ENCRYPTED_MAGIC: &[u8] = b"RESTAURANT-ENC1\n";
+
+fn is_encrypted(file_name: &Path) -> bool {
+    let mut magic = [0u8; ENCRYPTED_MAGIC.len()];
+    File::open(file_name).and_then(|mut file| file.read_exact(&mut magic)).is_ok() && magic == ENCRYPTED_MAGIC
+}
+
+#[cfg(feature = "encryption")]
+pub(crate) fn encrypt_data(data: &[u8], confirm: bool) -> Result<Vec<u8>, AppError> {
+    crypto::encrypt(data, crypto::passphrase(confirm)?)
+}
+
+#[cfg(feature = "encryption")]
+pub(crate) fn decrypt_data(data: &[u8]) -> Result<Vec<u8>, AppError> {
+    crypto::decrypt(data, crypto::passphrase(false)?)
+}
+
+#[cfg(not(feature = "encryption"))]
+pub(crate) fn encrypt_data(_: &[u8], _: bool) -> Result<Vec<u8>, AppError> {
+    Err(AppError::NotCompiledIn("encryption"))
+}
+
+#[cfg(not(feature = "encryption"))]
+pub(crate) fn decrypt_data(_: &[u8]) -> Result<Vec<u8>, AppError> {
+    Err(AppError::NotCompiledIn("encryption"))
+}
+
+fn checksum_path(file_name: &Path) -> PathBuf {
+    let mut name = file_name.as_os_str().to_owned();
+    name.push(".sha256");
+    PathBuf::from(name)
+}
+
+fn file_checksum(file_name: &Path) -> std::io::Result<String> {
+    use sha2::Digest;
+    let mut hasher = sha2::Sha256::new();
+    std::io::copy(&mut File::open(file_name)?, &mut hasher)?;
+    Ok(hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+// The sidecar uses the sha256sum format so it can also be checked by hand.
+pub(crate) fn write_checksum(file_name: &Path) -> std::io::Result<()> {
+    let checksum = file_checksum(file_name)?;
+    let name = file_name.file_name().unwrap_or_default().to_string_lossy();
+    save_atomically(&checksum_path(file_name), |file| writeln!(file, "{}  {}", checksum, name))
+}
+
+pub(crate) fn check_checksum(file_name: &Path) -> std::io::Result<Option<bool>> {
+    let expected = match std::fs::read_to_string(checksum_path(file_name)) {
+        Ok(contents) => contents.split_whitespace().next().unwrap_or_default().to_owned(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    Ok(Some(file_checksum(file_name)? == expected))
+}
+
+pub(crate) fn backup_dir(file_name: &Path) -> PathBuf {
+    file_name.with_file_name("backups")
+}
+
+pub(crate) fn list_backups(file_name: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let stem = file_name.file_stem().unwrap_or_default().to_string_lossy();
+    let prefix = format!("{}-", stem);
+    let mut backups = Vec::new();
+    let entries = match std::fs::read_dir(backup_dir(file_name)) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    for entry in entries {
+        let path = entry?.path();
+        let is_backup = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with(&prefix))
+            && path.extension() == file_name.extension();
+        if is_backup {
+            backups.push((std::fs::metadata(&path)?.modified()?, path));
+        }
+    }
+    backups.sort();
+    Ok(backups.into_iter().map(|(_, path)| path).collect())
+}
+
+pub(crate) static DRY_RUN: AtomicBool = AtomicBool::new(false);
+
+pub(crate) fn is_dry_run() -> bool {
+    DRY_RUN.load(Ordering::Relaxed)
+}
+
+pub(crate) fn backup_file(file_name: &Path, keep: usize) -> std::io::Result<()> {
+    if is_dry_run() {
+        return Ok(());
+    }
+    let dir = backup_dir(file_name);
+    std::fs::create_dir_all(&dir)?;
+
+    let stem = format!("{}-{}", file_name.file_stem().unwrap_or_default().to_string_lossy(), Local::now().format("%Y%m%d-%H%M%S"));
+    let extension = match file_name.extension() {
+        Some(extension) => format!(".{}", extension.to_string_lossy()),
+        None => String::new(),
+    };
+    let mut backup = dir.join(format!("{}{}", stem, extension));
+    for n in 2.. {
+        if !backup.exists() {
+            break;
+        }
+        backup = dir.join(format!("{}-{}{}", stem, n, extension));
+    }
+    std::fs::copy(file_name, &backup)?;
+
+    let backups = list_backups(file_name)?;
+    for old in backups.iter().take(backups.len().saturating_sub(keep)) {
+        std::fs::remove_file(old)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn find_backup(file_name: &Path, which: Option<&str>) -> Result<PathBuf, AppError> {
+    match which {
+        Some(which) => {
+            let path = PathBuf::from(which);
+            let candidates = [backup_dir(file_name).join(which), path];
+            match candidates.into_iter().find(|path| path.is_file()) {
+                Some(path) => Ok(path),
+                None => Err(AppError::BackupNotFound(which.to_owned())),
+            }
+        }
+        None => match list_backups(file_name)?.pop() {
+            Some(path) => Ok(path),
+            None => Err(AppError::NoBackups(backup_dir(file_name))),
+        },
+    }
+}
+
+pub(crate) fn save_atomically<F>(file_name: &Path, write: F) -> std::io::Result<()>
+where
+    F: FnOnce(&mut std::io::BufWriter<File>) -> std::io::Result<()>,
+{
+    // Every file this program changes goes through here, so a dry run stops here.
+    if is_dry_run() {
+        return Ok(());
+    }
+    let mut tmp_name = file_name.as_os_str().to_owned();
+    tmp_name.push(".tmp");
+    let tmp_file = PathBuf::from(tmp_name);
+    if let Some(dir) = file_name.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    let result = File::create(&tmp_file).and_then(|file| {
+        let mut writer = std::io::BufWriter::new(file);
+        write(&mut writer)?;
+        let file = writer.into_inner().map_err(|e| e.into_error())?;
+        file.sync_all()?;
+        std::fs::rename(&tmp_file, file_name)
+    });
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp_file);
+    }
+    result
+}
+
+fn parse_sale(sale: &str) -> Result<Sale, ParseError> {
+    let fields: Vec<&str> = sale.split(',').collect();
+
+    let id = match fields.first().filter(|id| !id.is_empty()) {
+        Some(id) => id.parse::<i64>()?,
+        None => return Err(ParseError::EmptyRecord),
+    };
+
+    let food_id = match fields.get(1) {
+        Some(food_id) => food_id.parse::<i64>()?,
+        None => return Err(ParseError::MissingField("food_id".to_owned())),
+    };
+
+    let quantity = match fields.get(2) {
+        Some(quantity) => quantity.parse::<Quantity>()?,
+        None => return Err(ParseError::MissingField("quantity".to_owned())),
+    };
+
+    let unit_price = match fields.get(3) {
+        Some(unit_price) => parse_price(unit_price)?,
+        None => return Err(ParseError::MissingField("unit_price".to_owned())),
+    };
+
+    let total = match fields.get(4) {
+        Some(total) => parse_price(total)?,
+        None => return Err(ParseError::MissingField("total".to_owned())),
+    };
+
+    let timestamp = match fields.get(5) {
+        Some(timestamp) => DateTime::parse_from_rfc3339(timestamp)?.with_timezone(&Local),
+        None => return Err(ParseError::MissingField("timestamp".to_owned())),
+    };
+
+    let refunded = match fields.get(6) {
+        None | Some(&"") | Some(&"completed") => false,
+        Some(&"refunded") => true,
+        Some(status) => return Err(ParseError::InvalidStatus(status.to_string())),
+    };
+
+    let net = match fields.get(7).filter(|net| !net.is_empty()) {
+        Some(net) => parse_price(net)?,
+        None => total,
+    };
+
+    let tax = match fields.get(8).filter(|tax| !tax.is_empty()) {
+        Some(tax) => parse_price(tax)?,
+        None => 0,
+    };
+
+    Ok(Sale { id, food_id, quantity, unit_price, total, timestamp, refunded, net, tax })
+}
+
+const SALES_HEADER: &str = "id,food_id,quantity,unit_price,total,timestamp,status,net,tax";
+
+fn parse_sales(sales: String, verbose: bool) -> Sales {
+    let mut sls = Sales::new();
+    let (records, errors) = parse_log("sales", SALES_HEADER, numbered_lines(&sales), parse_sale);
+    report_bad_records(&errors, verbose);
+    for sl in records {
+        sls.add(sl);
+    }
+    sls
+}
+
+pub(crate) fn load_sales(file_name: PathBuf, verbose: bool) -> std::io::Result<Sales> {
+    let mut file = match File::open(file_name) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Sales::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut buffer = String::new();
+    file.read_to_string(&mut buffer)?;
+
+    Ok(parse_sales(buffer, verbose))
+}
+
+pub(crate) fn sale_counter_file(sales_file: &Path) -> PathBuf {
+    let mut name = sales_file.as_os_str().to_owned();
+    name.push(".seq");
+    PathBuf::from(name)
+}
+
+pub(crate) fn reserve_sale_id(counter_file: &Path, sales: &Sales) -> Result<i64, AppError> {
+    let stored = match std::fs::read_to_string(counter_file) {
+        Ok(contents) => Some(contents.trim().parse::<i64>().map_err(ParseError::from)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+
+    let id = match stored {
+        Some(next) => next.max(sales.next_id()),
+        None => sales.next_id(),
+    };
+
+    save_atomically(counter_file, |file| writeln!(file, "{}", id + 1))?;
+    Ok(id)
+}
+
+pub(crate) fn save_sales(file_name: PathBuf, sales: Sales) -> std::io::Result<()> {
+    save_atomically(&file_name, |file| {
+        writeln!(file, "{}", SALES_HEADER)?;
+
+        for sale in sales.inner.iter() {
+            let line = format!(
+                "{},{},{},{},{},{},{},{},{}\n",
+                sale.id,
+                sale.food_id,
+                sale.quantity,
+                format_price(sale.unit_price),
+                format_price(sale.total),
+                sale.timestamp.to_rfc3339(),
+                if sale.refunded { "refunded" } else { "completed" },
+                format_price(sale.net),
+                format_price(sale.tax)
+            );
+            file.write_all(line.as_bytes())?;
+        }
+        file.flush()
+    })
+}
+
+fn parse_order_item(item: &str) -> Result<(i64, Quantity), ParseError> {
+    let invalid = || ParseError::InvalidOrderItem(item.to_owned());
+    let (id, quantity) = item.split_once(':').ok_or_else(invalid)?;
+    let id = id.trim().parse::<i64>().map_err(|_| invalid())?;
+    let quantity = quantity.trim().parse::<Quantity>().map_err(|_| invalid())?;
+    Ok((id, quantity))
+}
+
+pub(crate) fn parse_order_items(items: &[String]) -> Result<Vec<(i64, Quantity)>, ParseError> {
+    let mut merged: Vec<(i64, Quantity)> = Vec::new();
+    for item in items {
+        let (id, quantity) = parse_order_item(item)?;
+        match merged.iter_mut().find(|(existing, _)| *existing == id) {
+            Some((_, total)) => *total = Quantity(total.0.saturating_add(quantity.0)),
+            None => merged.push((id, quantity)),
+        }
+    }
+    Ok(merged)
+}
+
+fn parse_order(order: &str) -> Result<Order, ParseError> {
+    let fields: Vec<&str> = order.split(',').collect();
+
+    let id = match fields.first().filter(|id| !id.is_empty()) {
+        Some(id) => id.parse::<i64>()?,
+        None => return Err(ParseError::EmptyRecord),
+    };
+
+    let food_id = match fields.get(1) {
+        Some(food_id) => food_id.parse::<i64>()?,
+        None => return Err(ParseError::MissingField("food_id".to_owned())),
+    };
+
+    let quantity = match fields.get(2) {
+        Some(quantity) => quantity.parse::<Quantity>()?,
+        None => return Err(ParseError::MissingField("quantity".to_owned())),
+    };
+
+    let status = match fields.get(3) {
+        Some(status) => status.parse::<OrderStatus>()?,
+        None => return Err(ParseError::MissingField("status".to_owned())),
+    };
+
+    let created_at = match fields.get(4) {
+        Some(created_at) => DateTime::parse_from_rfc3339(created_at)?.with_timezone(&Local),
+        None => return Err(ParseError::MissingField("created_at".to_owned())),
+    };
+
+    Ok(Order { id, food_id, quantity, status, created_at })
+}
+
+const ORDERS_HEADER: &str = "id,food_id,quantity,status,created_at";
+
+fn parse_orders(orders: String, verbose: bool) -> Orders {
+    let mut ords = Orders::new();
+    let (records, errors) = parse_log("orders", ORDERS_HEADER, numbered_lines(&orders), parse_order);
+    report_bad_records(&errors, verbose);
+    for ord in records {
+        ords.add(ord);
+    }
+    ords
+}
+
+pub(crate) fn load_orders(file_name: PathBuf, verbose: bool) -> std::io::Result<Orders> {
+    let mut file = match File::open(file_name) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Orders::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut buffer = String::new();
+    file.read_to_string(&mut buffer)?;
+
+    Ok(parse_orders(buffer, verbose))
+}
+
+pub(crate) fn save_orders(file_name: PathBuf, orders: Orders) -> std::io::Result<()> {
+    save_atomically(&file_name, |file| {
+        writeln!(file, "{}", ORDERS_HEADER)?;
+
+        for order in orders.with_status(None) {
+            let line = format!(
+                "{},{},{},{},{}\n",
+                order.id,
+                order.food_id,
+                order.quantity,
+                order.status.as_str(),
+                order.created_at.to_rfc3339()
+            );
+            file.write_all(line.as_bytes())?;
+        }
+        file.flush()
+    })
+}
+
+fn parse_daily_total(total: &str) -> Result<DailyTotal, ParseError> {
+    let fields: Vec<&str> = total.split(',').collect();
+
+    let date = match fields.first().filter(|date| !date.is_empty()) {
+        Some(date) => parse_date(date)?,
+        None => return Err(ParseError::EmptyRecord),
+    };
+
+    let transactions = match fields.get(1) {
+        Some(transactions) => transactions.parse::<usize>()?,
+        None => return Err(ParseError::MissingField("transactions".to_owned())),
+    };
+
+    let items = match fields.get(2) {
+        Some(items) => items.parse::<Quantity>()?,
+        None => return Err(ParseError::MissingField("items".to_owned())),
+    };
+
+    let revenue = match fields.get(3) {
+        Some(revenue) => parse_price(revenue)?,
+        None => return Err(ParseError::MissingField("revenue".to_owned())),
+    };
+
+    let refunds = match fields.get(4) {
+        Some(refunds) => refunds.parse::<usize>()?,
+        None => return Err(ParseError::MissingField("refunds".to_owned())),
+    };
+
+    let refunded = match fields.get(5) {
+        Some(refunded) => parse_price(refunded)?,
+        None => return Err(ParseError::MissingField("refunded".to_owned())),
+    };
+
+    Ok(DailyTotal { date, transactions, items, revenue, refunds, refunded })
+}
+
+const DAILY_TOTALS_HEADER: &str = "date,transactions,items,revenue,refunds,refunded";
+
+fn parse_daily_totals(totals: String, verbose: bool) -> DailyTotals {
+    let mut dts = DailyTotals::new();
+    let (records, errors) = parse_log("daily totals", DAILY_TOTALS_HEADER, numbered_lines(&totals), parse_daily_total);
+    report_bad_records(&errors, verbose);
+    for dt in records {
+        dts.add(dt);
+    }
+    dts
+}
+
+pub(crate) fn load_daily_totals(file_name: PathBuf, verbose: bool) -> std::io::Result<DailyTotals> {
+    let mut file = match File::open(file_name) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(DailyTotals::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut buffer = String::new();
+    file.read_to_string(&mut buffer)?;
+
+    Ok(parse_daily_totals(buffer, verbose))
+}
+
+pub(crate) fn save_daily_totals(file_name: PathBuf, totals: DailyTotals) -> std::io::Result<()> {
+    save_atomically(&file_name, |file| {
+        writeln!(file, "{}", DAILY_TOTALS_HEADER)?;
+
+        for total in totals.inner.values() {
+            let line = format!(
+                "{},{},{},{},{},{}\n",
+                total.date,
+                total.transactions,
+                total.items,
+                format_price(total.revenue),
+                total.refunds,
+                format_price(total.refunded)
+            );
+            file.write_all(line.as_bytes())?;
+        }
+        file.flush()
+    })
+}
+
+fn parse_waste(waste: &str) -> Result<Waste, ParseError> {
+    let record = split_record(waste)?;
+    let fields: Vec<&str> = record.iter().map(String::as_str).collect();
+
+    let id = match fields.first().filter(|id| !id.is_empty()) {
+        Some(id) => id.parse::<i64>()?,
+        None => return Err(ParseError::EmptyRecord),
+    };
+
+    let food_id = match fields.get(1) {
+        Some(food_id) => food_id.parse::<i64>()?,
+        None => return Err(ParseError::MissingField("food_id".to_owned())),
+    };
+
+    let quantity = match fields.get(2) {
+        Some(quantity) => quantity.parse::<Quantity>()?,
+        None => return Err(ParseError::MissingField("quantity".to_owned())),
+    };
+
+    let unit_price = match fields.get(3) {
+        Some(unit_price) => parse_price(unit_price)?,
+        None => return Err(ParseError::MissingField("unit_price".to_owned())),
+    };
+
+    let timestamp = match fields.get(4) {
+        Some(timestamp) => DateTime::parse_from_rfc3339(timestamp)?.with_timezone(&Local),
+        None => return Err(ParseError::MissingField("timestamp".to_owned())),
+    };
+
+    let reason = fields.get(5..).map(|reason| reason.join(",")).unwrap_or_default();
+
+    Ok(Waste { id, food_id, quantity, unit_price, timestamp, reason })
+}
+
+const WASTE_HEADER: &str = "id,food_id,quantity,unit_price,timestamp,reason";
+
+fn parse_wastes(wastes: String, verbose: bool) -> Wastes {
+    let mut wst = Wastes::new();
+    let (records, errors) = parse_log("waste", WASTE_HEADER, split_records(&wastes), parse_waste);
+    report_bad_records(&errors, verbose);
+    for ws in records {
+        wst.add(ws);
+    }
+    wst
+}
+
+pub(crate) fn load_wastes(file_name: PathBuf, verbose: bool) -> std::io::Result<Wastes> {
+    let mut file = match File::open(file_name) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Wastes::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut buffer = String::new();
+    file.read_to_string(&mut buffer)?;
+
+    Ok(parse_wastes(buffer, verbose))
+}
+
+pub(crate) fn save_wastes(file_name: PathBuf, wastes: Wastes) -> std::io::Result<()> {
+    save_atomically(&file_name, |file| {
+        writeln!(file, "{}", WASTE_HEADER)?;
+
+        for waste in wastes.inner.iter() {
+            let line = format!(
+                "{},{},{},{},{},{}\n",
+                waste.id,
+                waste.food_id,
+                waste.quantity,
+                format_price(waste.unit_price),
+                waste.timestamp.to_rfc3339(),
+                quote_field(&waste.reason, b',')
+            );
+            file.write_all(line.as_bytes())?;
+        }
+        file.flush()
+    })
+}
+
+pub(crate) fn write_all_or_nothing<F>(files: &[&PathBuf], write: F) -> Result<(), AppError>
+where
+    F: FnOnce() -> Result<(), AppError>,
+{
+    let mut originals = Vec::new();
+    for path in files {
+        match std::fs::read(path) {
+            Ok(bytes) => originals.push((*path, Some(bytes))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => originals.push((*path, None)),
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    let result = write();
+    if result.is_err() {
+        for (path, original) in originals {
+            let _ = match original {
+                Some(bytes) => std::fs::write(path, bytes),
+                None => std::fs::remove_file(path),
+            };
+        }
+    }
+    result
+}
+
+#[cfg(feature = "sqlite")]
+mod sqlite {
+    use std::path::Path;
+
+    use chrono::prelude::*;
+    use rusqlite::{params, Connection, OptionalExtension, Row};
+
+    use super::Storage;
+    use crate::error::AppError;
+    use crate::model::{join_tags, parse_tags, Food, Foods, Money, Quantity};
+
+    const SCHEMA: &str = "
+        CREATE TABLE IF NOT EXISTS foods (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            stock INTEGER NOT NULL,
+            price INTEGER NOT NULL,
+            sold_count INTEGER NOT NULL DEFAULT 0,
+            cost INTEGER,
+            category TEXT NOT NULL,
+            available INTEGER NOT NULL DEFAULT 1,
+            min_stock INTEGER NOT NULL DEFAULT 0,
+            expires TEXT,
+            supplier TEXT NOT NULL DEFAULT '',
+            sku TEXT,
+            allergens TEXT NOT NULL DEFAULT '',
+            unit TEXT NOT NULL,
+            notes TEXT NOT NULL DEFAULT '',
+            created_at TEXT,
+            updated_at TEXT,
+            tags TEXT NOT NULL DEFAULT '',
+            special_until TEXT
+        )";
+
+    const UPSERT: &str = "
+        INSERT INTO foods (
+            id, name, stock, price, sold_count, cost, category, available, min_stock, expires,
+            supplier, sku, allergens, unit, notes, created_at, updated_at, tags, special_until
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19)
+        ON CONFLICT(id) DO UPDATE SET
+            name = excluded.name, stock = excluded.stock, price = excluded.price,
+            sold_count = excluded.sold_count, cost = excluded.cost, category = excluded.category,
+            available = excluded.available, min_stock = excluded.min_stock, expires = excluded.expires,
+            supplier = excluded.supplier, sku = excluded.sku, allergens = excluded.allergens,
+            unit = excluded.unit, notes = excluded.notes, created_at = excluded.created_at,
+            updated_at = excluded.updated_at, tags = excluded.tags, special_until = excluded.special_until";
+
+    const SELECT: &str = "
+        SELECT id, name, stock, price, sold_count, cost, category, available, min_stock, expires,
+            supplier, sku, allergens, unit, notes, created_at, updated_at, tags, special_until
+        FROM foods";
+
+    pub(crate) struct SqliteStore {
+        conn: Connection,
+    }
+
+    impl SqliteStore {
+        pub(crate) fn open(path: &Path) -> Result<Self, AppError> {
+            let conn = Connection::open(path)?;
+            conn.execute_batch(SCHEMA)?;
+            Ok(Self { conn })
+        }
+    }
+
+    fn parse_column<T, E>(index: usize, value: Option<String>, parse: impl Fn(&str) -> Result<T, E>) -> rusqlite::Result<Option<T>>
+    where
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        match value.filter(|value| !value.is_empty()) {
+            Some(value) => parse(&value)
+                .map(Some)
+                .map_err(|e| rusqlite::Error::FromSqlConversionFailure(index, rusqlite::types::Type::Text, Box::new(e))),
+            None => Ok(None),
+        }
+    }
+
+    fn food_from_row(row: &Row) -> rusqlite::Result<Food> {
+        let timestamp = |value: &str| DateTime::parse_from_rfc3339(value).map(|at| at.with_timezone(&Local));
+        let date = |value: &str| NaiveDate::parse_from_str(value, "%Y-%m-%d");
+        Ok(Food {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            stock: Quantity(row.get(2)?),
+            price: Money(row.get(3)?),
+            sold_count: Quantity(row.get(4)?),
+            cost: row.get::<_, Option<i64>>(5)?.map(Money),
+            category: row.get(6)?,
+            available: row.get(7)?,
+            min_stock: Quantity(row.get(8)?),
+            expires: parse_column(9, row.get(9)?, date)?,
+            supplier: row.get(10)?,
+            sku: row.get(11)?,
+            allergens: parse_tags(&row.get::<_, String>(12)?),
+            unit: row.get(13)?,
+            notes: row.get(14)?,
+            created_at: parse_column(15, row.get(15)?, timestamp)?,
+            updated_at: parse_column(16, row.get(16)?, timestamp)?,
+            tags: parse_tags(&row.get::<_, String>(17)?),
+            special_until: parse_column(18, row.get(18)?, date)?,
+            extra: Vec::new(),
+        })
+    }
+
+    fn upsert(conn: &Connection, food: &Food) -> rusqlite::Result<()> {
+        conn.execute(
+            UPSERT,
+            params![
+                food.id,
+                food.name,
+                food.stock.0,
+                food.price.cents(),
+                food.sold_count.0,
+                food.cost.map(Money::cents),
+                food.category,
+                food.available,
+                food.min_stock.0,
+                food.expires.map(|expires| expires.to_string()),
+                food.supplier,
+                food.sku,
+                join_tags(&food.allergens),
+                food.unit,
+                food.notes,
+                food.created_at.map(|created_at| created_at.to_rfc3339()),
+                food.updated_at.map(|updated_at| updated_at.to_rfc3339()),
+                join_tags(&food.tags),
+                food.special_until.map(|until| until.to_string()),
+            ],
+        )?;
+        Ok(())
+    }
+
+    impl Storage for SqliteStore {
+        fn load_foods(&self) -> Result<Foods, AppError> {
+            let mut statement = self.conn.prepare(SELECT)?;
+            let mut fds = Foods::new();
+            for food in statement.query_map([], food_from_row)? {
+                fds.insert(food?);
+            }
+            Ok(fds)
+        }
+
+        fn save_foods(&mut self, foods: Foods) -> Result<(), AppError> {
+            let transaction = self.conn.transaction()?;
+            transaction.execute("DELETE FROM foods", [])?;
+            for food in foods.sorted() {
+                upsert(&transaction, food)?;
+            }
+            transaction.commit()?;
+            Ok(())
+        }
+
+        fn save_food(&mut self, food: &Food) -> Result<(), AppError> {
+            Ok(upsert(&self.conn, food)?)
+        }
+
+        fn add_food(&mut self, mut food: Food) -> Result<(), AppError> {
+            food.stamp_created();
+            self.save_food(&food)
+        }
+
+        fn remove_foods(&mut self, ids: &[i64], missing_ok: bool) -> Result<(Vec<Food>, Vec<i64>), AppError> {
+            let transaction = self.conn.transaction()?;
+            let (mut removed, mut missing) = (Vec::new(), Vec::new());
+            for &id in ids {
+                match transaction.query_row(&format!("{} WHERE id = ?1", SELECT), [id], food_from_row).optional()? {
+                    Some(food) => removed.push(food),
+                    None => missing.push(id),
+                }
+            }
+            if !missing.is_empty() && !missing_ok {
+                return Err(AppError::MissingIds(missing));
+            }
+            for food in &removed {
+                transaction.execute("DELETE FROM foods WHERE id = ?1", [food.id])?;
+            }
+            transaction.commit()?;
+            Ok((removed, missing))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::model::Quantity;
+
+        fn store() -> SqliteStore {
+            let conn = Connection::open_in_memory().unwrap();
+            conn.execute_batch(SCHEMA).unwrap();
+            SqliteStore { conn }
+        }
+
+        fn food(id: i64, name: &str) -> Food {
+            Food::new(id, name, "54".parse().unwrap(), "18000".parse().unwrap())
+        }
+
+        fn menu() -> SqliteStore {
+            let mut store = store();
+            let foods: Foods = [food(1, "bakso"), food(2, "sate"), food(3, "risoles")].into_iter().collect();
+            store.save_foods(foods).unwrap();
+            store
+        }
+
+        fn ids(store: &SqliteStore) -> Vec<i64> {
+            store.load_foods().unwrap().sorted().iter().map(|food| food.id).collect()
+        }
+
+        #[test]
+        fn saved_foods_load_back_the_same() {
+            let mut full = food(3, "Nasi Goreng, spesial");
+            full.stock = "2.5".parse().unwrap();
+            full.price = "35000.50".parse().unwrap();
+            full.sold_count = "12".parse().unwrap();
+            full.cost = Some("20000".parse().unwrap());
+            full.category = "mains".to_owned();
+            full.available = false;
+            full.min_stock = Quantity(1_250);
+            full.expires = NaiveDate::from_ymd_opt(2024, 2, 29);
+            full.supplier = "Pak \"Budi\"".to_owned();
+            full.sku = Some("NG-01".to_owned());
+            full.allergens = ["egg", "soy"].iter().map(|a| a.to_string()).collect();
+            full.unit = "kg".to_owned();
+            full.notes = "extra pedas\nno msg".to_owned();
+            full.created_at = Some(Local.ymd(2024, 1, 2).and_hms(3, 4, 5));
+            full.updated_at = Some(Local.ymd(2024, 3, 4).and_hms(5, 6, 7));
+            full.tags = ["halal", "spicy"].iter().map(|t| t.to_string()).collect();
+            full.special_until = NaiveDate::from_ymd_opt(2024, 12, 31);
+            let foods: Foods = [food(1, "bakso"), full].into_iter().collect();
+
+            let mut store = store();
+            store.save_foods(foods.clone()).unwrap();
+            let loaded = store.load_foods().unwrap();
+            assert_eq!(loaded.sorted(), foods.sorted());
+
+            // Saving again replaces the menu rather than adding to it.
+            store.save_foods([food(2, "sate")].into_iter().collect()).unwrap();
+            assert_eq!(ids(&store), [2]);
+        }
+
+        #[test]
+        fn saving_an_existing_id_updates_its_row() {
+            let mut store = menu();
+            let mut sate = food(2, "sate ayam");
+            sate.price = "25000".parse().unwrap();
+            store.save_food(&sate).unwrap();
+            assert_eq!(ids(&store), [1, 2, 3]);
+            assert_eq!(store.load_foods().unwrap().get(2), Some(&sate));
+
+            sate.stock = "10".parse().unwrap();
+            let foods: Foods = [food(1, "bakso"), sate.clone()].into_iter().collect();
+            store.save_foods(foods).unwrap();
+            assert_eq!(ids(&store), [1, 2]);
+            assert_eq!(store.load_foods().unwrap().get(2), Some(&sate));
+        }
+
+        #[test]
+        fn a_failed_save_leaves_the_stored_menu_alone() {
+            let mut store = menu();
+            let before = store.load_foods().unwrap();
+            store
+                .conn
+                .execute_batch(
+                    "CREATE TRIGGER refuse BEFORE INSERT ON foods WHEN NEW.name = 'boom'
+                     BEGIN SELECT RAISE(ABORT, 'boom'); END",
+                )
+                .unwrap();
+
+            let foods: Foods = [food(1, "bakso urat"), food(9, "boom")].into_iter().collect();
+            assert!(store.save_foods(foods).is_err());
+            assert_eq!(store.load_foods().unwrap().sorted(), before.sorted());
+        }
+
+        #[test]
+        fn remove_foods_removes_the_given_ids() {
+            let mut store = menu();
+            let (removed, missing) = store.remove_foods(&[3, 1], false).unwrap();
+            let names: Vec<&str> = removed.iter().map(|food| food.name.as_str()).collect();
+            assert_eq!(names, ["risoles", "bakso"]);
+            assert!(missing.is_empty());
+            assert_eq!(ids(&store), [2]);
+        }
+
+        #[test]
+        fn remove_foods_with_a_missing_id_removes_nothing() {
+            let mut store = menu();
+            assert!(matches!(store.remove_foods(&[1, 9, 8], false), Err(AppError::MissingIds(ids)) if ids == [9, 8]));
+            assert_eq!(ids(&store), [1, 2, 3]);
+        }
+
+        #[test]
+        fn remove_foods_can_skip_missing_ids() {
+            let mut store = menu();
+            let (removed, missing) = store.remove_foods(&[1, 9], true).unwrap();
+            assert_eq!(removed.len(), 1);
+            assert_eq!(missing, [9]);
+            assert_eq!(ids(&store), [2, 3]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use proptest::option;
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::model::{format_price, parse_price, parse_tags, Money, MAX_AMOUNT};
+
+    fn food(id: i64, name: &str, stock: &str, price: &str) -> Food {
+        Food::new(id, name, stock.parse().unwrap(), price.parse().unwrap())
+    }
+
+    #[test]
+    fn parse_food_reads_a_valid_row() {
+        let parsed = parse_food("1,bakso,54,18000").unwrap();
+        assert_eq!(parsed.id, 1);
+        assert_eq!(parsed.name, "bakso");
+        assert_eq!(parsed.stock, "54".parse().unwrap());
+        assert_eq!(parsed.price, "18000".parse().unwrap());
+        assert_eq!(parsed.category, "uncategorized");
+        assert!(parsed.available);
+    }
+
+    #[test]
+    fn parse_food_trims_and_fills_defaults() {
+        let parsed = parse_food(" 7 , nasi goreng ,2.5,12000.50").unwrap();
+        assert_eq!(parsed.id, 7);
+        assert_eq!(parsed.name, "nasi goreng");
+        assert_eq!(parsed.stock, "2.5".parse().unwrap());
+        assert_eq!(parsed.price.cents(), 1_200_050);
+        assert_eq!(parsed.sold_count, Quantity::default());
+        assert_eq!(parsed.cost, None);
+    }
+
+    #[test]
+    fn parse_food_rejects_missing_fields() {
+        for record in ["1", "1,bakso", "1,bakso,54"] {
+            let e = parse_food(record).unwrap_err();
+            assert!(matches!(e, ParseError::Record { .. }), "{}: {}", record, e);
+        }
+    }
+
+    #[test]
+    fn parse_food_rejects_bad_numbers() {
+        assert!(matches!(parse_food("1,bakso,many,18000"), Err(ParseError::Record { .. })));
+        assert!(matches!(parse_food("1,bakso,54,free"), Err(ParseError::Record { .. })));
+    }
+
+    #[test]
+    fn parse_food_rejects_non_numeric_ids() {
+        // Without a number in front the row reads as a header, and not a valid one.
+        assert!(matches!(parse_food("one,bakso,54,18000"), Err(ParseError::SchemaMismatch(_))));
+        assert!(parse_food("1.5,bakso,54,18000").is_err());
+    }
+
+    #[test]
+    fn replaced_bytes_come_back_as_warnings() {
+        let opts = CsvOptions { lossy: true, ..CsvOptions::default() };
+        let (foods, warnings) = Foods::from_reader(&b"id,name,stock,price\n1,bak\xffso,54,18000\n"[..], &opts).unwrap();
+        assert_eq!(foods.get(1).unwrap().name, "bak\u{fffd}so");
+        assert_eq!(warnings.len(), 1);
+        assert!(matches!(warnings[0].error, ParseError::ReplacedUtf8(25)), "{:?}", warnings[0]);
+    }
+
+    #[test]
+    fn a_byte_order_mark_is_skipped() {
+        let mut file = &b"\xEF\xBB\xBFid,name,stock,price\n"[..];
+        assert_eq!(read_preamble(&mut file).unwrap(), (1, 3));
+        assert_eq!(file, b"id,name,stock,price\n");
+        let mut file = &b"\xEF\xBB\xBF# schema=2\nid,name,stock,price\n"[..];
+        assert_eq!(read_preamble(&mut file).unwrap(), (2, 14));
+
+        let (foods, warnings) = parse_foods(&b"\xEF\xBB\xBFid,name,stock,price\n1,bakso,54,18000\n"[..]).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(foods.get(1).unwrap().name, "bakso");
+    }
+
+    #[test]
+    fn invalid_utf8_is_an_error() {
+        assert!(matches!(read_preamble(&mut &b"# schema=2\xff\n"[..]), Err(ParseError::Read(_))));
+        let error = Foods::from_reader(&b"id,name,stock,price\n1,bak\xffso,54,18000\n"[..], &CsvOptions::default())
+            .unwrap_err();
+        assert!(matches!(error, ParseError::InvalidUtf8(25)), "{:?}", error);
+    }
+
+    #[test]
+    fn reordered_headers_come_back_as_warnings() {
+        let (foods, warnings) = parse_foods(&b"name,id,stock,price\nbakso,1,54,18000\n"[..]).unwrap();
+        assert_eq!(foods.get(1).unwrap().name, "bakso");
+        assert_eq!(warnings.len(), 1);
+        assert!(matches!(warnings[0].error, ParseError::SchemaMismatch(_)), "{:?}", warnings[0]);
+    }
+
+    #[test]
+    fn parse_food_of_nothing_is_an_error() {
+        assert!(matches!(parse_food(""), Err(ParseError::EmptyRecord)));
+    }
+
+    #[test]
+    fn parse_foods_skips_the_header_and_blank_lines() {
+        let text = "id,name,stock,price\n1,bakso,54,18000\n\n2,sate,63,22000\n\n";
+        let (foods, warnings) = parse_foods(text.as_bytes()).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(foods.sorted().iter().map(|food| food.name.as_str()).collect::<Vec<_>>(), ["bakso", "sate"]);
+    }
+
+    #[test]
+    fn parse_foods_reads_headerless_text() {
+        let (foods, _) = parse_foods("1,bakso,54,18000\n2,sate,63,22000\n".as_bytes()).unwrap();
+        assert_eq!(foods.inner.len(), 2);
+        assert_eq!(foods.get(2).unwrap().name, "sate");
+    }
+
+    #[test]
+    fn parse_foods_warns_about_bad_and_duplicate_records() {
+        let text = "id,name,stock,price\n1,bakso,54,18000\n2,sate,x,22000\n1,baso,3,1000\n";
+        let (foods, warnings) = parse_foods(text.as_bytes()).unwrap();
+        assert_eq!(foods.inner.len(), 1);
+        assert_eq!(foods.get(1).unwrap().name, "baso");
+
+        assert_eq!(warnings.len(), 2);
+        assert_eq!((warnings[0].line, warnings[0].raw.as_str()), (3, "2,sate,x,22000"));
+        assert!(matches!(warnings[0].error, ParseError::Record { record: 2, .. }));
+        assert_eq!((warnings[1].line, warnings[1].raw.as_str()), (2, "1,bakso,54,18000"));
+        assert!(matches!(
+            &warnings[1].error,
+            ParseError::DuplicateRecord { id: 1, first: 2, line: 4, kept } if kept == "1,baso,3,1000"
+        ));
+    }
+
+    #[test]
+    fn strict_parsing_fails_on_bad_records() {
+        let opts = CsvOptions { strict: true, ..CsvOptions::default() };
+        let text = "id,name,stock,price\n1,bakso,54,18000\n2,sate,x,22000\n";
+        match Foods::from_reader(text.as_bytes(), &opts) {
+            Err(ParseError::BadRecords(errors)) => assert_eq!(errors.len(), 1),
+            other => panic!("expected bad records, got {:?}", other.map(|(foods, _)| foods.inner.len())),
+        }
+    }
+
+    #[test]
+    fn write_foods_starts_with_the_schema_and_header() {
+        let mut foods = Foods::new();
+        foods.insert(food(2, "sate", "63", "22000"));
+        foods.insert(food(1, "bakso", "54", "18000"));
+        let mut out = Vec::new();
+        write_foods(&mut out, &foods, &CsvOptions::default()).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let mut lines = text.lines();
+        assert_eq!(lines.next(), Some(format!("{}{}", SCHEMA_PREFIX, SCHEMA_VERSION).as_str()));
+        assert_eq!(lines.next(), Some(FOOD_HEADER.join(",").as_str()));
+        assert!(lines.next().unwrap().starts_with("1,bakso,54,18000"));
+        assert!(lines.next().unwrap().starts_with("2,sate,63,22000"));
+        assert_eq!(lines.next(), None);
+    }
+
+    #[test]
+    fn json_keeps_numbers_booleans_and_lists() {
+        let mut full = food(3, "Nasi Goreng", "2.5", "4.50");
+        full.cost = Some("15000".parse().unwrap());
+        full.available = false;
+        full.allergens = ["egg", "soy"].iter().map(|a| a.to_string()).collect();
+        full.tags = ["halal", "spicy"].iter().map(|t| t.to_string()).collect();
+        let mut foods = Foods::new();
+        foods.insert(full.clone());
+
+        let json = foods_to_json(foods.sorted()).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let item = &value[0];
+        assert_eq!(item["price"], serde_json::json!(4.5));
+        assert_eq!(item["cost"], serde_json::json!(15000));
+        assert_eq!(item["stock"], serde_json::json!(2.5));
+        assert_eq!(item["available"], serde_json::json!(false));
+        assert_eq!(item["allergens"], serde_json::json!(["egg", "soy"]));
+        assert_eq!(item["tags"], serde_json::json!(["halal", "spicy"]));
+        assert_eq!(foods_from_json(&json).unwrap(), vec![full.clone()]);
+
+        let jsonl = foods_to_jsonl(foods.sorted()).unwrap();
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&jsonl).unwrap(), *item);
+        assert_eq!(foods_from_jsonl(&jsonl).unwrap(), vec![full.clone()]);
+
+        // What exports wrote before still imports.
+        let old = r#"[{"id":3,"name":"Nasi Goreng","stock":2.5,"price":"4.50","cost":"15000.00","available":0,
+            "allergens":"egg;soy","tags":"halal;spicy"}]"#;
+        assert_eq!(foods_from_json(old).unwrap(), vec![full]);
+    }
+
+    #[test]
+    fn names_with_commas_quotes_and_line_breaks_survive_save_and_load() {
+        let names = ["Fish & Chips, large", "Es \"Teh\" Manis", "Soto\nBetawi", "\"quoted\"", " spaced, \"out\" "];
+        let mut foods = Foods::new();
+        for (id, name) in (1..).zip(names) {
+            let mut item = food(id, "placeholder", "5", "32000");
+            item.name = name.to_owned();
+            assert_eq!(parse_food(&format_food(&item).unwrap()).unwrap(), item);
+            foods.insert(item);
+        }
+
+        let mut out = Vec::new();
+        write_foods(&mut out, &foods, &CsvOptions::default()).unwrap();
+        let (parsed, warnings) = parse_foods(out.as_slice()).unwrap();
+        assert!(warnings.is_empty());
+        let parsed: Vec<&str> = parsed.sorted().iter().map(|food| food.name.as_str()).collect();
+        assert_eq!(parsed, names);
+    }
+
+    #[test]
+    fn saved_foods_parse_back_the_same() {
+        let mut full = food(3, "Nasi Goreng, spesial", "2.5", "35000.50");
+        full.sold_count = "12".parse().unwrap();
+        full.cost = Some("20000".parse().unwrap());
+        full.category = "mains".to_owned();
+        full.available = false;
+        full.min_stock = "1.25".parse().unwrap();
+        full.expires = NaiveDate::from_ymd_opt(2024, 2, 29);
+        full.supplier = "Pak \"Budi\"".to_owned();
+        full.sku = Some("NG-01".to_owned());
+        full.allergens = ["egg", "soy"].iter().map(|a| a.to_string()).collect();
+        full.unit = "kg".to_owned();
+        full.notes = "extra pedas\nno msg".to_owned();
+        full.created_at = Some(Local.ymd(2024, 1, 2).and_hms(3, 4, 5));
+        full.updated_at = Some(Local.ymd(2024, 3, 4).and_hms(5, 6, 7));
+        full.tags = ["halal", "spicy"].iter().map(|t| t.to_string()).collect();
+        full.special_until = NaiveDate::from_ymd_opt(2024, 12, 31);
+        full.extra = vec![("origin".to_owned(), "Jakarta".to_owned())];
+
+        // Every record has a value in an extra column once one of them does.
+        let mut plain = food(1, "bakso", "54", "18000");
+        plain.extra = vec![("origin".to_owned(), String::new())];
+        let mut foods = Foods::new();
+        foods.insert(plain);
+        foods.insert(full);
+        let mut out = Vec::new();
+        write_foods(&mut out, &foods, &CsvOptions::default()).unwrap();
+        let (parsed, warnings) = parse_foods(out.as_slice()).unwrap();
+
+        assert!(warnings.is_empty());
+        assert_eq!(parsed.inner.len(), foods.inner.len());
+        for (before, after) in foods.sorted().into_iter().zip(parsed.sorted()) {
+            assert_eq!(before.id, after.id);
+            assert_eq!(before.name, after.name);
+            assert_eq!(before.stock, after.stock);
+            assert_eq!(before.price, after.price);
+            assert_eq!(before.sold_count, after.sold_count);
+            assert_eq!(before.cost, after.cost);
+            assert_eq!(before.category, after.category);
+            assert_eq!(before.available, after.available);
+            assert_eq!(before.min_stock, after.min_stock);
+            assert_eq!(before.expires, after.expires);
+            assert_eq!(before.supplier, after.supplier);
+            assert_eq!(before.sku, after.sku);
+            assert_eq!(before.allergens, after.allergens);
+            assert_eq!(before.unit, after.unit);
+            assert_eq!(before.notes, after.notes);
+            assert_eq!(before.created_at, after.created_at);
+            assert_eq!(before.updated_at, after.updated_at);
+            assert_eq!(before.tags, after.tags);
+            assert_eq!(before.special_until, after.special_until);
+            assert_eq!(before.extra, after.extra);
+            assert_eq!(before, after);
+        }
+    }
+
+    #[test]
+    fn round_trip_keeps_other_delimiters_and_line_endings() {
+        let opts = CsvOptions { delimiter: b';', save_delimiter: b';', crlf: true, ..CsvOptions::default() };
+        let mut foods = Foods::new();
+        foods.insert(food(1, "bakso; kuah", "54", "18000"));
+        let mut out = Vec::new();
+        write_foods(&mut out, &foods, &opts).unwrap();
+        assert!(String::from_utf8_lossy(&out).contains("\r\n"));
+        let (parsed, warnings) = Foods::from_reader(out.as_slice(), &opts).unwrap();
+        assert!(warnings.is_empty());
+        assert!(parsed.get(1) == foods.get(1));
+    }
+
+    #[test]
+    fn saved_logs_reload_without_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let when = Local.ymd(2024, 5, 6).and_hms(7, 8, 9);
+        let bakso = food(1, "bakso", "54", "18000");
+
+        let mut sales = Sales::new();
+        sales.add(Sale::new(1, &bakso, "2".parse().unwrap()));
+        sales.add(Sale::new(2, &bakso, "1".parse().unwrap()));
+        save_sales(dir.path().join("sales.csv"), sales).unwrap();
+        let text = std::fs::read_to_string(dir.path().join("sales.csv")).unwrap();
+        let (records, errors) = parse_log("sales", SALES_HEADER, numbered_lines(&text), parse_sale);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(records.len(), 2);
+
+        let mut orders = Orders::new();
+        let quantity = "3".parse().unwrap();
+        orders.add(Order { id: 1, food_id: 1, quantity, status: OrderStatus::Pending, created_at: when });
+        save_orders(dir.path().join("orders.csv"), orders).unwrap();
+        let text = std::fs::read_to_string(dir.path().join("orders.csv")).unwrap();
+        let (records, errors) = parse_log("orders", ORDERS_HEADER, numbered_lines(&text), parse_order);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(records.len(), 1);
+
+        let mut wastes = Wastes::new();
+        let reason = "dropped, \"by accident\"\non the floor".to_owned();
+        wastes.add(Waste { id: 1, food_id: 1, quantity, unit_price: 18000, timestamp: when, reason });
+        save_wastes(dir.path().join("waste.csv"), wastes).unwrap();
+        let text = std::fs::read_to_string(dir.path().join("waste.csv")).unwrap();
+        let (records, errors) = parse_log("waste", WASTE_HEADER, split_records(&text), parse_waste);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(records.len(), 1);
+
+        let mut totals = DailyTotals::new();
+        let date = when.date_naive();
+        let total = DailyTotal { date, transactions: 2, items: quantity, revenue: 54000, refunds: 0, refunded: 0 };
+        totals.add(total);
+        save_daily_totals(dir.path().join("totals.csv"), totals).unwrap();
+        let text = std::fs::read_to_string(dir.path().join("totals.csv")).unwrap();
+        let (records, errors) = parse_log("daily totals", DAILY_TOTALS_HEADER, numbered_lines(&text), parse_daily_total);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(records.len(), 1);
+    }
+
+    #[test]
+    fn logs_without_a_header_keep_their_first_record() {
+        let text = "1,1,2,18000.00,36000.00,2024-05-06T07:08:09+00:00,completed,36000.00,0.00\n";
+        let (records, errors) = parse_log("sales", SALES_HEADER, numbered_lines(text), parse_sale);
+        assert!(errors.is_empty());
+        assert_eq!(records.len(), 1);
+    }
+
+    #[test]
+    fn sale_ids_are_not_reused_after_removing_the_newest_sale() {
+        let dir = tempfile::tempdir().unwrap();
+        let counter = sale_counter_file(&dir.path().join("sales.csv"));
+        let bakso = food(1, "bakso", "54", "18000");
+        let mut sales = Sales::new();
+        let mut ids = Vec::new();
+        for _ in 0..3 {
+            let id = reserve_sale_id(&counter, &sales).unwrap();
+            sales.add(Sale::new(id, &bakso, "1".parse().unwrap()));
+            ids.push(id);
+        }
+        assert_eq!(ids, [1, 2, 3]);
+
+        sales.inner.retain(|sale| sale.id != 3);
+        assert_eq!(sales.next_id(), 3);
+        let id = reserve_sale_id(&counter, &sales).unwrap();
+        assert!(!ids.contains(&id), "{} was handed out again", id);
+        assert_eq!(id, 4);
+    }
+
+    fn csv_store(path: &Path) -> CsvStore {
+        CsvStore { path: path.to_path_buf(), opts: CsvOptions::default(), create: true, verified: Cell::new(false) }
+    }
+
+    #[test]
+    fn add_food_appends_to_a_current_data_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("food.csv");
+        let mut store = csv_store(&path);
+        store.save_foods([food(1, "bakso", "54", "18000")].into_iter().collect()).unwrap();
+        let before = std::fs::read(&path).unwrap();
+        assert!(store.is_appendable().unwrap());
+
+        store.add_food(food(2, "sate", "63", "22000")).unwrap();
+        let after = std::fs::read(&path).unwrap();
+        assert!(after.starts_with(&before));
+        let added = String::from_utf8_lossy(&after[before.len()..]).into_owned();
+        assert!(added.starts_with("2,sate,63,22000.00,"), "{}", added);
+        assert_eq!(added.lines().count(), 1);
+        assert_eq!(check_checksum(&path).unwrap(), Some(true));
+        assert_eq!(store.index().unwrap().next_id, 3);
+    }
+
+    #[test]
+    fn add_food_rewrites_an_older_data_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("food.csv");
+        std::fs::write(&path, "id,name,stock,price\n1,bakso,54,18000\n").unwrap();
+        let mut store = csv_store(&path);
+        assert!(!store.is_appendable().unwrap());
+
+        store.add_food(food(2, "sate", "63", "22000")).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(text.starts_with(SCHEMA_PREFIX), "{}", text);
+        let ids: Vec<i64> = store.load_foods().unwrap().sorted().iter().map(|food| food.id).collect();
+        assert_eq!(ids, [1, 2]);
+        // Now current, so the next add can append.
+        assert!(store.is_appendable().unwrap());
+    }
+
+    #[test]
+    fn gzip_files_save_and_load_back_the_same() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("food.csv.gz");
+        let foods: Foods = [food(1, "bakso", "54", "18000"), food(2, "Nasi Goreng, spesial", "2.5", "35000.50")]
+            .into_iter()
+            .collect();
+        save_foods(path.clone(), foods.clone(), &CsvOptions::default()).unwrap();
+
+        assert!(std::fs::read(&path).unwrap().starts_with(b"\x1f\x8b"));
+        let (loaded, warnings) = load_foods(path, &CsvOptions::default()).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(loaded.sorted(), foods.sorted());
+    }
+
+    #[test]
+    fn corrupt_gzip_files_are_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("food.csv.gz");
+        let mut data = Vec::new();
+        write_gzip(&mut data, &[food(1, "bakso", "54", "18000")].into_iter().collect(), &CsvOptions::default()).unwrap();
+
+        for corrupt in [&data[..data.len() / 2], b"id,name,stock,price\n1,bakso,54,18000\n"] {
+            std::fs::write(&path, corrupt).unwrap();
+            let error = load_foods(path.clone(), &CsvOptions::default()).unwrap_err();
+            assert!(error.to_string().contains("corrupt gzip stream"), "{}", error);
+        }
+    }
+
+    // A data file of `rows` generated items, made up as it is read, counting
+    // how many bytes were asked for so far.
+    struct GeneratedFile {
+        rows: usize,
+        line: Vec<u8>,
+        served: std::rc::Rc<std::cell::Cell<usize>>,
+    }
+
+    impl Read for GeneratedFile {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.line.is_empty() && self.rows > 0 {
+                self.line = format!("{},item {},{},{}.50\n", self.rows, self.rows, self.rows % 97, self.rows % 9_000).into_bytes();
+                self.rows -= 1;
+            }
+            let n = self.line.len().min(buf.len());
+            buf[..n].copy_from_slice(&self.line[..n]);
+            self.line.drain(..n);
+            self.served.set(self.served.get() + n);
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn food_records_stream_a_large_file() {
+        const ROWS: usize = 200_000;
+        let served = std::rc::Rc::new(std::cell::Cell::new(0));
+        let file = GeneratedFile { rows: ROWS, line: b"id,name,stock,price\n".to_vec(), served: served.clone() };
+        let records = FoodRecords::new(BufReader::new(file), &CsvOptions::default()).unwrap();
+        let (mut count, mut consumed) = (0, 0);
+        for food in records {
+            let food = food.unwrap();
+            count += 1;
+            consumed += format!("{},item {},{},{}.50\n", food.id, food.id, food.id % 97, food.id % 9_000).len();
+            // Only the buffers' worth is read ahead of the record handed out.
+            assert!(served.get() <= consumed + 64 * 1024, "{} bytes read for {} bytes of records", served.get(), consumed);
+        }
+        assert_eq!(count, ROWS);
+    }
+
+    #[test]
+    fn crlf_files_load_like_their_lf_version() {
+        let (lf, warnings) = parse_foods(&include_bytes!("../tests/fixtures/food-lf.csv")[..]).unwrap();
+        assert!(warnings.is_empty());
+        let (crlf, warnings) = parse_foods(&include_bytes!("../tests/fixtures/food-crlf.csv")[..]).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(crlf.sorted(), lf.sorted());
+
+        let tea = &crlf.inner[&2];
+        assert_eq!((tea.name.as_str(), tea.price, tea.notes.as_str()), ("Es Teh", Money(450), "served cold"));
+        assert_eq!(crlf.inner[&3].notes, "  spicy, \"hot\"  ");
+    }
+
+    #[test]
+    fn spaces_inside_quotes_survive_save_and_load() {
+        let mut foods = Foods::new();
+        let mut food = Food::new(1, " Fish & Chips, large ", Quantity(5), Money(32000));
+        food.notes = "  spicy, \"hot\"  ".to_owned();
+        food.category = "\tmains".to_owned();
+        foods.insert(food);
+        for opts in [CsvOptions::default(), CsvOptions { crlf: true, ..CsvOptions::default() }] {
+            let mut out = Vec::new();
+            write_foods(&mut out, &foods, &opts).unwrap();
+            let (parsed, _) = parse_foods(out.as_slice()).unwrap();
+            assert_eq!(parsed.sorted(), foods.sorted(), "{}", String::from_utf8_lossy(&out));
+        }
+    }
+
+    #[test]
+    fn a_failed_save_leaves_the_file_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("food.csv");
+        std::fs::write(&path, "id,name,stock,price\n1,bakso,54,18000\n").unwrap();
+
+        let result = save_atomically(&path, |file| {
+            file.write_all(b"half a file")?;
+            Err(std::io::Error::other("boom"))
+        });
+        assert_eq!(result.unwrap_err().to_string(), "boom");
+        assert_eq!(std::fs::read(&path).unwrap(), b"id,name,stock,price\n1,bakso,54,18000\n");
+        assert!(!dir.path().join("food.csv.tmp").exists());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    // Any text, and often some with spaces, tabs or quotes at either end.
+    fn text() -> impl Strategy<Value = String> {
+        let padding = || "[ \t\"]{0,3}";
+        prop_oneof![any::<String>(), (padding(), any::<String>(), padding()).prop_map(|(a, text, b)| a + &text + &b)]
+    }
+
+    fn tags() -> impl Strategy<Value = BTreeSet<String>> {
+        prop::collection::vec(any::<String>(), 0..4).prop_map(|tags| parse_tags(&tags.join(";")))
+    }
+
+    fn date() -> impl Strategy<Value = NaiveDate> {
+        (1..=9999i32, 1..=366u32).prop_map(|(year, day)| {
+            NaiveDate::from_yo_opt(year, day).unwrap_or_else(|| NaiveDate::from_yo_opt(year, 365).unwrap())
+        })
+    }
+
+    fn time() -> impl Strategy<Value = DateTime<Local>> {
+        (date(), 0..86_400u32, 0..1_000_000_000u32).prop_map(|(date, seconds, nanos)| {
+            let time = NaiveTime::from_num_seconds_from_midnight_opt(seconds, nanos).unwrap();
+            Local.from_utc_datetime(&date.and_time(time))
+        })
+    }
+
+    // Quantities and prices the parsers accept, the edges included.
+    fn amount() -> impl Strategy<Value = i64> {
+        prop_oneof![Just(MAX_AMOUNT - 1), Just(1 - MAX_AMOUNT), Just(0), 1 - MAX_AMOUNT..MAX_AMOUNT]
+    }
+
+    fn too_big() -> impl Strategy<Value = i64> {
+        prop_oneof![Just(i64::MIN), Just(i64::MAX), Just(MAX_AMOUNT), MAX_AMOUNT..=i64::MAX, i64::MIN..=-MAX_AMOUNT]
+    }
+
+    // Any item as the program keeps it, every field already in the form
+    // reading it back gives. Ids can be anything, negative ones included.
+    fn any_food() -> impl Strategy<Value = Food> {
+        let id = prop_oneof![Just(i64::MIN), Just(i64::MAX), any::<i64>()];
+        let numbers = (id, amount(), amount(), amount(), option::of(amount()), amount());
+        let texts = (text().prop_filter("names are required", |name| !name.is_empty()), text(), text(), text(), text(), text());
+        let rest = (any::<bool>(), tags(), tags(), option::of(date()), option::of(date()), option::of(time()), option::of(time()));
+        (numbers, texts, rest).prop_map(
+            |((id, stock, price, sold, cost, min_stock), (name, category, supplier, sku, unit, notes), rest)| {
+                let (available, allergens, tags, expires, special_until, created_at, updated_at) = rest;
+                let food = Food {
+                    sold_count: Quantity(sold),
+                    cost: cost.map(Money),
+                    category,
+                    available,
+                    min_stock: Quantity(min_stock),
+                    expires,
+                    supplier,
+                    sku: Some(sku),
+                    allergens,
+                    unit: unit.to_lowercase().trim_ascii().to_owned(),
+                    notes,
+                    created_at,
+                    updated_at,
+                    tags,
+                    special_until,
+                    ..Food::new(id, "", Quantity(stock), Money(price))
+                };
+                Food { name, ..food }.normalize().unwrap()
+            },
+        )
+    }
+
+    proptest! {
+        #[test]
+        fn any_food_survives_format_and_parse(food in any_food()) {
+            let record = format_food(&food).unwrap();
+            prop_assert_eq!(parse_food(&record).unwrap(), food, "{}", record);
+        }
+
+        #[test]
+        fn any_foods_survive_save_and_parse(foods in prop::collection::vec(any_food(), 0..8)) {
+            let mut saved = Foods::new();
+            for food in foods {
+                saved.insert(food);
+            }
+            let mut out = Vec::new();
+            write_foods(&mut out, &saved, &CsvOptions::default()).unwrap();
+            let (parsed, warnings) = parse_foods(out.as_slice()).unwrap();
+            prop_assert!(warnings.is_empty());
+            prop_assert_eq!(parsed.sorted(), saved.sorted());
+        }
+
+        #[test]
+        fn quantities_survive_display_and_parse(amount in amount()) {
+            prop_assert_eq!(Quantity(amount).to_string().parse::<Quantity>().unwrap(), Quantity(amount));
+        }
+
+        #[test]
+        fn prices_survive_format_and_parse(cents in amount()) {
+            prop_assert_eq!(parse_price(&format_price(cents)).unwrap(), cents);
+            prop_assert_eq!(parse_price(&format!("{}c", cents)).unwrap(), cents);
+        }
+
+        // Rather than coming back as some other number.
+        #[test]
+        fn amounts_out_of_range_are_rejected(amount in too_big()) {
+            let quantity = Quantity(amount).to_string().parse::<Quantity>();
+            prop_assert!(matches!(quantity, Err(ParseError::QuantityOutOfRange(_))), "{:?}", quantity);
+            let price = parse_price(&format_price(amount));
+            prop_assert!(matches!(price, Err(ParseError::PriceOutOfRange(_))), "{:?}", price);
+
+            let food = Food::new(1, "bakso", Quantity(amount), Money(0));
+            let record = format_food(&food).unwrap();
+            prop_assert!(parse_food(&record).is_err(), "{}", record);
+        }
+    }
+}
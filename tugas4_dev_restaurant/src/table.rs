@@ -0,0 +1,130 @@
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::color::{self, Color};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Align {
+    Left,
+    Right,
+}
+
+pub(crate) struct Table {
+    columns: Vec<(String, Align)>,
+    rows: Vec<(Vec<String>, Option<Color>)>,
+    shrink: Option<usize>,
+    highlight: Option<(usize, String)>,
+}
+
+const GAP: &str = "  ";
+const MIN_SHRUNK_WIDTH: usize = 6;
+
+impl Table {
+    pub(crate) fn new(columns: &[(&str, Align)]) -> Self {
+        Table {
+            columns: columns.iter().map(|(name, align)| (name.to_string(), *align)).collect(),
+            rows: Vec::new(),
+            shrink: None,
+            highlight: None,
+        }
+    }
+
+    // The column that gets truncated when the table is wider than the terminal.
+    pub(crate) fn shrink(mut self, column: usize) -> Self {
+        self.shrink = Some(column);
+        self
+    }
+
+    pub(crate) fn highlight(mut self, column: usize, needle: &str) -> Self {
+        self.highlight = Some((column, needle.to_owned()));
+        self
+    }
+
+    pub(crate) fn push_colored(&mut self, row: Vec<String>, color: Option<Color>) {
+        self.rows.push((row, color));
+    }
+
+    pub(crate) fn render(&self, max_width: Option<usize>) -> String {
+        let mut widths: Vec<usize> = self.columns.iter().map(|(name, _)| name.width()).collect();
+        for (row, _) in &self.rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.width());
+            }
+        }
+        let total = widths.iter().sum::<usize>() + GAP.len() * widths.len().saturating_sub(1);
+        if let (Some(max_width), Some(column)) = (max_width, self.shrink) {
+            if total > max_width {
+                widths[column] = widths[column].saturating_sub(total - max_width).max(MIN_SHRUNK_WIDTH);
+            }
+        }
+
+        let mut out = String::new();
+        let header: Vec<&str> = self.columns.iter().map(|(name, _)| name.as_str()).collect();
+        self.render_row(&mut out, &header, &widths, None, None);
+        for (row, color) in &self.rows {
+            let row: Vec<&str> = row.iter().map(String::as_str).collect();
+            self.render_row(&mut out, &row, &widths, *color, self.highlight.as_ref());
+        }
+        out
+    }
+
+    fn render_row(
+        &self,
+        out: &mut String,
+        row: &[&str],
+        widths: &[usize],
+        color: Option<Color>,
+        highlight: Option<&(usize, String)>,
+    ) {
+        let mut line = String::new();
+        for (i, ((_, align), width)) in self.columns.iter().zip(widths).enumerate() {
+            let cell = truncate(row.get(i).copied().unwrap_or_default(), *width);
+            let padding = " ".repeat(width - cell.width());
+            let cell = match highlight {
+                Some((column, needle)) if *column == i => color::highlight(&cell, needle, color),
+                _ => cell,
+            };
+            if i > 0 {
+                line.push_str(GAP);
+            }
+            match align {
+                Align::Left => {
+                    line.push_str(&cell);
+                    line.push_str(&padding);
+                }
+                Align::Right => {
+                    line.push_str(&padding);
+                    line.push_str(&cell);
+                }
+            }
+        }
+        out.push_str(&color::paint(line.trim_end(), color));
+        out.push('\n');
+    }
+}
+
+// Pads by display width, which format! does not know about.
+pub(crate) fn pad(cell: &str, width: usize, align: Align) -> String {
+    let padding = " ".repeat(width.saturating_sub(cell.width()));
+    match align {
+        Align::Left => format!("{}{}", cell, padding),
+        Align::Right => format!("{}{}", padding, cell),
+    }
+}
+
+pub(crate) fn truncate(cell: &str, width: usize) -> String {
+    if cell.width() <= width {
+        return cell.to_owned();
+    }
+    let mut out = String::new();
+    let mut used = 0;
+    for c in cell.chars() {
+        let w = c.width().unwrap_or(0);
+        if used + w + 1 > width {
+            break;
+        }
+        out.push(c);
+        used += w;
+    }
+    out.push('…');
+    out
+}
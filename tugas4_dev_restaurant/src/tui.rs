@@ -0,0 +1,187 @@
+use chrono::Local;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::widgets::{Cell, Paragraph, Row, Table, TableState};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::error::AppError;
+use crate::model::{Food, Foods, Money, Quantity, QUANTITY_SCALE};
+use crate::{color, output};
+
+enum Mode {
+    Browse,
+    Filter,
+    EditPrice(String),
+    ConfirmQuit,
+}
+
+struct App {
+    foods: Foods,
+    // ids of the items matching the filter, in id order
+    shown: Vec<i64>,
+    filter: String,
+    state: TableState,
+    mode: Mode,
+    dirty: bool,
+    message: String,
+}
+
+// Returns the edited menu when it should be saved.
+pub(crate) fn run(foods: Foods) -> Result<Option<Foods>, AppError> {
+    let mut app = App {
+        foods,
+        shown: Vec::new(),
+        filter: String::new(),
+        state: TableState::default().with_selected(0),
+        mode: Mode::Browse,
+        dirty: false,
+        message: String::new(),
+    };
+    app.refilter();
+    let mut terminal = ratatui::init();
+    let save = app.run(&mut terminal);
+    ratatui::restore();
+    Ok(save?.then_some(app.foods))
+}
+
+impl App {
+    // Returns whether the changes should be saved.
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<bool, AppError> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let key = match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => key.code,
+                _ => continue,
+            };
+            self.message.clear();
+            match (&mut self.mode, key) {
+                (Mode::Browse, KeyCode::Char('q') | KeyCode::Esc) if self.dirty => self.mode = Mode::ConfirmQuit,
+                (Mode::Browse, KeyCode::Char('q') | KeyCode::Esc) => return Ok(false),
+                (Mode::Browse, KeyCode::Down | KeyCode::Char('j')) => self.state.select_next(),
+                (Mode::Browse, KeyCode::Up | KeyCode::Char('k')) => self.state.select_previous(),
+                (Mode::Browse, KeyCode::PageDown) => self.state.scroll_down_by(terminal.size()?.height / 2),
+                (Mode::Browse, KeyCode::PageUp) => self.state.scroll_up_by(terminal.size()?.height / 2),
+                (Mode::Browse, KeyCode::Home) => self.state.select_first(),
+                (Mode::Browse, KeyCode::End) => self.state.select_last(),
+                (Mode::Browse, KeyCode::Char('+')) => self.bump_stock(1),
+                (Mode::Browse, KeyCode::Char('-')) => self.bump_stock(-1),
+                (Mode::Browse, KeyCode::Char('/')) => self.mode = Mode::Filter,
+                (Mode::Browse, KeyCode::Char('e')) => {
+                    if let Some(food) = self.selected() {
+                        self.mode = Mode::EditPrice(food.price.to_string());
+                    }
+                }
+                (Mode::Filter, KeyCode::Enter) => self.mode = Mode::Browse,
+                (Mode::Filter, KeyCode::Esc) => {
+                    self.filter.clear();
+                    self.refilter();
+                    self.mode = Mode::Browse;
+                }
+                (Mode::Filter, KeyCode::Backspace) => {
+                    self.filter.pop();
+                    self.refilter();
+                }
+                (Mode::Filter, KeyCode::Char(c)) => {
+                    self.filter.push(c);
+                    self.refilter();
+                }
+                (Mode::EditPrice(_), KeyCode::Esc) => self.mode = Mode::Browse,
+                (Mode::EditPrice(price), KeyCode::Backspace) => {
+                    price.pop();
+                }
+                (Mode::EditPrice(price), KeyCode::Char(c)) => price.push(c),
+                (Mode::EditPrice(price), KeyCode::Enter) => match price.parse::<Money>() {
+                    Ok(price) => {
+                        self.update(|food| food.price = price);
+                        self.mode = Mode::Browse;
+                    }
+                    Err(e) => self.message = e.to_string(),
+                },
+                (Mode::ConfirmQuit, KeyCode::Char('y')) => return Ok(true),
+                (Mode::ConfirmQuit, KeyCode::Char('n')) => return Ok(false),
+                (Mode::ConfirmQuit, KeyCode::Esc | KeyCode::Char('c')) => self.mode = Mode::Browse,
+                _ => {}
+            }
+        }
+    }
+
+    fn refilter(&mut self) {
+        let filter = self.filter.to_lowercase();
+        self.shown = self
+            .foods
+            .sorted()
+            .into_iter()
+            .filter(|food| food.name.to_lowercase().contains(&filter))
+            .map(|food| food.id)
+            .collect();
+        let last = self.shown.len().saturating_sub(1);
+        self.state.select(Some(self.state.selected().unwrap_or(0).min(last)));
+    }
+
+    fn selected(&self) -> Option<&Food> {
+        let id = self.shown.get(self.state.selected()?)?;
+        self.foods.inner.get(id)
+    }
+
+    fn update(&mut self, change: impl FnOnce(&mut Food)) {
+        let id = match self.state.selected().and_then(|row| self.shown.get(row)) {
+            Some(id) => *id,
+            None => return,
+        };
+        if let Some(food) = self.foods.inner.get_mut(&id) {
+            change(food);
+            food.updated_at = Some(Local::now());
+            self.dirty = true;
+        }
+    }
+
+    fn bump_stock(&mut self, by: i64) {
+        self.update(|food| food.stock = Quantity((food.stock.0 + by * QUANTITY_SCALE).max(0)));
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [table_area, status_area] = Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+
+        let rows = self.shown.iter().filter_map(|id| self.foods.inner.get(id)).map(|food| {
+            let style = match output::stock_color(food) {
+                Some(color::Color::Red) => Style::new().fg(Color::Red),
+                Some(color::Color::Yellow) => Style::new().fg(Color::Yellow),
+                None => Style::new(),
+            };
+            Row::new([
+                Cell::from(food.id.to_string()),
+                Cell::from(food.name.clone()),
+                Cell::from(food.category.clone()),
+                Cell::from(format!("{} {}", food.stock, food.unit)),
+                Cell::from(food.price.to_string()),
+            ])
+            .style(style)
+        });
+        let widths = [
+            Constraint::Length(6),
+            Constraint::Fill(1),
+            Constraint::Length(16),
+            Constraint::Length(12),
+            Constraint::Length(12),
+        ];
+        let table = Table::new(rows, widths)
+            .header(Row::new(["Id", "Name", "Category", "Stock", "Price"]).style(Style::new().add_modifier(Modifier::BOLD)))
+            .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(table, table_area, &mut self.state);
+
+        let status = match &self.mode {
+            _ if !self.message.is_empty() => self.message.clone(),
+            Mode::Browse => format!(
+                "{} of {} items{}  ↑/↓ move  +/- stock  e price  / filter  q quit",
+                self.shown.len(),
+                self.foods.inner.len(),
+                if self.dirty { " (changed)" } else { "" }
+            ),
+            Mode::Filter => format!("/{}", self.filter),
+            Mode::EditPrice(price) => format!("price: {}", price),
+            Mode::ConfirmQuit => "save changes? y yes, n discard, c cancel".to_owned(),
+        };
+        frame.render_widget(Paragraph::new(status), status_area);
+    }
+}
@@ -0,0 +1,517 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+use tempfile::TempDir;
+
+const MENU: &str = "id,name,stock,price\n1,bakso,54,18000\n2,sate,63,22000\n3,risoles,44,3500\n";
+
+// The binary run inside dir, with no config or environment of the machine
+// running the tests leaking in.
+fn restaurant(dir: &TempDir) -> Command {
+    let mut cmd = Command::cargo_bin("tugas4_dev_restaurant").unwrap();
+    cmd.current_dir(dir.path())
+        .env("HOME", dir.path())
+        .env("XDG_CONFIG_HOME", dir.path().join("config"))
+        .env_remove("COLUMNS");
+    for var in tugas4_dev_restaurant::cli::ENV_OVERRIDES {
+        cmd.env_remove(var);
+    }
+    cmd
+}
+
+fn with_menu(text: &str) -> (TempDir, PathBuf) {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("food.csv");
+    fs::write(&path, text).unwrap();
+    (dir, path)
+}
+
+// The item rows of a data file, without the schema line and header.
+fn rows(path: &Path) -> Vec<String> {
+    let text = fs::read_to_string(path).unwrap();
+    text.lines().filter(|line| !line.starts_with('#') && !line.starts_with("id,")).map(str::to_owned).collect()
+}
+
+#[test]
+fn add_creates_a_missing_data_file() {
+    let dir = TempDir::new().unwrap();
+    restaurant(&dir).args(["add", "bakso", "54", "18000"]).assert().success().stdout("").stderr("");
+
+    let path = dir.path().join("food.csv");
+    let text = fs::read_to_string(&path).unwrap();
+    assert!(text.starts_with("# schema="), "{}", text);
+    let rows = rows(&path);
+    assert_eq!(rows.len(), 1);
+    assert!(rows[0].starts_with("1,bakso,54,18000.00,"), "{}", rows[0]);
+}
+
+#[test]
+fn add_creates_missing_directories() {
+    let dir = TempDir::new().unwrap();
+    restaurant(&dir).args(["-d", "shop/menu/food.csv", "add", "sate", "1", "2"]).assert().success();
+    assert_eq!(rows(&dir.path().join("shop/menu/food.csv")).len(), 1);
+}
+
+#[test]
+fn add_gives_the_next_id() {
+    let (dir, path) = with_menu(MENU);
+    restaurant(&dir).args(["add", "soto", "5", "9000"]).assert().success();
+    assert!(rows(&path)[3].starts_with("4,soto,5,9000.00,"));
+}
+
+#[test]
+fn add_rejects_a_bad_stock() {
+    let (dir, path) = with_menu(MENU);
+    restaurant(&dir)
+        .args(["add", "soto", "many", "9000"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("invalid quantity \"many\""));
+    assert_eq!(fs::read_to_string(path).unwrap(), MENU);
+}
+
+#[test]
+fn list_without_a_data_file_fails() {
+    let dir = TempDir::new().unwrap();
+    restaurant(&dir)
+        .arg("list")
+        .assert()
+        .code(4)
+        .stdout("")
+        .stderr(predicate::str::contains("no data file yet at food.csv"));
+    assert!(!dir.path().join("food.csv").exists());
+}
+
+#[test]
+fn list_of_an_empty_file() {
+    for text in ["", "id,name,stock,price\n"] {
+        let (dir, _) = with_menu(text);
+        restaurant(&dir).arg("list").assert().success().stdout("No items yet\n").stderr("");
+    }
+}
+
+#[test]
+fn list_with_nothing_matching() {
+    let (dir, _) = with_menu(MENU);
+    restaurant(&dir).args(["list", "--category", "drinks"]).assert().success().stdout("No items match\n");
+}
+
+#[test]
+fn list_shows_every_item() {
+    let (dir, _) = with_menu(MENU);
+    restaurant(&dir)
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("Id  Name"))
+        .stdout(predicate::str::contains("bakso"))
+        .stdout(predicate::str::contains("sate"))
+        .stdout(predicate::str::contains("risoles"));
+}
+
+#[test]
+fn list_as_a_table_matches_the_snapshot() {
+    let (dir, _) = with_menu(MENU);
+    restaurant(&dir).arg("list").assert().success().stdout(concat!(
+        "Id  Name     Category        Stock     Price  Status\n",
+        " 1  bakso    uncategorized  54 pcs  18000.00\n",
+        " 2  sate     uncategorized  63 pcs  22000.00\n",
+        " 3  risoles  uncategorized  44 pcs   3500.00\n",
+    ));
+}
+
+const CSV_HEADER: &str = "id,name,stock,price,sold_count,cost,category,available,min_stock,expires,\
+                          supplier,sku,allergens,unit,notes,created_at,updated_at,tags,special_until\n";
+
+#[test]
+fn list_as_csv_matches_the_snapshot() {
+    let (dir, _) = with_menu(MENU);
+    let expected = format!(
+        "# schema=2\n{}{}{}{}",
+        CSV_HEADER,
+        "1,bakso,54,18000.00,0,,uncategorized,1,0,,,,,pcs,,,,,\n",
+        "2,sate,63,22000.00,0,,uncategorized,1,0,,,,,pcs,,,,,\n",
+        "3,risoles,44,3500.00,0,,uncategorized,1,0,,,,,pcs,,,,,\n"
+    );
+    restaurant(&dir).args(["--format", "csv", "list"]).assert().success().stdout(expected).stderr("");
+}
+
+#[test]
+fn list_as_json_matches_the_snapshot() {
+    let (dir, _) = with_menu("id,name,stock,price\n1,bakso,54,18000\n");
+    restaurant(&dir)
+        .args(["--format", "json", "list"])
+        .assert()
+        .success()
+        .stdout(include_str!("snapshots/list.json"))
+        .stderr("");
+    restaurant(&dir).args(["--format", "jsonl", "list"]).assert().success().stdout(concat!(
+        r#"{"id":1,"name":"bakso","stock":54,"price":18000,"sold_count":0,"cost":null,"#,
+        r#""category":"uncategorized","available":true,"min_stock":0,"expires":null,"supplier":"","#,
+        r#""sku":null,"allergens":[],"unit":"pcs","notes":"","created_at":null,"updated_at":null,"#,
+        r#""tags":[],"special_until":null}"#,
+        "\n"
+    ));
+}
+
+#[test]
+fn search_as_data_keeps_its_footer_off_stdout() {
+    let (dir, _) = with_menu(MENU);
+    restaurant(&dir)
+        .args(["--format", "csv", "search", "bak"])
+        .assert()
+        .success()
+        .stdout(format!("# schema=2\n{}1,bakso,54,18000.00,0,,uncategorized,1,0,,,,,pcs,,,,,\n", CSV_HEADER))
+        .stderr("1 of 3 items matched\n");
+}
+
+#[test]
+fn list_as_csv_with_an_empty_file_prints_only_the_header() {
+    let (dir, _) = with_menu("");
+    restaurant(&dir)
+        .args(["--format", "csv", "list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("id,name,stock,price").and(predicate::str::contains("No items").not()));
+}
+
+#[test]
+fn edit_changes_the_file() {
+    let (dir, path) = with_menu(MENU);
+    restaurant(&dir).args(["edit", "2", "--name", "Sate Ayam", "--price", "25000"]).assert().success();
+    let rows = rows(&path);
+    assert!(rows[1].starts_with("2,Sate Ayam,63,25000.00,"), "{}", rows[1]);
+    assert!(rows[0].starts_with("1,bakso,54,18000.00,"));
+}
+
+#[test]
+fn edit_of_a_missing_id() {
+    let (dir, path) = with_menu(MENU);
+    restaurant(&dir)
+        .args(["edit", "9", "--price", "1000"])
+        .assert()
+        .code(3)
+        .stderr("An error occurred: no item with id 9\n");
+    assert_eq!(fs::read_to_string(path).unwrap(), MENU);
+}
+
+#[test]
+fn remove_an_item() {
+    let (dir, path) = with_menu(MENU);
+    restaurant(&dir).args(["remove", "2"]).assert().success().stdout("removed sate (id 2)\n");
+    let rows = rows(&path);
+    assert_eq!(rows.len(), 2);
+    assert!(rows.iter().all(|row| !row.contains("sate")));
+}
+
+#[test]
+fn remove_of_a_missing_id() {
+    let (dir, path) = with_menu(MENU);
+    restaurant(&dir)
+        .args(["remove", "9"])
+        .assert()
+        .code(3)
+        .stdout("")
+        .stderr("An error occurred: no item with id 9\n");
+    assert_eq!(fs::read_to_string(path).unwrap(), MENU);
+}
+
+#[test]
+fn remove_with_one_id_missing_removes_nothing() {
+    let (dir, path) = with_menu(MENU);
+    restaurant(&dir)
+        .args(["remove", "1", "9"])
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("nothing was removed"));
+    assert_eq!(fs::read_to_string(&path).unwrap(), MENU);
+
+    restaurant(&dir).args(["remove", "--missing-ok", "1", "9"]).assert().success();
+    assert_eq!(rows(&path).len(), 2);
+}
+
+#[test]
+fn remove_without_a_data_file() {
+    let dir = TempDir::new().unwrap();
+    restaurant(&dir).args(["remove", "1"]).assert().code(3).stderr(predicate::str::contains("no item with id 1"));
+    assert!(!dir.path().join("food.csv").exists());
+}
+
+#[test]
+fn search_finds_names_ignoring_case() {
+    let (dir, _) = with_menu(MENU);
+    restaurant(&dir)
+        .args(["search", "BAK"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("bakso").and(predicate::str::contains("1 of 3 items matched")))
+        .stdout(predicate::str::contains("sate").not());
+}
+
+#[test]
+fn search_without_a_match_suggests_names() {
+    let (dir, _) = with_menu(MENU);
+    restaurant(&dir)
+        .args(["search", "bkaso"])
+        .assert()
+        .success()
+        .stdout("No records found among 3 items\ndid you mean: bakso (id 1)?\n");
+}
+
+#[test]
+fn search_with_a_bad_regex() {
+    let (dir, _) = with_menu(MENU);
+    restaurant(&dir).args(["search", "--regex", "("]).assert().code(2);
+}
+
+#[test]
+fn corrupt_rows_are_skipped_quietly() {
+    let (dir, _) = with_menu("id,name,stock,price\n1,bakso,54,18000\n2,sate,x,22000\n");
+    restaurant(&dir)
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("bakso").and(predicate::str::contains("sate").not()))
+        .stderr("");
+}
+
+#[test]
+fn verbose_shows_corrupt_rows() {
+    let (dir, _) = with_menu("id,name,stock,price\n1,bakso,54,18000\n2,sate,x,22000\n");
+    restaurant(&dir)
+        .args(["-v", "list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("bakso"))
+        .stderr(predicate::str::contains("Error on record 2 (line 3): invalid quantity \"x\""))
+        .stderr(predicate::str::contains(" > \"2,sate,x,22000\""));
+}
+
+#[test]
+fn strict_fails_on_corrupt_rows() {
+    let (dir, _) = with_menu("id,name,stock,price\n1,bakso,54,18000\n2,sate,x,22000\n");
+    restaurant(&dir)
+        .args(["--strict", "list"])
+        .assert()
+        .code(4)
+        .stdout("")
+        .stderr(predicate::str::contains("1 bad record(s) in the data file"));
+}
+
+#[test]
+fn duplicate_ids_always_warn() {
+    let (dir, _) = with_menu("id,name,stock,price\n1,bakso,54,18000\n1,baso,3,1000\n");
+    restaurant(&dir)
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("baso").and(predicate::str::contains("bakso").not()))
+        .stderr(concat!(
+            "warning: duplicate id 1 on lines 2 and 3, keeping line 3\n",
+            "  kept line 3: \"1,baso,3,1000\"\n",
+            "  dropped line 2: \"1,bakso,54,18000\"\n"
+        ));
+}
+
+#[test]
+fn errors_as_json() {
+    let (dir, _) = with_menu(MENU);
+    restaurant(&dir)
+        .args(["--format", "json", "remove", "9"])
+        .assert()
+        .code(3)
+        .stderr("{\"error\":\"no item with id 9\"}\n");
+}
+
+#[test]
+fn unknown_commands_are_usage_errors() {
+    let dir = TempDir::new().unwrap();
+    restaurant(&dir).arg("frobnicate").assert().code(2).stdout("");
+}
+
+#[test]
+fn bad_values_in_the_arguments_are_usage_errors() {
+    let (dir, path) = with_menu(MENU);
+    for args in [&["order", "3:x"][..], &["report", "--from", "bad"], &["close-day", "--date", "yesterday"], &["sell", "one", "1"]] {
+        restaurant(&dir).args(args).assert().code(2).stderr(predicate::str::starts_with("An error occurred: "));
+    }
+    assert_eq!(fs::read_to_string(path).unwrap(), MENU);
+}
+
+#[test]
+fn a_bad_log_line_is_still_a_data_file_error() {
+    let (dir, _) = with_menu(MENU);
+    fs::write(dir.path().join("sales.csv.seq"), "many\n").unwrap();
+    restaurant(&dir).args(["sell", "1", "1"]).assert().code(4);
+}
+
+#[test]
+fn help_goes_to_stdout() {
+    let dir = TempDir::new().unwrap();
+    restaurant(&dir).arg("--help").assert().success().stdout(predicate::str::contains("USAGE")).stderr("");
+}
+
+// After changing a flag or command, regenerate it with COLUMNS and the
+// RESTAURANT_* variables unset, `tugas4_dev_restaurant --help > tests/snapshots/help.txt`,
+// and check the diff reads right.
+#[test]
+fn help_matches_the_snapshot() {
+    let dir = TempDir::new().unwrap();
+    restaurant(&dir).arg("--help").assert().success().stdout(include_str!("snapshots/help.txt"));
+}
+
+#[test]
+fn reprice_all_includes_disabled_items() {
+    let (dir, path) = with_menu(MENU);
+    restaurant(&dir).args(["disable", "2"]).assert().success();
+    restaurant(&dir).args(["reprice", "--percent", "10", "--all"]).assert().success();
+    let rows = rows(&path);
+    assert!(rows[0].starts_with("1,bakso,54,19800.00,"), "{}", rows[0]);
+    assert!(rows[1].starts_with("2,sate,63,24200.00,"), "{}", rows[1]);
+    assert!(rows[2].starts_with("3,risoles,44,3850.00,"), "{}", rows[2]);
+}
+
+#[test]
+fn reprice_by_name_only_changes_matching_items() {
+    let (dir, path) = with_menu(MENU);
+    restaurant(&dir).args(["reprice", "--percent", "10", "--name-contains", "SAT", "--yes"]).assert().success();
+    let rows = rows(&path);
+    assert!(rows[0].starts_with("1,bakso,54,18000.00,"), "{}", rows[0]);
+    assert!(rows[1].starts_with("2,sate,63,24200.00,"), "{}", rows[1]);
+    assert!(rows[2].starts_with("3,risoles,44,3500.00,"), "{}", rows[2]);
+}
+
+#[test]
+fn reprice_needs_a_selection() {
+    let (dir, path) = with_menu(MENU);
+    restaurant(&dir).args(["reprice", "--percent", "10"]).assert().code(2);
+    restaurant(&dir).args(["reprice", "--percent", "10", "--tag", "spicy", "--all"]).assert().code(2);
+    restaurant(&dir).args(["reprice", "--percent", "10", "--name-contains", "bak", "--all"]).assert().code(2);
+    assert_eq!(fs::read_to_string(path).unwrap(), MENU);
+}
+
+#[test]
+fn the_data_file_can_come_from_the_environment() {
+    let (dir, _) = with_menu("");
+    fs::write(dir.path().join("other.csv"), MENU).unwrap();
+    restaurant(&dir)
+        .env("RESTAURANT_DATA_FILE", "other.csv")
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("bakso"));
+    restaurant(&dir).env("RESTAURANT_DATA_FILE", "other.csv").args(["-d", "food.csv", "list"]).assert().stdout("No items yet\n");
+}
+
+#[test]
+fn lookup_prints_the_same_details_as_show() {
+    let (dir, _) = with_menu("id,name,stock,price,sku\n1,bakso,54,18000,BK-1\n2,sate,63,22000,ST-1\n");
+    let show = restaurant(&dir).args(["show", "2"]).assert().success().get_output().stdout.clone();
+    let show = String::from_utf8(show).unwrap();
+    assert!(show.starts_with("#2 sate\n") && show.contains("  SKU          ST-1\n"), "{}", show);
+    restaurant(&dir).args(["lookup", "ST-1"]).assert().success().stdout(show);
+    restaurant(&dir).args(["lookup", "XX-9"]).assert().code(3);
+}
+
+#[test]
+fn commands_that_save_files_are_writers() {
+    let (dir, _) = with_menu(MENU);
+    restaurant(&dir).args(["-d", "-", "report", "--out", "-"]).write_stdin(MENU).assert().success();
+    for args in [&["report", "--out", "report.txt"][..], &["config", "init", "restaurant.toml"], &["--accept-external-changes", "verify"]] {
+        restaurant(&dir)
+            .args(["-d", "-"])
+            .args(args)
+            .write_stdin(MENU)
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains("only works with commands that write no files"));
+    }
+    assert!(!dir.path().join("report.txt").exists());
+    assert!(!dir.path().join("restaurant.toml").exists());
+}
+
+#[test]
+fn close_day_adds_up_to_the_net_sales() {
+    let (dir, _) = with_menu(MENU);
+    restaurant(&dir).args(["sell", "1", "2", "--discount-percent", "10"]).assert().success();
+    restaurant(&dir).args(["sell", "2", "1"]).assert().success();
+    restaurant(&dir).args(["refund", "2"]).assert().success();
+    restaurant(&dir).arg("close-day").assert().success().stdout(predicate::str::contains(concat!(
+        "Gross sales                58000.00\n",
+        "Discounts given            -3600.00\n",
+        "Refunds                           1\n",
+        "Refunded amount           -22000.00\n",
+        "Net sales                  32400.00\n",
+    )));
+}
+
+#[test]
+fn migrate_upgrades_an_old_data_file() {
+    let (dir, path) = with_menu(MENU);
+    restaurant(&dir)
+        .args(["--dry-run", "migrate"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("schema 1 -> 2").and(predicate::str::contains("adds columns: sold_count")));
+    assert_eq!(fs::read_to_string(&path).unwrap(), MENU);
+
+    restaurant(&dir).arg("migrate").assert().success().stdout(predicate::str::contains("Migrated 3 item(s) to schema 2"));
+    let text = fs::read_to_string(&path).unwrap();
+    assert!(text.starts_with(&format!("# schema=2\n{}1,bakso,54,18000.00,", CSV_HEADER)), "{}", text);
+    assert_eq!(rows(&path).len(), 3);
+    restaurant(&dir).arg("migrate").assert().success().stdout(predicate::str::contains("is already at schema 2"));
+}
+
+#[test]
+fn a_newer_schema_is_refused() {
+    let menu = format!("# schema=3\n{}", MENU);
+    let (dir, path) = with_menu(&menu);
+    restaurant(&dir)
+        .arg("list")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("data file uses schema 3").and(predicate::str::contains("upgrade the program")));
+    restaurant(&dir)
+        .args(["add", "soto", "5", "9000"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("data file uses schema 3"));
+    assert_eq!(fs::read_to_string(&path).unwrap(), menu);
+}
+
+// The data and sales files as they are on disk.
+fn files(dir: &TempDir) -> (Vec<u8>, Vec<u8>) {
+    (fs::read(dir.path().join("food.csv")).unwrap(), fs::read(dir.path().join("sales.csv")).unwrap())
+}
+
+#[test]
+fn a_failed_order_changes_neither_file() {
+    let (dir, _) = with_menu(MENU);
+    restaurant(&dir).args(["sell", "1", "2"]).assert().success();
+    let before = files(&dir);
+
+    // risoles has 44 in stock, so none of the lines are sold.
+    restaurant(&dir)
+        .args(["order", "1:2", "2:1", "3:45"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("risoles"));
+    restaurant(&dir).args(["order", "1:2", "9:1"]).assert().failure();
+    restaurant(&dir).args(["order", "1:2", "2:x"]).assert().failure();
+    restaurant(&dir).args(["sell", "3", "45"]).assert().failure();
+    assert_eq!(files(&dir), before);
+}
+
+#[test]
+fn a_failed_refund_changes_neither_file() {
+    let (dir, _) = with_menu(MENU);
+    restaurant(&dir).args(["sell", "1", "2"]).assert().success();
+    restaurant(&dir).args(["refund", "1"]).assert().success();
+    let before = files(&dir);
+
+    restaurant(&dir).args(["refund", "1"]).assert().failure().stderr(predicate::str::contains("sale 1 has already been refunded"));
+    restaurant(&dir).args(["refund", "9"]).assert().failure();
+    assert_eq!(files(&dir), before);
+}